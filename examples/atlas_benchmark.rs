@@ -0,0 +1,69 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::{fs::File, io::BufReader, time::Instant};
+
+use image::{ImageReader, ImageBuffer, Rgba};
+
+use shc_tiles::prelude::*;
+
+/**
+ * Times mip generation and page assembly of the 4096 tile atlas from the `atlas_builder`
+ * example, comparing the parallel paths against serial references.
+ */
+fn main() {
+    let mut img = load_image("assets/tile_wall.png");
+    let size = img.width();
+
+    // // Create Atlas // //
+    let mut atlas = TileAtlasBuilder::new(size);
+    for i in 0..4096 {
+        atlas.insert_single("base", &format!("image{i}"), 0, &img, size, [0_u32, 0_u32]);
+        for (idx, data) in img.iter_mut().enumerate() {
+            if (idx + 1) % 4 == 0 { continue; }
+            *data = data.wrapping_add(idx as u8);
+        }
+    }
+
+    // // Downsample // //
+    let mut serial = atlas.clone();
+    let start = Instant::now();
+    let tiles = serial.tiles().map(|(g, t, _)| (g.to_owned(), t.to_owned())).collect::<Vec<_>>();
+    for (group_id, tile_id) in &tiles {
+        serial.downsample_levels_for(group_id, tile_id, 0..u32::MAX, false, DownsampleBilinearSRGB);
+    }
+    println!("downsample serial:   {:?}", start.elapsed());
+
+    let start = Instant::now();
+    atlas.downsample_levels(0..u32::MAX, false, DownsampleBilinearSRGB);
+    println!("downsample parallel: {:?}", start.elapsed());
+
+    for (group_id, tile_id, set) in atlas.tiles() {
+        let reference = serial.get_tile(group_id, tile_id).unwrap();
+        assert!(set.levels.iter().zip(&reference.levels).all(|(a, b)| a.data == b.data), "Mismatched mips for {group_id}/{tile_id}");
+    }
+
+    // // Build Image // //
+    let start  = Instant::now();
+    let layout = atlas.build_layout();
+    let mut reference = Vec::new();
+    for page in 0..layout.page_count() {
+        for level in 0..atlas.find_mip_level_common_max() {
+            atlas.build_page_with_layout(&layout, &mut reference, level, page);
+        }
+    }
+    println!("build serial:        {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let image = atlas.build_image();
+    println!("build parallel:      {:?}", start.elapsed());
+
+    let data = image.data.as_ref().unwrap();
+    assert!(*data == reference, "Mismatched atlas image");
+    println!("checksum: {:08x}", crc32fast::hash(data));
+}
+
+fn load_image(path: &str) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let file = BufReader::new(File::open(path).unwrap());
+    let reader = ImageReader::new(file).with_guessed_format().unwrap().decode().unwrap();
+    reader.to_rgba8()
+}
@@ -42,7 +42,7 @@ fn main() {
     atlas.write_compressed_to(std::fs::File::create("out/atlas_compressed.sfa").unwrap()).unwrap();
 
     // // Load Atlas // //
-    let atlas = TileAtlasBuilder::read_compressed_from(std::fs::File::open("out/atlas_compressed.sfa").unwrap()).unwrap();
+    let atlas = TileAtlasBuilder::read_from(std::fs::File::open("out/atlas_compressed.sfa").unwrap()).unwrap();
 
     // // Save Images // //
     let set_a = atlas.get_tile("base", "image0").unwrap();
@@ -61,7 +61,7 @@ fn main() {
     for level in 0..levels {
         for page in 0..pages {
             let range = atlas.get_page_range_bytes(page, level).unwrap();
-            let page_size = atlas.mip_level_size(level)*16;
+            let page_size = atlas.mip_level_size(level)*atlas.page_tiles();
             save_buffer_with_format(
                 format!("out/page-dump-p{page}-l{level}.png"),
                 &image.data.as_ref().unwrap()[range], 
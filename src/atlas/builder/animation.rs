@@ -0,0 +1,682 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use bevy::render::render_resource::TextureFormat;
+use image::{codecs::{gif::GifDecoder, png::PngDecoder}, AnimationDecoder, Frame, ImageFormat};
+use thiserror::Error;
+
+use super::{TileAtlasBuilder, TileAtlasFrameTiming, TileAtlasPixelFormat, TileAtlasSheetError, TileAtlasSheetRect};
+
+#[derive(Debug, Error)]
+pub enum TileAtlasAnimationError {
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+
+    #[error(transparent)]
+    Sheet(#[from] TileAtlasSheetError),
+
+    #[error("animation frames can't be converted to {0:?}")]
+    ImageFormat(TileAtlasPixelFormat),
+
+    #[error("aseprite file is invalid, {0}")]
+    Aseprite(&'static str),
+
+    #[error("aseprite files with {0} bits per pixel aren't supported")]
+    AsepriteColorDepth(u16),
+
+    #[error("aseprite tilemap layers aren't supported")]
+    AsepriteTilemap,
+}
+
+/**
+ * The order an animation tag plays its frames in.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TileAtlasAnimationDirection {
+    #[default]
+    Forward,
+    Reverse,
+    /// Forward then back, without repeating the end frames.
+    PingPong,
+    /// Back then forward, without repeating the end frames.
+    PingPongReverse,
+}
+
+/**
+ * A named range of frames in an animation, such as an Aseprite tag.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileAtlasAnimationTag {
+    pub name: String,
+    /// The first frame of the tag.
+    pub from: u32,
+    /// The last frame of the tag, inclusive.
+    pub to:   u32,
+    pub direction: TileAtlasAnimationDirection,
+}
+
+impl TileAtlasAnimationTag {
+
+    /**
+     * The indices of the frames in the order they're played, a ping-pong tag plays its inner frames twice.
+     */
+    #[must_use]
+    pub fn sequence(&self) -> Vec<u32> {
+        let forward = self.from..=self.to.max(self.from);
+        let inner   = (self.from + 1)..self.to;
+        match self.direction {
+            TileAtlasAnimationDirection::Forward         => forward.collect(),
+            TileAtlasAnimationDirection::Reverse         => forward.rev().collect(),
+            TileAtlasAnimationDirection::PingPong        => forward.chain(inner.rev()).collect(),
+            TileAtlasAnimationDirection::PingPongReverse => forward.rev().chain(inner).collect(),
+        }
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct TileAtlasAnimationFrame {
+    /// The composited frame as sRGB RGBA8 pixels.
+    pub data: Vec<u8>,
+    /// How long the frame is shown for in milliseconds.
+    pub duration: u32,
+}
+
+/**
+ * An animation decoded into whole frames of the same size, ready to be inserted as tile sequences.
+ * - Read from animated GIF, APNG and Aseprite files, see `TileAtlasBuilder::insert_animation`.
+ * - The frame durations become the tiles' `TileAtlasFrameTiming`.
+ */
+#[derive(Debug, Default, Clone)]
+pub struct TileAtlasAnimation {
+    pub size:   [u32; 2],
+    pub frames: Vec<TileAtlasAnimationFrame>,
+    pub tags:   Vec<TileAtlasAnimationTag>,
+}
+
+impl TileAtlasAnimation {
+
+    /**
+     * Decodes an animated GIF, a still GIF has a single frame.
+     */
+    #[allow(clippy::std_instead_of_core)] // `core::io::Cursor` isn't stable
+    pub fn from_gif(src: &[u8]) -> Result<Self, TileAtlasAnimationError> {
+        let decoder = GifDecoder::new(std::io::Cursor::new(src))?;
+        Ok(Self::from_image_frames(decoder.into_frames().collect_frames()?))
+    }
+
+    /**
+     * Decodes an APNG, a PNG without an animation has a single frame.
+     */
+    #[allow(clippy::std_instead_of_core)] // `core::io::Cursor` isn't stable
+    pub fn from_apng(src: &[u8]) -> Result<Self, TileAtlasAnimationError> {
+        let decoder = PngDecoder::new(std::io::Cursor::new(src))?;
+        if !decoder.is_apng()? {
+            let image = image::load_from_memory_with_format(src, ImageFormat::Png)?.into_rgba8();
+            return Ok(Self{
+                size:   [image.width(), image.height()],
+                frames: vec![TileAtlasAnimationFrame{ data: image.into_raw(), duration: 0 }],
+                tags:   Vec::new(),
+            });
+        }
+        Ok(Self::from_image_frames(decoder.apng()?.into_frames().collect_frames()?))
+    }
+
+    /**
+     * Decodes an Aseprite `.aseprite` or `.ase` file, compositing its visible layers and keeping its tags.
+     * - Layers are blended normally with their opacity, other blend modes are treated as normal.
+     */
+    pub fn from_aseprite(src: &[u8]) -> Result<Self, TileAtlasAnimationError> {
+        aseprite::decode(src)
+    }
+
+    fn from_image_frames(frames: Vec<Frame>) -> Self {
+        let size = frames.first().map_or([0, 0], |f| [f.buffer().width(), f.buffer().height()]);
+        let frames = frames.into_iter().map(|frame| {
+            let (numer, denom) = frame.delay().numer_denom_ms();
+            TileAtlasAnimationFrame{ data: frame.into_buffer().into_raw(), duration: numer / denom.max(1) }
+        });
+        Self{ size, frames: frames.collect(), tags: Vec::new() }
+    }
+
+    /**
+     * The frame timing of a sequence of frame indices, see `TileAtlasAnimationTag::sequence`.
+     */
+    #[must_use]
+    pub fn timing(&self, sequence: &[u32]) -> TileAtlasFrameTiming {
+        TileAtlasFrameTiming{ durations: sequence.iter().map(|&i| self.frames[i as usize].duration).collect() }
+    }
+
+    /**
+     * The tiles of the animation as (`tile_id`, `sequence`), for an animation inserted as `tile_id`.
+     * - Each tag is a tile named `{tile_id}.{tag}`, untagged animations are a single tile of every frame.
+     */
+    #[must_use]
+    pub fn tiles(&self, tile_id: &str) -> Vec<(String, Vec<u32>)> {
+        if self.tags.is_empty() {
+            return vec![(tile_id.to_owned(), (0..self.frames.len() as u32).collect())];
+        }
+        self.tags.iter().map(|tag| (format!("{tile_id}.{}", tag.name), tag.sequence())).collect()
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Inserts the tiles of an `animation` into the group with the given `group_id` at the specified mip `level`, see `TileAtlasAnimation::tiles`.
+     * - Frames smaller than the tile are placed in its corner, on a cleared tile.
+     * - The frame durations of each tile are recorded, see `Self::set_timing`.
+     */
+    pub fn insert_animation(
+        &mut self,
+        group_id:  &str,
+        tile_id:   &str,
+        level:     u32,
+        animation: &TileAtlasAnimation,
+    ) -> Result<(), TileAtlasAnimationError> {
+        // Frames are stacked into a strip, so they can be cut out as the rects of a sheet
+        let format = self.format();
+        let strip  = animation.frames.iter().flat_map(|f| f.data.iter().copied()).collect::<Vec<_>>();
+        let strip  = format.convert(&strip, TextureFormat::Rgba8UnormSrgb).ok_or(TileAtlasAnimationError::ImageFormat(format))?;
+
+        let [width, height] = animation.size;
+        let tiles = animation.tiles(tile_id);
+        for (tile_id, sequence) in &tiles {
+            let frames = sequence.iter().map(|&i| TileAtlasSheetRect {
+                name:     tile_id.clone(),
+                position: [0, i*height],
+                size:     [width, height],
+                offset:   [0, 0],
+            }).collect::<Vec<_>>();
+            self.insert_rects(group_id, tile_id, level, &strip, width, &frames.iter().collect::<Vec<_>>())?;
+        }
+        for (tile_id, sequence) in &tiles {
+            self.set_timing(group_id, tile_id, animation.timing(sequence));
+        }
+        Ok(())
+    }
+
+}
+
+/**
+ * A minimal reader of the Aseprite file format, see <https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md>.
+ */
+mod aseprite {
+
+    use super::{TileAtlasAnimation, TileAtlasAnimationDirection, TileAtlasAnimationError, TileAtlasAnimationFrame, TileAtlasAnimationTag};
+
+    const HEADER_MAGIC: u16 = 0xA5E0;
+    const FRAME_MAGIC:  u16 = 0xF1FA;
+
+    const CHUNK_PALETTE_OLD:   u16 = 0x0004;
+    const CHUNK_PALETTE_OLD_6: u16 = 0x0011;
+    const CHUNK_LAYER:         u16 = 0x2004;
+    const CHUNK_CEL:           u16 = 0x2005;
+    const CHUNK_TAGS:          u16 = 0x2018;
+    const CHUNK_PALETTE:       u16 = 0x2019;
+
+    const LAYER_VISIBLE:    u16 = 0x0001;
+    const LAYER_BACKGROUND: u16 = 0x0008;
+    const LAYER_REFERENCE:  u16 = 0x0040;
+    const LAYER_GROUP:      u16 = 1;
+    const LAYER_TILEMAP:    u16 = 2;
+
+    const CEL_RAW:        u16 = 0;
+    const CEL_LINKED:     u16 = 1;
+    const CEL_COMPRESSED: u16 = 2;
+
+    const HEADER_LAYER_OPACITY: u32 = 0x0001;
+
+    struct Reader<'a> {
+        src: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Reader<'a> {
+
+        const fn new(src: &'a [u8]) -> Self {
+            Self{ src, pos: 0 }
+        }
+
+        fn bytes(&mut self, len: usize) -> Result<&'a [u8], TileAtlasAnimationError> {
+            let bytes = self.src.get(self.pos..self.pos + len).ok_or(TileAtlasAnimationError::Aseprite("unexpected end of data"))?;
+            self.pos += len;
+            Ok(bytes)
+        }
+
+        fn u8(&mut self) -> Result<u8, TileAtlasAnimationError> {
+            Ok(self.bytes(1)?[0])
+        }
+
+        fn u16(&mut self) -> Result<u16, TileAtlasAnimationError> {
+            Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+        }
+
+        fn i16(&mut self) -> Result<i16, TileAtlasAnimationError> {
+            Ok(i16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+        }
+
+        fn u32(&mut self) -> Result<u32, TileAtlasAnimationError> {
+            Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+        }
+
+        fn string(&mut self) -> Result<String, TileAtlasAnimationError> {
+            let len = self.u16()? as usize;
+            Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+        }
+
+    }
+
+    struct Layer {
+        visible:    bool,
+        background: bool,
+        opacity:    u8,
+    }
+
+    #[derive(Clone)]
+    struct Cel {
+        layer:   usize,
+        x:       i32,
+        y:       i32,
+        opacity: u8,
+        z_index: i16,
+        width:   u32,
+        height:  u32,
+        /// Pixels in the file's color depth.
+        pixels:  Vec<u8>,
+    }
+
+    pub(super) fn decode(src: &[u8]) -> Result<TileAtlasAnimation, TileAtlasAnimationError> {
+        let mut header = Reader::new(src);
+        header.u32()?; // File size
+        if header.u16()? != HEADER_MAGIC { return Err(TileAtlasAnimationError::Aseprite("missing header magic")); }
+        let frame_count = header.u16()? as usize;
+        let width       = header.u16()? as u32;
+        let height      = header.u16()? as u32;
+        let color_depth = header.u16()?;
+        let flags       = header.u32()?;
+        header.bytes(10)?; // Speed and reserved
+        let transparent_index = header.u8()?;
+        header.bytes(99)?; // Grid and reserved
+        if !matches!(color_depth, 8 | 16 | 32) { return Err(TileAtlasAnimationError::AsepriteColorDepth(color_depth)); }
+
+        let mut layers  = Vec::<Layer>::new();
+        let mut groups  = Vec::<bool>::new();
+        let mut palette = vec![[0_u8; 4]; 256];
+        let mut palette_alpha = false;
+        let mut tags    = Vec::new();
+        let mut frames  = Vec::<(u32, Vec<Cel>)>::with_capacity(frame_count);
+
+        let mut reader = header;
+        for _ in 0..frame_count {
+            let frame_len = reader.u32()? as usize;
+            let mut frame = Reader::new(reader.bytes(frame_len.checked_sub(4).ok_or(TileAtlasAnimationError::Aseprite("frame is too short"))?)?);
+            if frame.u16()? != FRAME_MAGIC { return Err(TileAtlasAnimationError::Aseprite("missing frame magic")); }
+            let old_chunks = frame.u16()?;
+            let duration   = frame.u16()? as u32;
+            frame.bytes(2)?;
+            let chunks = match frame.u32()? {
+                0 => old_chunks as u32,
+                n => n,
+            };
+
+            let mut cels = Vec::new();
+            for _ in 0..chunks {
+                let chunk_len = frame.u32()? as usize;
+                let mut chunk = Reader::new(frame.bytes(chunk_len.checked_sub(4).ok_or(TileAtlasAnimationError::Aseprite("chunk is too short"))?)?);
+                match chunk.u16()? {
+                    CHUNK_LAYER => {
+                        let layer_flags = chunk.u16()?;
+                        let layer_type  = chunk.u16()?;
+                        let level       = chunk.u16()? as usize;
+                        chunk.bytes(6)?; // Default size and blend mode
+                        let opacity = if flags & HEADER_LAYER_OPACITY != 0 { chunk.u8()? } else { 255 };
+
+                        // Layers are hidden by any hidden group they're nested in
+                        groups.truncate(level);
+                        let visible = layer_flags & LAYER_VISIBLE != 0 && layer_flags & LAYER_REFERENCE == 0 && groups.last().copied().unwrap_or(true);
+                        match layer_type {
+                            LAYER_GROUP   => groups.push(visible),
+                            LAYER_TILEMAP if visible => return Err(TileAtlasAnimationError::AsepriteTilemap),
+                            _ => {},
+                        }
+                        layers.push(Layer{ visible, background: layer_flags & LAYER_BACKGROUND != 0, opacity });
+                    },
+                    CHUNK_CEL => {
+                        let layer   = chunk.u16()? as usize;
+                        let x       = chunk.i16()? as i32;
+                        let y       = chunk.i16()? as i32;
+                        let opacity = chunk.u8()?;
+                        let kind    = chunk.u16()?;
+                        let z_index = chunk.i16()?;
+                        chunk.bytes(5)?;
+                        match kind {
+                            CEL_RAW | CEL_COMPRESSED => {
+                                let width  = chunk.u16()? as u32;
+                                let height = chunk.u16()? as u32;
+                                let data   = &chunk.src[chunk.pos..];
+                                let pixels = if kind == CEL_RAW { data.to_vec() } else {
+                                    miniz_oxide::inflate::decompress_to_vec_zlib(data).map_err(|_| TileAtlasAnimationError::Aseprite("cel data is corrupt"))?
+                                };
+                                let len = (width as usize).checked_mul(height as usize)
+                                    .and_then(|len| len.checked_mul(color_depth as usize/8))
+                                    .ok_or(TileAtlasAnimationError::Aseprite("cel is too large"))?;
+                                if pixels.len() < len {
+                                    return Err(TileAtlasAnimationError::Aseprite("cel data is truncated"));
+                                }
+                                cels.push(Cel{ layer, x, y, opacity, z_index, width, height, pixels });
+                            },
+                            CEL_LINKED => {
+                                let linked = chunk.u16()? as usize;
+                                let cel = frames.get(linked)
+                                    .and_then(|(_, cels)| cels.iter().find(|c| c.layer == layer))
+                                    .ok_or(TileAtlasAnimationError::Aseprite("linked cel is missing"))?;
+                                cels.push(cel.clone());
+                            },
+                            _ if layers.get(layer).is_some_and(|l| l.visible) => return Err(TileAtlasAnimationError::AsepriteTilemap),
+                            _ => {},
+                        }
+                    },
+                    CHUNK_TAGS => {
+                        let count = chunk.u16()?;
+                        chunk.bytes(8)?;
+                        for _ in 0..count {
+                            let from      = chunk.u16()? as u32;
+                            let to        = chunk.u16()? as u32;
+                            let direction = match chunk.u8()? {
+                                1 => TileAtlasAnimationDirection::Reverse,
+                                2 => TileAtlasAnimationDirection::PingPong,
+                                3 => TileAtlasAnimationDirection::PingPongReverse,
+                                _ => TileAtlasAnimationDirection::Forward,
+                            };
+                            chunk.bytes(12)?; // Repeat, reserved and color
+                            let name = chunk.string()?;
+                            if from as usize >= frame_count || to as usize >= frame_count {
+                                return Err(TileAtlasAnimationError::Aseprite("tag is out of range"));
+                            }
+                            tags.push(TileAtlasAnimationTag{ name, from, to, direction });
+                        }
+                    },
+                    CHUNK_PALETTE => {
+                        let size  = chunk.u32()? as usize;
+                        let first = chunk.u32()? as usize;
+                        let last  = chunk.u32()? as usize;
+                        chunk.bytes(8)?;
+                        palette_alpha = true;
+                        if size > palette.len() { palette.resize(size, [0; 4]); }
+                        for index in first..=last {
+                            let entry_flags = chunk.u16()?;
+                            let color = chunk.bytes(4)?;
+                            if let Some(entry) = palette.get_mut(index) { entry.copy_from_slice(color); }
+                            if entry_flags & 0x0001 != 0 { chunk.string()?; }
+                        }
+                    },
+                    // Older palettes are only kept for compatibility once a file has a palette with alpha
+                    kind @ (CHUNK_PALETTE_OLD | CHUNK_PALETTE_OLD_6) if !palette_alpha => {
+                        let packets = chunk.u16()?;
+                        let mut index = 0_usize;
+                        for _ in 0..packets {
+                            index += chunk.u8()? as usize;
+                            let count = match chunk.u8()? { 0 => 256, n => n as usize };
+                            for _ in 0..count {
+                                let rgb = chunk.bytes(3)?;
+                                let rgb = if kind == CHUNK_PALETTE_OLD_6 { [rgb[0], rgb[1], rgb[2]].map(|v| (v as u32*255/63) as u8) } else { [rgb[0], rgb[1], rgb[2]] };
+                                if let Some(entry) = palette.get_mut(index) { *entry = [rgb[0], rgb[1], rgb[2], 255]; }
+                                index += 1;
+                            }
+                        }
+                    },
+                    _ => {},
+                }
+            }
+            frames.push((duration, cels));
+        }
+
+        let frames = frames.iter().map(|(duration, cels)| {
+            // Cels are drawn in layer order, shifted by their z-index, and by z-index when they tie
+            let mut cels = cels.iter().filter(|c| layers.get(c.layer).is_some_and(|l| l.visible)).collect::<Vec<_>>();
+            cels.sort_by_key(|c| (c.layer as i32 + c.z_index as i32, c.z_index));
+
+            let mut data = vec![0_u8; width as usize*height as usize*4];
+            for cel in cels {
+                let layer = &layers[cel.layer];
+                let opacity = cel.opacity as f32 * layer.opacity as f32 / (255.0*255.0);
+                for cy in 0..cel.height {
+                    for cx in 0..cel.width {
+                        let (x, y) = (cel.x + cx as i32, cel.y + cy as i32);
+                        if x < 0 || y < 0 || x as u32 >= width || y as u32 >= height { continue; }
+                        let i = (cy*cel.width + cx) as usize;
+                        let color = match color_depth {
+                            32 => cel.pixels[i*4..][..4].try_into().unwrap(),
+                            16 => [cel.pixels[i*2], cel.pixels[i*2], cel.pixels[i*2], cel.pixels[i*2 + 1]],
+                            _  => {
+                                let index = cel.pixels[i];
+                                if index == transparent_index && !layer.background { continue; }
+                                palette.get(index as usize).copied().unwrap_or_default()
+                            },
+                        };
+                        blend(&mut data[(y as u32*width + x as u32) as usize*4..][..4], color, opacity);
+                    }
+                }
+            }
+            TileAtlasAnimationFrame{ data, duration: *duration }
+        });
+
+        Ok(TileAtlasAnimation{ size: [width, height], frames: frames.collect(), tags })
+    }
+
+    /**
+     * Blends a straight alpha `src` color over `dst` with the `opacity`.
+     */
+    fn blend(dst: &mut [u8], src: [u8; 4], opacity: f32) {
+        let src_a = src[3] as f32 / 255.0 * opacity;
+        let dst_a = dst[3] as f32 / 255.0;
+        let out_a = src_a + dst_a*(1.0 - src_a);
+        if out_a <= 0.0 { return; }
+        for c in 0..3 {
+            let value = (src[c] as f32*src_a + dst[c] as f32*dst_a*(1.0 - src_a)) / out_a;
+            dst[c] = value.round() as u8;
+        }
+        dst[3] = (out_a * 255.0).round() as u8;
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED:   [u8; 4] = [255,   0,   0, 255];
+    const GREEN: [u8; 4] = [  0, 255,   0, 255];
+    const BLUE:  [u8; 4] = [  0,   0, 255, 255];
+    const CLEAR: [u8; 4] = [  0,   0,   0,   0];
+
+    fn file(size: u16, color_depth: u16, frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut dst = Vec::new();
+        dst.extend_from_slice(&0_u32.to_le_bytes());
+        dst.extend_from_slice(&0xA5E0_u16.to_le_bytes());
+        dst.extend_from_slice(&(frames.len() as u16).to_le_bytes());
+        dst.extend_from_slice(&size.to_le_bytes());
+        dst.extend_from_slice(&size.to_le_bytes());
+        dst.extend_from_slice(&color_depth.to_le_bytes());
+        dst.extend_from_slice(&1_u32.to_le_bytes()); // Layer opacity is valid
+        dst.extend_from_slice(&[0; 10]);
+        dst.push(0); // Transparent index
+        dst.extend_from_slice(&[0; 99]);
+        let len = dst.len() + frames.iter().map(Vec::len).sum::<usize>();
+        dst[..4].copy_from_slice(&(len as u32).to_le_bytes());
+        for frame in frames { dst.extend_from_slice(frame); }
+        dst
+    }
+
+    fn frame(duration: u16, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let len = 16 + chunks.iter().map(Vec::len).sum::<usize>();
+        let mut dst = Vec::new();
+        dst.extend_from_slice(&(len as u32).to_le_bytes());
+        dst.extend_from_slice(&0xF1FA_u16.to_le_bytes());
+        dst.extend_from_slice(&(chunks.len() as u16).to_le_bytes());
+        dst.extend_from_slice(&duration.to_le_bytes());
+        dst.extend_from_slice(&[0; 2]);
+        dst.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        for chunk in chunks { dst.extend_from_slice(chunk); }
+        dst
+    }
+
+    fn chunk(kind: u16, body: &[u8]) -> Vec<u8> {
+        let mut dst = Vec::new();
+        dst.extend_from_slice(&(body.len() as u32 + 6).to_le_bytes());
+        dst.extend_from_slice(&kind.to_le_bytes());
+        dst.extend_from_slice(body);
+        dst
+    }
+
+    fn layer(flags: u16, kind: u16, level: u16) -> Vec<u8> {
+        let mut body = Vec::new();
+        for v in [flags, kind, level, 0, 0, 0] { body.extend_from_slice(&v.to_le_bytes()); }
+        body.push(255);
+        body.extend_from_slice(&[0; 3]);
+        body.extend_from_slice(&0_u16.to_le_bytes()); // Name
+        chunk(0x2004, &body)
+    }
+
+    fn cel(layer: u16, position: [i16; 2], kind: u16, data: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&layer.to_le_bytes());
+        body.extend_from_slice(&position[0].to_le_bytes());
+        body.extend_from_slice(&position[1].to_le_bytes());
+        body.push(255);
+        body.extend_from_slice(&kind.to_le_bytes());
+        body.extend_from_slice(&0_i16.to_le_bytes());
+        body.extend_from_slice(&[0; 5]);
+        body.extend_from_slice(data);
+        chunk(0x2005, &body)
+    }
+
+    fn image(size: [u16; 2], pixels: &[u8]) -> Vec<u8> {
+        let mut dst = Vec::new();
+        dst.extend_from_slice(&size[0].to_le_bytes());
+        dst.extend_from_slice(&size[1].to_le_bytes());
+        dst.extend_from_slice(pixels);
+        dst
+    }
+
+    fn pixels(colors: &[[u8; 4]]) -> Vec<u8> {
+        colors.concat()
+    }
+
+    #[test]
+    fn raw_cel() {
+        let colors = [RED, GREEN, BLUE, CLEAR];
+        let src = file(2, 32, &[frame(100, &[
+            layer(1, 0, 0),
+            cel(0, [0, 0], 0, &image([2, 2], &pixels(&colors))),
+        ])]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        assert_eq!(animation.size, [2, 2]);
+        assert_eq!(animation.frames.len(), 1);
+        assert_eq!(animation.frames[0].duration, 100);
+        assert_eq!(animation.frames[0].data, pixels(&colors));
+    }
+
+    #[test]
+    fn compressed_cel() {
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&pixels(&[GREEN]), 6);
+        let src = file(2, 32, &[frame(100, &[
+            layer(1, 0, 0),
+            cel(0, [1, 1], 2, &image([1, 1], &data)),
+        ])]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        assert_eq!(animation.frames[0].data, pixels(&[CLEAR, CLEAR, CLEAR, GREEN]));
+    }
+
+    #[test]
+    fn linked_cel() {
+        let src = file(1, 32, &[
+            frame(100, &[layer(1, 0, 0), cel(0, [0, 0], 0, &image([1, 1], &RED))]),
+            frame(50,  &[cel(0, [0, 0], 1, &0_u16.to_le_bytes())]),
+        ]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        assert_eq!(animation.frames.len(), 2);
+        assert_eq!(animation.frames[1].duration, 50);
+        assert_eq!(animation.frames[1].data, RED);
+    }
+
+    #[test]
+    fn truncated_cel() {
+        let src = file(2, 32, &[frame(100, &[
+            layer(1, 0, 0),
+            cel(0, [0, 0], 0, &image([2, 2], &RED)),
+        ])]);
+        assert!(matches!(TileAtlasAnimation::from_aseprite(&src), Err(TileAtlasAnimationError::Aseprite(_))));
+    }
+
+    #[test]
+    fn indexed_palette() {
+        let mut palette = Vec::new();
+        for v in [3_u32, 0, 2] { palette.extend_from_slice(&v.to_le_bytes()); }
+        palette.extend_from_slice(&[0; 8]);
+        for color in [BLUE, RED, [0, 255, 0, 128]] {
+            palette.extend_from_slice(&0_u16.to_le_bytes());
+            palette.extend_from_slice(&color);
+        }
+        let src = file(2, 8, &[frame(100, &[
+            layer(1, 0, 0),
+            chunk(0x2019, &palette),
+            cel(0, [0, 0], 0, &image([2, 2], &[0, 1, 2, 1])),
+        ])]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        // The transparent index is skipped, rather than drawn with its palette color
+        assert_eq!(animation.frames[0].data, pixels(&[CLEAR, RED, [0, 255, 0, 128], RED]));
+    }
+
+    #[test]
+    fn tags() {
+        let mut tags = Vec::new();
+        tags.extend_from_slice(&2_u16.to_le_bytes());
+        tags.extend_from_slice(&[0; 8]);
+        for (from, to, direction, name) in [(0_u16, 2_u16, 2_u8, "walk"), (1, 2, 1, "idle")] {
+            tags.extend_from_slice(&from.to_le_bytes());
+            tags.extend_from_slice(&to.to_le_bytes());
+            tags.push(direction);
+            tags.extend_from_slice(&[0; 12]);
+            tags.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            tags.extend_from_slice(name.as_bytes());
+        }
+        let src = file(1, 32, &[
+            frame(100, &[layer(1, 0, 0), chunk(0x2018, &tags)]),
+            frame(200, &[]),
+            frame(300, &[]),
+        ]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        assert_eq!(animation.tags.len(), 2);
+        assert_eq!(animation.tags[0], TileAtlasAnimationTag{ name: "walk".to_owned(), from: 0, to: 2, direction: TileAtlasAnimationDirection::PingPong });
+        assert_eq!(animation.tiles("hero"), vec![
+            ("hero.walk".to_owned(), vec![0, 1, 2, 1]),
+            ("hero.idle".to_owned(), vec![2, 1]),
+        ]);
+        assert_eq!(animation.timing(&[0, 1, 2, 1]).durations, vec![100, 200, 300, 200]);
+    }
+
+    #[test]
+    fn hidden_group() {
+        let src = file(1, 32, &[frame(100, &[
+            layer(0, 1, 0), // Hidden group
+            layer(1, 0, 1), // Visible layer inside the hidden group
+            layer(1, 0, 0), // Visible layer after the group
+            cel(1, [0, 0], 0, &image([1, 1], &RED)),
+            cel(2, [0, 0], 0, &image([1, 1], &BLUE)),
+        ])]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        assert_eq!(animation.frames[0].data, BLUE);
+
+        // The layer inside is drawn once the group is shown
+        let src = file(1, 32, &[frame(100, &[
+            layer(1, 1, 0),
+            layer(1, 0, 1),
+            cel(1, [0, 0], 0, &image([1, 1], &RED)),
+        ])]);
+        let animation = TileAtlasAnimation::from_aseprite(&src).unwrap();
+        assert_eq!(animation.frames[0].data, RED);
+    }
+
+}
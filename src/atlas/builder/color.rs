@@ -0,0 +1,73 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+// Colour conversions shared by the downsamplers and pixel formats, all operating on normalized RGBA
+
+use super::TileAtlasPixelFormat;
+
+pub(super) fn srgba_channel_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055)/1.055).powf(2.4)
+    }
+}
+
+pub(super) fn srgba_to_linear(v: [f32; 4]) -> [f32; 4] {
+    [
+        srgba_channel_to_linear(v[0]),
+        srgba_channel_to_linear(v[1]),
+        srgba_channel_to_linear(v[2]),
+        v[3]
+    ]
+}
+
+pub(super) fn linear_channel_to_srgb(v: f32) -> f32 {
+    if v <= 0.003_130_8 {
+        v * 12.92
+    } else {
+        (v.powf(1.0/2.4)*1.055) - 0.055
+    }
+}
+
+pub(super) fn linear_to_srgba(v: [f32; 4]) -> [f32; 4] {
+    [
+        linear_channel_to_srgb(v[0]),
+        linear_channel_to_srgb(v[1]),
+        linear_channel_to_srgb(v[2]),
+        v[3]
+    ]
+}
+
+pub(super) fn premul_alpha(v: [f32; 4]) -> [f32; 4] {
+    [
+        v[0]*v[3],
+        v[1]*v[3],
+        v[2]*v[3],
+        v[3]
+    ]
+}
+
+pub(super) fn demul_alpha(v: [f32; 4]) -> [f32; 4] {
+    [
+        v[0]/v[3],
+        v[1]/v[3],
+        v[2]/v[3],
+        v[3]
+    ]
+}
+
+/**
+ * Reads the pixel at `offset` in linear space, sRGB formats are converted.
+ */
+pub(super) fn read_linear(format: TileAtlasPixelFormat, src: &[u8], offset: usize) -> [f32; 4] {
+    let value = format.read(src, offset);
+    if format.is_srgb() { srgba_to_linear(value) } else { value }
+}
+
+/**
+ * Writes a linear pixel at `offset`, sRGB formats are converted.
+ */
+pub(super) fn write_linear(format: TileAtlasPixelFormat, dst: &mut [u8], offset: usize, value: [f32; 4], rounded: bool) {
+    let value = if format.is_srgb() { linear_to_srgba(value) } else { value };
+    format.write(dst, offset, value, rounded);
+}
@@ -0,0 +1,274 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+// BC7 mode 6 stores a single subset with 7 bit RGBA endpoints, a shared low bit per endpoint
+// and 4 bit indices, which suits tiles with smooth colours and alpha.
+
+const WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+const POWER_ITERATIONS: usize = 8;
+
+/**
+ * Quantized endpoints as their 7 bit channels and low bits.
+ */
+#[derive(Debug, Clone, Copy)]
+struct Endpoints {
+    colors: [[u8; 4]; 2],
+    p_bits: [u8; 2],
+}
+
+impl Endpoints {
+
+    /**
+     * Quantizes a pair of endpoints, choosing the low bit that best matches each.
+     */
+    fn quantize(e0: [f32; 4], e1: [f32; 4]) -> Self {
+        let (c0, p0) = quantize_endpoint(e0);
+        let (c1, p1) = quantize_endpoint(e1);
+        Self{ colors: [c0, c1], p_bits: [p0, p1] }
+    }
+
+    fn expanded(&self, endpoint: usize) -> [u8; 4] {
+        self.colors[endpoint].map(|c| (c << 1) | self.p_bits[endpoint])
+    }
+
+    fn palette(&self) -> [[u8; 4]; 16] {
+        let e0 = self.expanded(0);
+        let e1 = self.expanded(1);
+        WEIGHTS.map(|w| core::array::from_fn(|c| (((64 - w)*e0[c] as u32 + w*e1[c] as u32 + 32) >> 6) as u8))
+    }
+
+}
+
+/**
+ * Encodes a 4x4 block of RGBA8 pixels, in row-major order, as a BC7 mode 6 block.
+ */
+pub(super) fn bc7_encode_block(pixels: &[[u8; 4]; 16]) -> [u8; 16] {
+    let pixels_f = pixels.map(|p| p.map(f32::from));
+
+    let (e0, e1) = bounding_box(&pixels_f);
+    let mut best = fit(pixels, Endpoints::quantize(e0, e1));
+    if let Some((e0, e1)) = principal_axis(&pixels_f) {
+        let candidate = fit(pixels, Endpoints::quantize(e0, e1));
+        if candidate.2 < best.2 { best = candidate; }
+    }
+
+    // Refine the endpoints to the least squares fit of the chosen indices
+    if let Some((e0, e1)) = least_squares(&pixels_f, &best.1) {
+        let candidate = fit(pixels, Endpoints::quantize(e0, e1));
+        if candidate.2 < best.2 { best = candidate; }
+    }
+
+    let (mut endpoints, mut indices, _) = best;
+
+    // The first index drops its high bit, so it must be in the first half of the palette
+    if indices[0] >= 8 {
+        endpoints.colors.swap(0, 1);
+        endpoints.p_bits.swap(0, 1);
+        indices = indices.map(|i| 15 - i);
+    }
+
+    pack(&endpoints, &indices)
+}
+
+/**
+ * Picks the closest palette entry for every pixel, returning the indices and total squared error.
+ */
+fn fit(pixels: &[[u8; 4]; 16], endpoints: Endpoints) -> (Endpoints, [u8; 16], u32) {
+    let palette = endpoints.palette();
+    let mut indices = [0_u8; 16];
+    let mut error   = 0;
+    for (index, pixel) in indices.iter_mut().zip(pixels) {
+        let (i, e) = palette.iter().map(|entry| distance(*entry, *pixel)).enumerate().min_by_key(|&(_, e)| e).unwrap();
+        *index = i as u8;
+        error += e;
+    }
+    (endpoints, indices, error)
+}
+
+fn distance(a: [u8; 4], b: [u8; 4]) -> u32 {
+    a.iter().zip(b).map(|(&a, b)| (a as i32 - b as i32).pow(2) as u32).sum()
+}
+
+fn quantize_endpoint(value: [f32; 4]) -> ([u8; 4], u8) {
+    let candidate = |p: u8| {
+        let colors = value.map(|v| ((v - p as f32)/2.0).round().clamp(0.0, 127.0) as u8);
+        let error  = colors.iter().zip(value).map(|(&c, v)| (((c << 1) | p) as f32 - v).powi(2)).sum::<f32>();
+        (colors, p, error)
+    };
+
+    let (c0, p0, e0) = candidate(0);
+    let (c1, p1, e1) = candidate(1);
+    if e0 <= e1 { (c0, p0) } else { (c1, p1) }
+}
+
+fn bounding_box(pixels: &[[f32; 4]; 16]) -> ([f32; 4], [f32; 4]) {
+    let mut min = [255.0_f32; 4];
+    let mut max = [0.0_f32; 4];
+    for pixel in pixels {
+        for c in 0..4 {
+            min[c] = min[c].min(pixel[c]);
+            max[c] = max[c].max(pixel[c]);
+        }
+    }
+    (min, max)
+}
+
+/**
+ * Finds endpoints along the direction of greatest variance, returns `None` for solid blocks.
+ */
+fn principal_axis(pixels: &[[f32; 4]; 16]) -> Option<([f32; 4], [f32; 4])> {
+    let mean: [f32; 4] = core::array::from_fn(|c| pixels.iter().map(|p| p[c]).sum::<f32>()/16.0);
+
+    let mut covariance = [[0.0_f32; 4]; 4];
+    for pixel in pixels {
+        let d: [f32; 4] = core::array::from_fn(|c| pixel[c] - mean[c]);
+        for (i, row) in covariance.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v += d[i]*d[j];
+            }
+        }
+    }
+
+    let (min, max) = bounding_box(pixels);
+    let mut axis: [f32; 4] = core::array::from_fn(|c| max[c] - min[c]);
+    for _ in 0..POWER_ITERATIONS {
+        let next: [f32; 4] = core::array::from_fn(|i| (0..4).map(|j| covariance[i][j]*axis[j]).sum());
+        let length = next.iter().map(|v| v*v).sum::<f32>().sqrt();
+        if length <= f32::EPSILON { return None; }
+        axis = next.map(|v| v/length);
+    }
+
+    let project = |p: &[f32; 4]| (0..4).map(|c| (p[c] - mean[c])*axis[c]).sum::<f32>();
+    let t_min = pixels.iter().map(project).fold(f32::MAX, f32::min);
+    let t_max = pixels.iter().map(project).fold(f32::MIN, f32::max);
+    Some((
+        core::array::from_fn(|c| (mean[c] + axis[c]*t_min).clamp(0.0, 255.0)),
+        core::array::from_fn(|c| (mean[c] + axis[c]*t_max).clamp(0.0, 255.0)),
+    ))
+}
+
+/**
+ * Solves for the endpoints that best reproduce the pixels with the given `indices`.
+ */
+fn least_squares(pixels: &[[f32; 4]; 16], indices: &[u8; 16]) -> Option<([f32; 4], [f32; 4])> {
+    let (mut aa, mut ab, mut bb) = (0.0_f32, 0.0_f32, 0.0_f32);
+    let mut ax = [0.0_f32; 4];
+    let mut bx = [0.0_f32; 4];
+    for (pixel, &index) in pixels.iter().zip(indices) {
+        let b = WEIGHTS[index as usize] as f32/64.0;
+        let a = 1.0 - b;
+        aa += a*a;
+        ab += a*b;
+        bb += b*b;
+        for c in 0..4 {
+            ax[c] += a*pixel[c];
+            bx[c] += b*pixel[c];
+        }
+    }
+
+    let det = aa*bb - ab*ab;
+    if det.abs() <= f32::EPSILON { return None; }
+    Some((
+        core::array::from_fn(|c| ((bb*ax[c] - ab*bx[c])/det).clamp(0.0, 255.0)),
+        core::array::from_fn(|c| ((aa*bx[c] - ab*ax[c])/det).clamp(0.0, 255.0)),
+    ))
+}
+
+fn pack(endpoints: &Endpoints, indices: &[u8; 16]) -> [u8; 16] {
+    let mut bits = 0_u128;
+    let mut offset = 0;
+    let mut push = |value: u8, count: u32| {
+        bits |= (value as u128) << offset;
+        offset += count;
+    };
+
+    push(1 << 6, 7);
+    for c in 0..4 {
+        push(endpoints.colors[0][c], 7);
+        push(endpoints.colors[1][c], 7);
+    }
+    push(endpoints.p_bits[0], 1);
+    push(endpoints.p_bits[1], 1);
+
+    push(indices[0], 3);
+    for &index in &indices[1..] {
+        push(index, 4);
+    }
+
+    bits.to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Decodes a BC7 mode 6 block, following the format rather than the encoder.
+     */
+    fn decode(block: [u8; 16]) -> ([[u8; 4]; 2], [[u8; 4]; 16]) {
+        let bits = u128::from_le_bytes(block);
+        let mut offset = 0;
+        let mut read = |count: u32| {
+            let value = ((bits >> offset) & ((1 << count) - 1)) as u8;
+            offset += count;
+            value
+        };
+
+        assert_eq!(read(7), 1 << 6, "Not a mode 6 block");
+        // Endpoints are interleaved by channel
+        let channels: [[u8; 2]; 4] = core::array::from_fn(|_| [read(7), read(7)]);
+        let p_bits = [read(1), read(1)];
+        let endpoints = [0, 1].map(|e| channels.map(|c| (c[e] << 1) | p_bits[e]));
+
+        let pixels = core::array::from_fn(|i| {
+            let w = WEIGHTS[read(if i == 0 { 3 } else { 4 }) as usize];
+            core::array::from_fn(|c| (((64 - w)*endpoints[0][c] as u32 + w*endpoints[1][c] as u32 + 32) >> 6) as u8)
+        });
+        (endpoints, pixels)
+    }
+
+    fn max_error(a: &[[u8; 4]; 16], b: &[[u8; 4]; 16]) -> u8 {
+        a.iter().flatten().zip(b.iter().flatten()).map(|(a, b)| a.abs_diff(*b)).max().unwrap()
+    }
+
+    fn round_trip(pixels: &[[u8; 4]; 16], bound: u8) -> [[u8; 4]; 2] {
+        let (endpoints, decoded) = decode(bc7_encode_block(pixels));
+        let error = max_error(pixels, &decoded);
+        assert!(error <= bound, "Error of {error} exceeds {bound}, decoded {decoded:?}");
+        endpoints
+    }
+
+    #[test]
+    fn solid() {
+        round_trip(&[[200, 100, 50, 255]; 16], 1);
+        round_trip(&[[0, 0, 0, 0]; 16], 0);
+        round_trip(&[[255, 255, 255, 255]; 16], 0);
+    }
+
+    #[test]
+    fn gradient() {
+        let pixels = core::array::from_fn(|i| {
+            let v = (i*17) as u8;
+            [v, v/2, 255 - v, 255]
+        });
+        round_trip(&pixels, 4);
+    }
+
+    #[test]
+    fn alpha_edge() {
+        let pixels = core::array::from_fn(|i| if i % 4 < 2 { [255, 255, 255, 255] } else { [0, 0, 0, 0] });
+        round_trip(&pixels, 0);
+    }
+
+    #[test]
+    fn first_index_swap() {
+        // The first pixel is the brightest, so it starts at the far end of the palette and the endpoints are swapped
+        let pixels = core::array::from_fn(|i| {
+            let v = 255 - (i*17) as u8;
+            [v, v, v, 255]
+        });
+        let endpoints = round_trip(&pixels, 4);
+        assert!(endpoints[0][0] > endpoints[1][0], "Endpoints weren't swapped: {endpoints:?}");
+    }
+
+}
@@ -0,0 +1,264 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use bevy::{asset::RenderAssetUsages, image::{CompressedImageFormats, Image, ImageSampler}, render::render_resource::{TextureFormat, TextureUsages}, tasks::ParallelSliceMut};
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{compute_task_pool, TileAtlasBuilder, TileAtlasPixelFormat};
+
+mod bc7;
+use bc7::bc7_encode_block;
+
+/**
+ * How the pixels of an atlas image are encoded on the GPU.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode, Serialize, Deserialize)]
+pub enum TileAtlasCompression {
+    /// Uncompressed in the builder's pixel format, supported everywhere.
+    #[default]
+    None,
+    /// `Bc7RgbaUnormSrgb` or `Bc7RgbaUnorm` blocks at a quarter of the size, supported by most desktop devices.
+    /// - Only RGBA8 pixel formats can be encoded.
+    Bc7,
+}
+
+impl TileAtlasCompression {
+
+    /**
+     * The texture format of an image in the pixel `format` with this compression, `None` when it can't be encoded.
+     */
+    #[must_use]
+    pub const fn texture_format(self, format: TileAtlasPixelFormat) -> Option<TextureFormat> {
+        match (self, format) {
+            (Self::None, format) => Some(format.texture_format()),
+            (Self::Bc7, TileAtlasPixelFormat::Rgba8Srgb)  => Some(TextureFormat::Bc7RgbaUnormSrgb),
+            (Self::Bc7, TileAtlasPixelFormat::Rgba8Unorm) => Some(TextureFormat::Bc7RgbaUnorm),
+            (Self::Bc7, _) => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn from_texture_format(format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::Bc7RgbaUnormSrgb | TextureFormat::Bc7RgbaUnorm => Some(Self::Bc7),
+            format if TileAtlasPixelFormat::from_texture_format(format).is_some() => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    /**
+     * If images in the pixel `format` can be encoded with this compression.
+     */
+    #[must_use]
+    pub const fn supports_format(self, format: TileAtlasPixelFormat) -> bool {
+        self.texture_format(format).is_some()
+    }
+
+    /**
+     * The width and height of a block in pixels.
+     */
+    #[must_use]
+    pub const fn block_size(self) -> u32 {
+        match self {
+            Self::None => 1,
+            Self::Bc7  => 4,
+        }
+    }
+
+    /**
+     * The length of a block in bytes, for `Self::None` the length of an RGBA8 pixel.
+     */
+    #[must_use]
+    pub const fn block_len(self) -> u32 {
+        match self {
+            Self::None => 4,
+            Self::Bc7  => 16,
+        }
+    }
+
+    /**
+     * The length of a square image `size` pixels wide, which must be a multiple of `Self::block_size`.
+     */
+    #[must_use]
+    pub const fn data_len(self, size: u32) -> u32 {
+        (size/self.block_size()).pow(2)*self.block_len()
+    }
+
+    #[must_use]
+    pub const fn is_supported(self, formats: CompressedImageFormats) -> bool {
+        match self {
+            Self::None => true,
+            Self::Bc7  => formats.contains(CompressedImageFormats::BC),
+        }
+    }
+
+    /**
+     * Encodes a square RGBA8 image `size` pixels wide into `dst`, which must be `Self::data_len` long.
+     */
+    pub fn encode(self, src: &[u8], size: u32, dst: &mut [u8]) {
+        let block_size = self.block_size() as usize;
+        let block_len  = self.block_len() as usize;
+        let size       = size as usize;
+        assert!(size.is_multiple_of(block_size), "Image isn't a whole number of blocks");
+        assert_eq!(dst.len(), self.data_len(size as u32) as usize);
+
+        let blocks = size/block_size;
+        for (i, dst) in dst.chunks_exact_mut(block_len).enumerate() {
+            let (x, y) = ((i % blocks)*block_size, (i / blocks)*block_size);
+            match self {
+                Self::None => dst.copy_from_slice(&src[(y*size + x)*4..][..4]),
+                Self::Bc7  => {
+                    let pixels = core::array::from_fn(|p| src[((y + p/4)*size + x + p%4)*4..][..4].try_into().unwrap());
+                    dst.copy_from_slice(&bc7_encode_block(&pixels));
+                },
+            }
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TileAtlasCompressionError {
+    #[error("tile atlas slots are {slot_size}px wide, which isn't a multiple of the {block_size}px blocks of {compression:?}")]
+    Unaligned {
+        compression: TileAtlasCompression,
+        slot_size:   u32,
+        block_size:  u32,
+    },
+    #[error("tile atlas pixel format {format:?} can't be encoded as {compression:?}")]
+    UnsupportedFormat {
+        compression: TileAtlasCompression,
+        format:      TileAtlasPixelFormat,
+    },
+}
+
+/**
+ * An atlas image encoded ahead of time by `TileAtlasBuilder::compress`, stored in the atlas file.
+ * - The tiles are stored alongside it, for devices that don't support the compression.
+ */
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct TileAtlasCompressedImage {
+    pub compression: TileAtlasCompression,
+    pub level_count: u32,
+    /// Set by any change to the tiles or their layout after compressing, so the image is encoded again.
+    pub dirty: bool,
+    pub data: Vec<u8>,
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * The number of mip levels in an image with the given `compression`, the levels where every slot covers whole blocks,
+     * so neighbouring tiles never share a block.
+     */
+    #[must_use]
+    pub fn compressed_level_count(&self, compression: TileAtlasCompression) -> u32 {
+        (0..self.find_mip_level_common_max()).take_while(|&level| self.mip_level_size(level).is_multiple_of(compression.block_size())).count() as u32
+    }
+
+    /**
+     * Encodes the atlas image ahead of time, which is stored in the atlas file so loading it doesn't encode it again.
+     * - Changes made after compressing are detected when building the image, which encodes it again.
+     * - `TileAtlasCompression::None` discards the encoded image.
+     */
+    pub fn compress(&mut self, compression: TileAtlasCompression) -> Result<(), TileAtlasCompressionError> {
+        self.compressed = None;
+        if compression == TileAtlasCompression::None { return Ok(()); }
+        if !compression.supports_format(self.format) {
+            return Err(TileAtlasCompressionError::UnsupportedFormat{ compression, format: self.format });
+        }
+
+        let level_count = self.compressed_level_count(compression);
+        if level_count == 0 {
+            return Err(TileAtlasCompressionError::Unaligned{ compression, slot_size: self.size, block_size: compression.block_size() });
+        }
+
+        let layout = self.build_layout();
+        let src    = self.build_image_data(&layout, level_count);
+        let data   = self.encode_image_data(compression, &src, layout.page_count(), level_count);
+        self.compressed = Some(TileAtlasCompressedImage{ compression, level_count, dirty: false, data });
+        Ok(())
+    }
+
+    /**
+     * Marks the image encoded by `Self::compress` as out of date, called by every change to the tiles or their layout.
+     */
+    pub(super) const fn mark_dirty(&mut self) {
+        if let Some(compressed) = &mut self.compressed {
+            compressed.dirty = true;
+        }
+    }
+
+    #[must_use]
+    pub const fn compressed(&self) -> Option<&TileAtlasCompressedImage> {
+        self.compressed.as_ref()
+    }
+
+    /**
+     * If the image encoded by `Self::compress` still matches the tiles, which it does until they or their layout change.
+     */
+    #[must_use]
+    pub fn is_compressed_current(&self) -> bool {
+        self.compressed.as_ref().is_some_and(|c| !c.dirty && c.level_count == self.compressed_level_count(c.compression))
+    }
+
+    /**
+     * Builds the atlas image with the given `compression`, using the image encoded by `Self::compress` when it's current.
+     * - Falls back to an uncompressed image when the slots aren't a whole number of blocks, see `Self::compressed_level_count`,
+     *   or the pixel format can't be encoded, see `TileAtlasCompression::supports_format`.
+     */
+    #[must_use]
+    pub fn build_image_with_compression(
+        &self,
+        compression:   TileAtlasCompression,
+        texture_label: Option<&'static str>,
+        texture_usage: TextureUsages,
+        asset_usage:   RenderAssetUsages,
+        sampler:       ImageSampler
+    ) -> Image {
+        let level_count = self.compressed_level_count(compression);
+        let format      = compression.texture_format(self.format).filter(|_| compression != TileAtlasCompression::None);
+        let Some(format) = format.filter(|_| level_count > 0) else {
+            return self.build_image_with_settings(texture_label, texture_usage, asset_usage, sampler);
+        };
+
+        let layout     = self.build_layout();
+        let page_count = layout.page_count();
+        let data = match &self.compressed {
+            Some(c) if c.compression == compression && c.level_count == level_count && !c.dirty => c.data.clone(),
+            _ => self.encode_image_data(compression, &self.build_image_data(&layout, level_count), page_count, level_count),
+        };
+
+        self.image_from_data(data, format, page_count, level_count, texture_label, texture_usage, asset_usage, sampler)
+    }
+
+    /**
+     * Encodes every page and level of the uncompressed image `src` in parallel.
+     */
+    fn encode_image_data(&self, compression: TileAtlasCompression, src: &[u8], page_count: u32, level_count: u32) -> Vec<u8> {
+        let page_size = |level: u32| self.page_size() >> level;
+        let total_len = (page_count as usize) * (0..level_count).map(|l| compression.data_len(page_size(l)) as usize).sum::<usize>();
+        let mut dst   = vec![0_u8; total_len];
+
+        let mut jobs = Vec::with_capacity((page_count*level_count) as usize);
+        let (mut src, mut rest) = (src, dst.as_mut_slice());
+        for _ in 0..page_count {
+            for level in 0..level_count {
+                let (src_page, src_next) = src.split_at(self.page_len(level) as usize);
+                let (dst_page, dst_next) = rest.split_at_mut(compression.data_len(page_size(level)) as usize);
+                jobs.push((level, src_page, dst_page));
+                (src, rest) = (src_next, dst_next);
+            }
+        }
+
+        jobs.par_splat_map_mut(compute_task_pool(), None, |_, jobs| {
+            for (level, src_page, dst_page) in jobs {
+                compression.encode(src_page, page_size(*level), dst_page);
+            }
+        });
+
+        dst
+    }
+
+}
@@ -0,0 +1,63 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use super::{TileAtlasBuilder, TileAtlasBuilderSet};
+
+/**
+ * The tiles that differ between two atlas builders, each list is sorted by (`group_id`, `tile_id`).
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TileAtlasBuilderDiff {
+    pub added:   Vec<(String, String)>,
+    pub removed: Vec<(String, String)>,
+    pub changed: Vec<(String, String)>,
+    /// Set when the tile sizes differ, in which case every shared tile is reported as changed.
+    pub size_changed: Option<(u32, u32)>,
+}
+
+impl TileAtlasBuilderDiff {
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.size_changed.is_none()
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Compares this builder against `other`, reporting the tiles `other` adds, removes or changes.
+     * - A tile has changed if the image data of any level or the length of any sequence differs.
+     */
+    #[must_use]
+    pub fn diff(&self, other: &TileAtlasBuilder) -> TileAtlasBuilderDiff {
+        let mut result = TileAtlasBuilderDiff {
+            size_changed: ((self.size, self.tile_size) != (other.size, other.tile_size)).then_some((self.tile_size, other.tile_size)),
+            ..TileAtlasBuilderDiff::default()
+        };
+
+        for (group_id, tile_id, set) in self.tiles() {
+            let key = (group_id.to_owned(), tile_id.to_owned());
+            match other.get_tile(group_id, tile_id) {
+                None => result.removed.push(key),
+                Some(other_set) if result.size_changed.is_some() || !tile_set_eq(set, other_set) => result.changed.push(key),
+                Some(_) => {},
+            }
+        }
+
+        result.added.extend(other.tiles()
+            .filter(|(group_id, tile_id, _)| self.get_tile(group_id, tile_id).is_none())
+            .map(|(group_id, tile_id, _)| (group_id.to_owned(), tile_id.to_owned()))
+        );
+
+        result.added.sort_unstable();
+        result.removed.sort_unstable();
+        result.changed.sort_unstable();
+        result
+    }
+
+}
+
+fn tile_set_eq(lhs: &TileAtlasBuilderSet, rhs: &TileAtlasBuilderSet) -> bool {
+    lhs.levels.len() == rhs.levels.len() && lhs.levels.iter().zip(rhs.levels.iter()).all(|(l, r)| l.data == r.data)
+}
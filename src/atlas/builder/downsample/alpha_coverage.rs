@@ -0,0 +1,114 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use half::f16;
+
+use crate::atlas::builder::TileAtlasPixelFormat;
+
+use super::DownsampleAlgorithm;
+
+/**
+ * Wraps another downsampler and rescales the alpha of the result, so the fraction of pixels
+ * with an alpha above `cutoff` matches the source level.
+ * - Keeps alpha masked tiles such as foliage from thinning out at lower mip levels.
+ * - When generating levels with `TileAtlasBuilder` the coverage of level 0 is matched instead.
+ * - Formats without alpha are left as the inner downsampler produced them.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct DownsampleAlphaCoverage<D> {
+    pub inner:  D,
+    /// Normalized alpha cutoff, from 0 to 1.
+    pub cutoff: f32,
+}
+
+impl<D> DownsampleAlphaCoverage<D> {
+
+    #[must_use]
+    pub const fn new(inner: D, cutoff: f32) -> Self {
+        Self{ inner, cutoff }
+    }
+
+}
+
+impl<D: DownsampleAlgorithm> DownsampleAlgorithm for DownsampleAlphaCoverage<D> {
+
+    fn downsample(
+        &self,
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        self.downsample_with_base(src, src, src_size, dst);
+    }
+
+    fn downsample_with_base(
+        &self,
+        base: &[u8],
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        self.downsample_format(TileAtlasPixelFormat::Rgba8Srgb, base, src, src_size, dst);
+    }
+
+    fn downsample_format(
+        &self,
+        format: TileAtlasPixelFormat,
+        base: &[u8],
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        self.inner.downsample_format(format, base, src, src_size, dst);
+        if !format.has_alpha() { return; }
+
+        let cutoff = self.cutoff*alpha_max(format);
+        let target = alpha_coverage(format, base, cutoff);
+
+        // Coverage only grows with the scale, search for the scale that best matches the target
+        let (mut low, mut high) = (0.0_f32, 255.0_f32);
+        for _ in 0..16 {
+            let scale = f32::midpoint(low, high);
+            if alpha_coverage_scaled(format, dst, cutoff, scale) < target { low = scale; } else { high = scale; }
+        }
+
+        // Ties keep the higher coverage, so thin details don't vanish
+        let scale = if (alpha_coverage_scaled(format, dst, cutoff, low) - target).abs() < (alpha_coverage_scaled(format, dst, cutoff, high) - target).abs() { low } else { high };
+        for pixel in dst.chunks_exact_mut(format.pixel_len() as usize) {
+            set_alpha(format, pixel, scale_alpha(format, get_alpha(format, pixel), scale));
+        }
+    }
+
+}
+
+/**
+ * The largest alpha in the units of `get_alpha`, alpha is compared as stored so 8 bit formats match exactly.
+ */
+const fn alpha_max(format: TileAtlasPixelFormat) -> f32 {
+    if format.is_float() { 1.0 } else { 255.0 }
+}
+
+fn get_alpha(format: TileAtlasPixelFormat, pixel: &[u8]) -> f32 {
+    if format.is_float() { f16::from_le_bytes([pixel[6], pixel[7]]).to_f32() } else { pixel[3] as f32 }
+}
+
+fn set_alpha(format: TileAtlasPixelFormat, pixel: &mut [u8], alpha: f32) {
+    if format.is_float() { pixel[6..8].copy_from_slice(&f16::from_f32(alpha).to_le_bytes()); } else { pixel[3] = alpha as u8; }
+}
+
+fn scale_alpha(format: TileAtlasPixelFormat, alpha: f32, scale: f32) -> f32 {
+    let alpha = alpha*scale;
+    if format.is_float() { alpha.min(1.0) } else { alpha.round().min(255.0) }
+}
+
+fn alpha_coverage(format: TileAtlasPixelFormat, data: &[u8], cutoff: f32) -> f32 {
+    alpha_coverage_scaled(format, data, cutoff, 1.0)
+}
+
+fn alpha_coverage_scaled(format: TileAtlasPixelFormat, data: &[u8], cutoff: f32, scale: f32) -> f32 {
+    let alphas = data.chunks_exact(format.pixel_len() as usize).map(|pixel| get_alpha(format, pixel));
+    let count  = alphas.clone().filter(|&a| scale_alpha(format, a, scale) > cutoff).count();
+    count as f32 / alphas.count().max(1) as f32
+}
@@ -1,8 +1,19 @@
 // Copyright 2025 Natalie Baker // Apache License v2 //
 
+use super::TileAtlasPixelFormat;
+
+mod alpha_coverage;
+pub use alpha_coverage::*;
+
 mod bilinear_srgb;
 pub use bilinear_srgb::*;
 
+mod nearest;
+pub use nearest::*;
+
+mod separable;
+pub use separable::*;
+
 pub trait DownsampleAlgorithm {
     fn downsample(
         &self,
@@ -11,5 +22,67 @@ pub trait DownsampleAlgorithm {
 
         dst: &mut [u8],
     );
+
+    /**
+     * Downsamples `src` knowing the level 0 image it was derived from, which is `src` itself at level 0.
+     * - Called by `TileAtlasBuilder` when generating levels, defaults to `Self::downsample`.
+     */
+    fn downsample_with_base(
+        &self,
+        base: &[u8],
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        let _ = base;
+        self.downsample(src, src_size, dst);
+    }
+
+    /**
+     * Downsamples `src` stored in the pixel `format`, knowing the level 0 image it was derived from.
+     * - Called by `TileAtlasBuilder` when generating levels, defaults to `Self::downsample_with_base`.
+     * - The default only supports `TileAtlasPixelFormat::Rgba8Srgb`, and panics for other formats.
+     */
+    fn downsample_format(
+        &self,
+        format: TileAtlasPixelFormat,
+        base: &[u8],
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        assert!(format == TileAtlasPixelFormat::Rgba8Srgb, "Downsampler doesn't support {format:?} tiles");
+        self.downsample_with_base(base, src, src_size, dst);
+    }
+}
+
+impl<T: DownsampleAlgorithm + ?Sized> DownsampleAlgorithm for &T {
+    fn downsample(&self, src: &[u8], src_size: usize, dst: &mut [u8]) {
+        (**self).downsample(src, src_size, dst);
+    }
+
+    fn downsample_with_base(&self, base: &[u8], src: &[u8], src_size: usize, dst: &mut [u8]) {
+        (**self).downsample_with_base(base, src, src_size, dst);
+    }
+
+    fn downsample_format(&self, format: TileAtlasPixelFormat, base: &[u8], src: &[u8], src_size: usize, dst: &mut [u8]) {
+        (**self).downsample_format(format, base, src, src_size, dst);
+    }
+}
+
+impl<T: DownsampleAlgorithm + ?Sized> DownsampleAlgorithm for std::sync::Arc<T> {
+    fn downsample(&self, src: &[u8], src_size: usize, dst: &mut [u8]) {
+        (**self).downsample(src, src_size, dst);
+    }
+
+    fn downsample_with_base(&self, base: &[u8], src: &[u8], src_size: usize, dst: &mut [u8]) {
+        (**self).downsample_with_base(base, src, src_size, dst);
+    }
+
+    fn downsample_format(&self, format: TileAtlasPixelFormat, base: &[u8], src: &[u8], src_size: usize, dst: &mut [u8]) {
+        (**self).downsample_format(format, base, src, src_size, dst);
+    }
 }
 
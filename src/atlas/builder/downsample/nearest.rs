@@ -0,0 +1,48 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use crate::atlas::builder::TileAtlasPixelFormat;
+
+use super::DownsampleAlgorithm;
+
+/**
+ * Halves a tile by keeping the top left pixel of every 2x2 block, keeping pixel art crisp.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DownsampleNearest;
+
+impl DownsampleAlgorithm for DownsampleNearest {
+
+    fn downsample(
+        &self,
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        self.downsample_format(TileAtlasPixelFormat::Rgba8Srgb, src, src, src_size, dst);
+    }
+
+    fn downsample_format(
+        &self,
+        format: TileAtlasPixelFormat,
+        _base: &[u8],
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        assert!(src_size.trailing_zeros() > 0);
+
+        let dst_size  = src_size.unbounded_shr(1);
+        let pixel_len = format.pixel_len() as usize;
+
+        for y in 0..dst_size {
+            for x in 0..dst_size {
+                let src_offset = ((x*2) + src_size*(y*2))*pixel_len;
+                let dst_offset = (y*dst_size + x)*pixel_len;
+                dst[dst_offset..dst_offset+pixel_len].copy_from_slice(&src[src_offset..src_offset+pixel_len]);
+            }
+        }
+    }
+
+}
@@ -0,0 +1,221 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use core::f32::consts::PI;
+
+use crate::atlas::builder::{color::{demul_alpha, premul_alpha, read_linear, write_linear}, TileAtlasPixelFormat};
+
+use super::DownsampleAlgorithm;
+
+/**
+ * A symmetric filter kernel used by `DownsampleSeparable`.
+ */
+pub trait DownsampleKernel {
+    /**
+     * The radius beyond which the kernel is zero, in destination pixels.
+     */
+    fn support(&self) -> f32;
+
+    /**
+     * The weight at distance `x` from the center, in destination pixels.
+     */
+    fn weight(&self, x: f32) -> f32;
+}
+
+/**
+ * Halves a tile with a separable `DownsampleKernel`, filtering in linear premultiplied space.
+ * - Samples beyond the edges of the tile are clamped to the edge, so neighbouring tiles never bleed in.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DownsampleSeparable<K>(pub K);
+
+pub type DownsampleLanczos3  = DownsampleSeparable<KernelLanczos3>;
+pub type DownsampleKaiser    = DownsampleSeparable<KernelKaiser>;
+pub type DownsampleMitchell  = DownsampleSeparable<KernelMitchell>;
+
+impl<K: DownsampleKernel> DownsampleAlgorithm for DownsampleSeparable<K> {
+
+    fn downsample(
+        &self,
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        self.downsample_format(TileAtlasPixelFormat::Rgba8Srgb, src, src, src_size, dst);
+    }
+
+    fn downsample_format(
+        &self,
+        format: TileAtlasPixelFormat,
+        _base: &[u8],
+        src: &[u8],
+        src_size: usize,
+
+        dst: &mut [u8],
+    ) {
+        assert!(src_size.trailing_zeros() > 0);
+
+        let dst_size = src_size.unbounded_shr(1);
+        let weights  = self.weights(src_size);
+
+        let src = (0..src_size*src_size)
+            .map(|i| premul_alpha(read_linear(format, src, i)))
+            .collect::<Vec<_>>();
+
+        // Rows are filtered first, into a dst_size by src_size buffer
+        let mut horizontal = vec![[0.0_f32; 4]; dst_size*src_size];
+        for y in 0..src_size {
+            for (x, taps) in weights.iter().enumerate() {
+                horizontal[y*dst_size + x] = convolve(taps.iter().map(|&(i, w)| (src[y*src_size + i], w)));
+            }
+        }
+
+        for (y, taps) in weights.iter().enumerate() {
+            for x in 0..dst_size {
+                let sample = convolve(taps.iter().map(|&(i, w)| (horizontal[i*dst_size + x], w)));
+                write_linear(format, dst, y*dst_size + x, clamp_sample(format, sample), true);
+            }
+        }
+    }
+
+}
+
+impl<K: DownsampleKernel> DownsampleSeparable<K> {
+
+    /**
+     * The normalized (`src_index`, `weight`) taps of every destination pixel along one axis.
+     */
+    fn weights(&self, src_size: usize) -> Vec<Vec<(usize, f32)>> {
+        let support = self.0.support()*2.0;
+        (0..src_size/2).map(|x| {
+            let center = (x*2 + 1) as f32;
+            let start  = (center - support).floor() as isize;
+            let end    = (center + support).ceil()  as isize;
+
+            let mut taps = (start..end)
+                .map(|i| (i.clamp(0, src_size as isize - 1) as usize, self.0.weight((i as f32 + 0.5 - center)/2.0)))
+                .filter(|&(_, w)| w != 0.0)
+                .collect::<Vec<_>>();
+
+            let total = taps.iter().map(|&(_, w)| w).sum::<f32>();
+            for (_, w) in &mut taps { *w /= total; }
+            taps
+        }).collect()
+    }
+
+}
+
+fn convolve(taps: impl Iterator<Item = ([f32; 4], f32)>) -> [f32; 4] {
+    taps.fold([0.0; 4], |acc, (v, w)| [0, 1, 2, 3].map(|c| v[c].mul_add(w, acc[c])))
+}
+
+fn clamp_sample(format: TileAtlasPixelFormat, v: [f32; 4]) -> [f32; 4] {
+    // Negative lobes can overshoot, fully transparent results have no meaningful colour
+    let alpha = v[3].clamp(0.0, 1.0);
+    if alpha <= 0.0 { return [0.0; 4]; }
+    demul_alpha([v[0], v[1], v[2], alpha]).map(|c| c.clamp(0.0, format.max_value()))
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 { return 1.0; }
+    let x = x*PI;
+    x.sin()/x
+}
+
+/**
+ * Windowed sinc with 3 lobes, sharp with slight ringing.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KernelLanczos3;
+
+impl DownsampleKernel for KernelLanczos3 {
+    fn support(&self) -> f32 {
+        3.0
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        if x.abs() >= 3.0 { return 0.0; }
+        sinc(x)*sinc(x/3.0)
+    }
+}
+
+/**
+ * Kaiser windowed sinc, `alpha` trades sharpness for less ringing.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct KernelKaiser {
+    pub alpha:   f32,
+    pub support: f32,
+}
+
+impl Default for KernelKaiser {
+    fn default() -> Self {
+        Self {
+            alpha:   4.0,
+            support: 3.0,
+        }
+    }
+}
+
+impl DownsampleKernel for KernelKaiser {
+    fn support(&self) -> f32 {
+        self.support
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let t = x/self.support;
+        if t.abs() >= 1.0 { return 0.0; }
+        sinc(x)*bessel_i0(self.alpha*(1.0 - t*t).sqrt())/bessel_i0(self.alpha)
+    }
+}
+
+/**
+ * Zeroth order modified Bessel function of the first kind.
+ */
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum  = 1.0;
+    let mut term = 1.0;
+    for k in 1..32 {
+        term *= (x/(2.0*k as f32)).powi(2);
+        sum  += term;
+        if term < sum*1e-8 { break; }
+    }
+    sum
+}
+
+/**
+ * Mitchell-Netravali cubic, the default `b` and `c` of 1/3 balance blur and ringing.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct KernelMitchell {
+    pub b: f32,
+    pub c: f32,
+}
+
+impl Default for KernelMitchell {
+    fn default() -> Self {
+        Self {
+            b: 1.0/3.0,
+            c: 1.0/3.0,
+        }
+    }
+}
+
+impl DownsampleKernel for KernelMitchell {
+    fn support(&self) -> f32 {
+        2.0
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let Self{ b, c } = *self;
+        let x = x.abs();
+        let result = if x < 1.0 {
+            (12.0 - 9.0*b - 6.0*c)*x*x*x + (-18.0 + 12.0*b + 6.0*c)*x*x + (6.0 - 2.0*b)
+        } else if x < 2.0 {
+            (-b - 6.0*c)*x*x*x + (6.0*b + 30.0*c)*x*x + (-12.0*b - 48.0*c)*x + (8.0*b + 24.0*c)
+        } else {
+            0.0
+        };
+        result/6.0
+    }
+}
@@ -0,0 +1,599 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use bevy::platform::hash::FixedHasher;
+use bitcode::Decode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{TileAtlasBuilder, TileAtlasBuilderGroup, TileAtlasLayout, TileAtlasLimitsError, TILE_ATLAS_PAGE_TILES_MAX};
+
+/**
+ * Magic bytes found at the start of every tile atlas file.
+ */
+pub const TILE_ATLAS_FILE_MAGIC: [u8; 4] = *b"SFA\x1A";
+
+/**
+ * The current version of the tile atlas file format, files of older
+ * versions are migrated when read.
+ * - Version 0 is the legacy headerless format, a raw bitcode stream that may be brotli compressed.
+ * - Version 1 introduced the container header, the header's tile size is the slot size including any padding and border.
+ * - The payload is the bitcode encoded builder, changes to it once released need a new version and a migration in `TileAtlasBuilder::decode_payload`.
+ */
+pub const TILE_ATLAS_FILE_VERSION: u16 = 1;
+
+/**
+ * The conventional file extension of a tile atlas file.
+ */
+pub const TILE_ATLAS_FILE_EXTENSION: &str = "sfa";
+
+/**
+ * Size of the tile atlas file header in bytes.
+ */
+pub const TILE_ATLAS_FILE_HEADER_LEN: usize = 32;
+
+const COMPRESSION_BUFFER_SIZE: usize = 8*1024*1024; // 8 MiB
+const COMPRESSION_QUALITY:     u32   = 7;
+const COMPRESSION_WINDOW:      u32   = 24;
+
+#[derive(Debug, Error)]
+pub enum TileAtlasBuilderReadError {
+    #[error("failed to read tile atlas: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to decode tile atlas: {0}")]
+    Decode(#[from] bitcode::Error),
+
+    #[error("data is not a tile atlas file")]
+    UnrecognisedFormat,
+
+    #[error("tile atlas header is truncated, expected {TILE_ATLAS_FILE_HEADER_LEN} bytes but found {0}")]
+    TruncatedHeader(usize),
+
+    #[error("tile atlas file version {0} is newer than the supported version {TILE_ATLAS_FILE_VERSION}")]
+    UnsupportedVersion(u16),
+
+    #[error("tile atlas uses unknown compression codec {0}")]
+    UnknownCodec(u8),
+
+    #[error("tile atlas payload is truncated, expected {expected} bytes but found {found}")]
+    TruncatedPayload {
+        expected: u64,
+        found:    u64,
+    },
+
+    #[error("tile atlas checksum mismatch, expected {expected:#010x} but found {found:#010x}")]
+    ChecksumMismatch {
+        expected: u32,
+        found:    u32,
+    },
+
+    #[error("tile atlas header doesn't match its contents, {field} is {expected} in the header but {found} in the payload")]
+    HeaderMismatch {
+        field:    &'static str,
+        expected: u32,
+        found:    u32,
+    },
+
+    #[error("tile atlas has {0} tiles per page side, expected 1 to {TILE_ATLAS_PAGE_TILES_MAX}")]
+    InvalidPageTiles(u32),
+
+    #[error("tile atlas has {size}px slots, which can't hold a {tile_size}px tile with a {border}px border{}", if *padded { " padded to a power of two" } else { "" })]
+    InvalidSlotSize {
+        size:      u32,
+        tile_size: u32,
+        border:    u32,
+        padded:    bool,
+    },
+
+    #[error("tile atlas pages of {page_tiles}x{page_tiles} slots {size}px wide are too large to address")]
+    InvalidPageSize {
+        size:       u32,
+        page_tiles: u32,
+    },
+
+    #[error("tile \"{group_id}/{tile_id}\" has {found} mip levels, expected {expected}")]
+    InvalidLevelCount {
+        group_id: String,
+        tile_id:  String,
+        expected: usize,
+        found:    usize,
+    },
+
+    #[error("tile \"{group_id}/{tile_id}\" has {found} frames in mip level {level}, more than the {expected} frames of mip level 0")]
+    InvalidFrameCount {
+        group_id: String,
+        tile_id:  String,
+        level:    usize,
+        expected: usize,
+        found:    usize,
+    },
+
+    #[error("frame {frame} of tile \"{group_id}/{tile_id}\" is {found} bytes in mip level {level}, expected {expected}")]
+    InvalidFrameLen {
+        group_id: String,
+        tile_id:  String,
+        level:    usize,
+        frame:    usize,
+        expected: usize,
+        found:    usize,
+    },
+
+    #[error("tile atlas compressed image is {found} bytes, expected {expected}")]
+    InvalidCompressedLen {
+        expected: usize,
+        found:    usize,
+    },
+
+    #[error("tile atlas can't be laid out, {0}")]
+    Layout(#[from] TileAtlasLimitsError),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum TileAtlasFileCodec {
+    None   = 0,
+    #[default]
+    Brotli = 1,
+}
+
+impl TileAtlasFileCodec {
+
+    #[must_use]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::None),
+            1 => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+
+}
+
+/**
+ * The fixed-size header at the start of a tile atlas file.
+ * - All values are stored little-endian.
+ * - The checksum is the CRC-32 of the payload as stored, ie. after compression.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileAtlasFileHeader {
+    pub version:     u16,
+    pub codec:       TileAtlasFileCodec,
+    pub tile_size:   u32,
+    pub page_count:  u32,
+    pub tile_count:  u32,
+    pub checksum:    u32,
+    pub payload_len: u64,
+}
+
+impl TileAtlasFileHeader {
+
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; TILE_ATLAS_FILE_HEADER_LEN] {
+        let mut dst = [0_u8; TILE_ATLAS_FILE_HEADER_LEN];
+        dst[ 0.. 4].copy_from_slice(&TILE_ATLAS_FILE_MAGIC);
+        dst[ 4.. 6].copy_from_slice(&self.version.to_le_bytes());
+        dst[ 6] = self.codec as u8;
+        dst[ 7] = 0; // Reserved
+        dst[ 8..12].copy_from_slice(&self.tile_size.to_le_bytes());
+        dst[12..16].copy_from_slice(&self.page_count.to_le_bytes());
+        dst[16..20].copy_from_slice(&self.tile_count.to_le_bytes());
+        dst[20..24].copy_from_slice(&self.checksum.to_le_bytes());
+        dst[24..32].copy_from_slice(&self.payload_len.to_le_bytes());
+        dst
+    }
+
+    /**
+     * Parses a header from the start of `src`.
+     * - Returns `Ok(None)` if `src` doesn't start with the file magic, ie. it's a legacy file.
+     */
+    pub fn from_bytes(src: &[u8]) -> Result<Option<Self>, TileAtlasBuilderReadError> {
+        if !src.starts_with(&TILE_ATLAS_FILE_MAGIC) { return Ok(None); }
+        if src.len() < TILE_ATLAS_FILE_HEADER_LEN { return Err(TileAtlasBuilderReadError::TruncatedHeader(src.len())); }
+
+        let version = u16::from_le_bytes([src[4], src[5]]);
+        if version > TILE_ATLAS_FILE_VERSION { return Err(TileAtlasBuilderReadError::UnsupportedVersion(version)); }
+
+        let codec = TileAtlasFileCodec::from_u8(src[6]).ok_or(TileAtlasBuilderReadError::UnknownCodec(src[6]))?;
+        let read_u32 = |o: usize| u32::from_le_bytes(src[o..o+4].try_into().unwrap());
+
+        Ok(Some(Self {
+            version,
+            codec,
+            tile_size:   read_u32(8),
+            page_count:  read_u32(12),
+            tile_count:  read_u32(16),
+            checksum:    read_u32(20),
+            payload_len: u64::from_le_bytes(src[24..32].try_into().unwrap()),
+        }))
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Writes the builder as a tile atlas file, compressing the payload with the given `codec`.
+     * - Fails when the tiles can't be laid out, see `Self::try_build_layout`.
+     */
+    pub fn write_to(&self, mut sink: impl Write, codec: TileAtlasFileCodec) -> Result<(), std::io::Error> {
+        let payload = bitcode::encode(self);
+        let payload = match codec {
+            TileAtlasFileCodec::None   => payload,
+            TileAtlasFileCodec::Brotli => {
+                let mut dst = Vec::with_capacity(payload.len()/2);
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut dst, COMPRESSION_BUFFER_SIZE, COMPRESSION_QUALITY, COMPRESSION_WINDOW);
+                    writer.write_all(&payload)?;
+                    writer.flush()?;
+                }
+                dst
+            },
+        };
+
+        let layout = self.try_build_layout().map_err(std::io::Error::other)?;
+        let header = TileAtlasFileHeader {
+            version:     TILE_ATLAS_FILE_VERSION,
+            codec,
+            tile_size:   self.size,
+            page_count:  layout.page_count(),
+            tile_count:  self.image_count(),
+            checksum:    crc32fast::hash(&payload),
+            payload_len: payload.len() as u64,
+        };
+
+        sink.write_all(&header.to_bytes())?;
+        sink.write_all(&payload)
+    }
+
+    pub fn write_compressed_to(&self, sink: impl Write) -> Result<(), std::io::Error> {
+        self.write_to(sink, TileAtlasFileCodec::Brotli)
+    }
+
+    pub fn write_uncompressed_to(&self, sink: impl Write) -> Result<(), std::io::Error> {
+        self.write_to(sink, TileAtlasFileCodec::None)
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Reads a tile atlas file, detecting its codec and migrating older versions of the format.
+     */
+    pub fn read_from(mut source: impl Read) -> Result<Self, TileAtlasBuilderReadError> {
+        let mut src = Vec::new();
+        source.read_to_end(&mut src)?;
+        Self::read_from_bytes(&src)
+    }
+
+    /**
+     * Reads a tile atlas file from a byte slice, see `Self::read_from`.
+     */
+    pub fn read_from_bytes(src: &[u8]) -> Result<Self, TileAtlasBuilderReadError> {
+        let Some(header) = TileAtlasFileHeader::from_bytes(src)? else {
+            return Self::read_legacy(src);
+        };
+
+        let payload = &src[TILE_ATLAS_FILE_HEADER_LEN..];
+        if (payload.len() as u64) < header.payload_len {
+            return Err(TileAtlasBuilderReadError::TruncatedPayload{ expected: header.payload_len, found: payload.len() as u64 });
+        }
+        let payload = &payload[..header.payload_len as usize];
+
+        let checksum = crc32fast::hash(payload);
+        if checksum != header.checksum {
+            return Err(TileAtlasBuilderReadError::ChecksumMismatch{ expected: header.checksum, found: checksum });
+        }
+
+        let payload = match header.codec {
+            TileAtlasFileCodec::None   => Vec::from(payload),
+            TileAtlasFileCodec::Brotli => {
+                let mut dst = Vec::with_capacity(payload.len()*2);
+                brotli::Decompressor::new(payload, COMPRESSION_BUFFER_SIZE).read_to_end(&mut dst)?;
+                dst
+            },
+        };
+
+        let builder = Self::decode_payload(header.version, &payload)?;
+        let layout  = builder.validate_payload()?;
+        builder.validate_header(&header, &layout)?;
+        Ok(builder)
+    }
+
+    #[deprecated(note = "use `TileAtlasBuilder::read_from`, which detects the codec")]
+    pub fn read_compressed_from(source: impl Read) -> Result<Self, TileAtlasBuilderReadError> {
+        Self::read_from(source)
+    }
+
+    #[deprecated(note = "use `TileAtlasBuilder::read_from`, which detects the codec")]
+    pub fn read_uncompressed_from(source: impl Read) -> Result<Self, TileAtlasBuilderReadError> {
+        Self::read_from(source)
+    }
+
+    /**
+     * Reads only the header of a tile atlas file.
+     * - Returns `Ok(None)` for legacy files, which have no header.
+     */
+    pub fn read_header_from(mut source: impl Read) -> Result<Option<TileAtlasFileHeader>, TileAtlasBuilderReadError> {
+        let mut src = Vec::with_capacity(TILE_ATLAS_FILE_HEADER_LEN);
+        source.by_ref().take(TILE_ATLAS_FILE_HEADER_LEN as u64).read_to_end(&mut src)?;
+        TileAtlasFileHeader::from_bytes(&src)
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Decodes a payload of the given format `version`, migrating it to the current version.
+     */
+    fn decode_payload(version: u16, payload: &[u8]) -> Result<Self, TileAtlasBuilderReadError> {
+        match version {
+            0 => Ok(bitcode::decode::<TileAtlasBuilderLegacy>(payload)?.into()),
+            1 => Ok(bitcode::decode(payload)?),
+            _ => Err(TileAtlasBuilderReadError::UnsupportedVersion(version)),
+        }
+    }
+
+    /**
+     * Reads a version 0 file, which may either be a brotli compressed or raw bitcode stream.
+     */
+    fn read_legacy(src: &[u8]) -> Result<Self, TileAtlasBuilderReadError> {
+        let mut decompressed = Vec::with_capacity(src.len()*2);
+        let builder = brotli::Decompressor::new(src, COMPRESSION_BUFFER_SIZE).read_to_end(&mut decompressed).ok()
+            .and_then(|_| Self::decode_payload(0, &decompressed).ok());
+        let builder = match builder {
+            Some(builder) => builder,
+            None => Self::decode_payload(0, src).map_err(|_| TileAtlasBuilderReadError::UnrecognisedFormat)?,
+        };
+        builder.validate_payload()?;
+        Ok(builder)
+    }
+
+    /**
+     * Checks a decoded payload is a builder that can be built without panicking, returning its layout.
+     * - The slots must hold the tiles and their border, every tile must have a sequence for each mip level,
+     *   and every frame must be the size of a slot in its level.
+     */
+    fn validate_payload(&self) -> Result<TileAtlasLayout, TileAtlasBuilderReadError> {
+        if !(1..=TILE_ATLAS_PAGE_TILES_MAX).contains(&self.page_tiles) {
+            return Err(TileAtlasBuilderReadError::InvalidPageTiles(self.page_tiles));
+        }
+
+        let content = self.border.checked_mul(2).and_then(|border| border.checked_add(self.tile_size));
+        if self.tile_size == 0 || content.is_none_or(|content| content > self.size) || (self.padded && !self.size.is_power_of_two()) {
+            return Err(TileAtlasBuilderReadError::InvalidSlotSize{ size: self.size, tile_size: self.tile_size, border: self.border, padded: self.padded });
+        }
+
+        // Page lengths are computed as `u32`, so the largest level of a page must fit
+        let page_len = self.size.checked_mul(self.page_tiles).and_then(|size| size.checked_mul(size)).and_then(|len| len.checked_mul(self.format.pixel_len()));
+        if page_len.is_none() {
+            return Err(TileAtlasBuilderReadError::InvalidPageSize{ size: self.size, page_tiles: self.page_tiles });
+        }
+
+        let level_count = self.mip_levels_max() as usize;
+        for (group_id, tile_id, set) in self.tiles() {
+            if set.levels.len() != level_count {
+                return Err(TileAtlasBuilderReadError::InvalidLevelCount{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned(), expected: level_count, found: set.levels.len() });
+            }
+
+            let frame_count = set.levels[0].data.len();
+            for (level, sequence) in set.levels.iter().enumerate() {
+                if sequence.data.len() > frame_count {
+                    return Err(TileAtlasBuilderReadError::InvalidFrameCount{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned(), level, expected: frame_count, found: sequence.data.len() });
+                }
+
+                let expected = self.mip_level_data_len(level as u32) as usize;
+                if let Some((frame, data)) = sequence.data.iter().enumerate().find(|(_, data)| data.len() != expected) {
+                    return Err(TileAtlasBuilderReadError::InvalidFrameLen{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned(), level, frame, expected, found: data.len() });
+                }
+            }
+        }
+
+        let layout = self.try_build_layout()?;
+        if let Some(compressed) = self.compressed().filter(|_| self.is_compressed_current()) {
+            let page_len = |level: u32| compressed.compression.data_len(self.page_size() >> level) as usize;
+            let expected = layout.page_count() as usize * (0..compressed.level_count).map(page_len).sum::<usize>();
+            if compressed.data.len() != expected {
+                return Err(TileAtlasBuilderReadError::InvalidCompressedLen{ expected, found: compressed.data.len() });
+            }
+        }
+
+        Ok(layout)
+    }
+
+    fn validate_header(&self, header: &TileAtlasFileHeader, layout: &TileAtlasLayout) -> Result<(), TileAtlasBuilderReadError> {
+        let fields = [
+            ("tile size",  header.tile_size,  self.size            ),
+            ("page count", header.page_count, layout.page_count()  ),
+            ("tile count", header.tile_count, self.image_count()   ),
+        ];
+
+        for (field, expected, found) in fields {
+            if expected != found {
+                return Err(TileAtlasBuilderReadError::HeaderMismatch{ field, expected, found });
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+/**
+ * The payload of version 0 files, which predate the container header.
+ */
+#[derive(Decode)]
+struct TileAtlasBuilderLegacy {
+    size:   u32,
+    groups: HashMap<String, TileAtlasBuilderGroup, FixedHasher>,
+}
+
+impl From<TileAtlasBuilderLegacy> for TileAtlasBuilder {
+    fn from(value: TileAtlasBuilderLegacy) -> Self {
+        Self {
+            groups: value.groups,
+            ..Self::new(value.size)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use bevy::platform::hash::FixedHasher;
+    use bitcode::Encode;
+
+    use super::*;
+    use crate::atlas::builder::{TileAtlasBuilderSet, TILE_ATLAS_PAGE_TILES};
+
+    fn builder() -> TileAtlasBuilder {
+        let src = (0..4*4*4*2).map(|i| i as u8).collect::<Vec<_>>();
+        let mut builder = TileAtlasBuilder::new(4);
+        builder.insert_single("walls", "brick", 0, &src, 8, [0, 0]);
+        builder.insert_single("walls", "stone", 0, &src, 8, [4, 0]);
+        builder.pin("walls", "stone", 7);
+        builder
+    }
+
+    fn write(builder: &TileAtlasBuilder, codec: TileAtlasFileCodec) -> Vec<u8> {
+        let mut dst = Vec::new();
+        builder.write_to(&mut dst, codec).unwrap();
+        dst
+    }
+
+    #[test]
+    fn round_trip() {
+        let builder = builder();
+        for codec in [TileAtlasFileCodec::None, TileAtlasFileCodec::Brotli] {
+            let data = write(&builder, codec);
+            let header = TileAtlasFileHeader::from_bytes(&data).unwrap().unwrap();
+            assert_eq!(header.version, TILE_ATLAS_FILE_VERSION);
+            assert_eq!(header.codec, codec);
+            assert_eq!(header.tile_size, 4);
+            assert_eq!(header.tile_count, 2);
+            assert_eq!(header.payload_len as usize, data.len() - TILE_ATLAS_FILE_HEADER_LEN);
+
+            let read = TileAtlasBuilder::read_from(data.as_slice()).unwrap();
+            assert_eq!(bitcode::encode(&read), bitcode::encode(&builder));
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_readers() {
+        let builder = builder();
+        let compressed   = TileAtlasBuilder::read_compressed_from(write(&builder, TileAtlasFileCodec::Brotli).as_slice()).unwrap();
+        let uncompressed = TileAtlasBuilder::read_uncompressed_from(write(&builder, TileAtlasFileCodec::None).as_slice()).unwrap();
+        assert_eq!(bitcode::encode(&compressed),   bitcode::encode(&builder));
+        assert_eq!(bitcode::encode(&uncompressed), bitcode::encode(&builder));
+    }
+
+    #[test]
+    fn legacy_migration() {
+        #[derive(Encode)]
+        struct Legacy {
+            size:   u32,
+            groups: HashMap<String, TileAtlasBuilderGroup, FixedHasher>,
+        }
+
+        let tile = TileAtlasBuilderSet::new(3);
+        let mut group = TileAtlasBuilderGroup::default();
+        group.tile_sets.insert("brick".to_owned(), tile);
+        let legacy = bitcode::encode(&Legacy{ size: 4, groups: [("walls".to_owned(), group)].into_iter().collect() });
+
+        let mut compressed = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut compressed, COMPRESSION_BUFFER_SIZE, COMPRESSION_QUALITY, COMPRESSION_WINDOW);
+            writer.write_all(&legacy).unwrap();
+        }
+
+        for data in [&legacy, &compressed] {
+            assert_eq!(TileAtlasBuilder::read_header_from(data.as_slice()).unwrap(), None);
+            let read = TileAtlasBuilder::read_from(data.as_slice()).unwrap();
+            assert_eq!(read.slot_size(), 4);
+            assert_eq!(read.page_tiles(), TILE_ATLAS_PAGE_TILES);
+            assert!(read.get_tile("walls", "brick").is_some());
+        }
+    }
+
+    #[test]
+    fn unrecognised() {
+        let result = TileAtlasBuilder::read_from([0xFF_u8; 64].as_slice());
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::UnrecognisedFormat)));
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut data = write(&builder(), TileAtlasFileCodec::None);
+        *data.last_mut().unwrap() ^= 0xFF;
+        let result = TileAtlasBuilder::read_from(data.as_slice());
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::ChecksumMismatch{ .. })));
+    }
+
+    #[test]
+    fn header_validation() {
+        let data = write(&builder(), TileAtlasFileCodec::Brotli);
+
+        let result = TileAtlasBuilder::read_from(&data[..TILE_ATLAS_FILE_HEADER_LEN/2]);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::TruncatedHeader(16))));
+
+        let result = TileAtlasBuilder::read_from(&data[..data.len() - 1]);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::TruncatedPayload{ .. })));
+
+        let mut version = data.clone();
+        version[4..6].copy_from_slice(&(TILE_ATLAS_FILE_VERSION + 1).to_le_bytes());
+        let result = TileAtlasBuilder::read_from(version.as_slice());
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::UnsupportedVersion(v)) if v == TILE_ATLAS_FILE_VERSION + 1));
+
+        let mut codec = data.clone();
+        codec[6] = 0xFF;
+        let result = TileAtlasBuilder::read_from(codec.as_slice());
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::UnknownCodec(0xFF))));
+
+        let mut tile_size = data;
+        tile_size[8..12].copy_from_slice(&8_u32.to_le_bytes());
+        let result = TileAtlasBuilder::read_from(tile_size.as_slice());
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::HeaderMismatch{ field: "tile size", expected: 8, found: 4 })));
+    }
+
+    #[test]
+    fn payload_validation() {
+        fn tile(builder: &mut TileAtlasBuilder) -> &mut TileAtlasBuilderSet {
+            builder.groups.get_mut("walls").unwrap().tile_sets.get_mut("brick").unwrap()
+        }
+        // Each payload is written with a valid header and checksum, so only its structure is wrong
+        let read = |builder: &TileAtlasBuilder| TileAtlasBuilder::read_from(write(builder, TileAtlasFileCodec::None).as_slice());
+
+        let mut frame_len = builder();
+        tile(&mut frame_len).levels[0].data[0] = Box::new([0; 3]);
+        let result = read(&frame_len);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::InvalidFrameLen{ level: 0, frame: 0, expected: 64, found: 3, .. })));
+
+        let mut frame_count = builder();
+        tile(&mut frame_count).levels[1].data = vec![Box::new([0; 16]); 2];
+        let result = read(&frame_count);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::InvalidFrameCount{ level: 1, expected: 1, found: 2, .. })));
+
+        let mut level_count = builder();
+        tile(&mut level_count).levels = Box::new([]);
+        let result = read(&level_count);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::InvalidLevelCount{ expected: 3, found: 0, .. })));
+
+        let mut slot_size = builder();
+        slot_size.border = 1;
+        let result = read(&slot_size);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::InvalidSlotSize{ size: 4, tile_size: 4, border: 1, .. })));
+
+        let mut page_size = builder();
+        page_size.size = u32::MAX;
+        let result = read(&page_size);
+        assert!(matches!(result, Err(TileAtlasBuilderReadError::InvalidPageSize{ .. })));
+    }
+
+}
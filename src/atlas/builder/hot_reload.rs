@@ -0,0 +1,217 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::HashMap;
+
+use bevy::{image::{CompressedImageFormatSupport, CompressedImageFormats}, platform::hash::FixedHasher, prelude::*, render::renderer::RenderDevice};
+
+use crate::atlas::{builder::{TileAtlasBuildQueueManifestItem, TileAtlasBuildSettings, TileAtlasBuilder, TileAtlasLimits, TileAtlasManifestError, TileAtlasManifestMips, TileAtlasSheetRect, TileSetSettings}, TileAtlas, TileAtlasPatches};
+
+/**
+ * The source images a tile was built from, kept to rebuild the tile when they change.
+ */
+#[derive(Debug, Clone)]
+pub enum TileAtlasSource {
+    Image {
+        handle:   Handle<Image>,
+        settings: TileSetSettings,
+        mips:     TileAtlasManifestMips,
+    },
+    Manifest(TileAtlasBuildQueueManifestItem),
+    Sheet {
+        handle: Handle<Image>,
+        /// The rects of the tile's frames in the sheet.
+        frames: Vec<TileAtlasSheetRect>,
+        mips:   TileAtlasManifestMips,
+    },
+}
+
+impl TileAtlasSource {
+
+    pub fn handles(&self) -> impl Iterator<Item = &Handle<Image>> {
+        let (image, manifest) = match self {
+            Self::Image{ handle, .. } | Self::Sheet{ handle, .. } => (Some(handle), None),
+            Self::Manifest(item)      => (None, Some(item.handles.values())),
+        };
+        image.into_iter().chain(manifest.into_iter().flatten())
+    }
+
+    /**
+     * Replaces every level of the tile in the `builder` with freshly sliced source images, downsampled with the `build_settings`.
+     * - Returns `Ok(false)` if any source image isn't loaded.
+     * - The tile is sliced into a scratch builder first, so it's left untouched when slicing fails.
+     */
+    pub fn reload(
+        &self,
+        builder:  &mut TileAtlasBuilder,
+        group_id: &str,
+        tile_id:  &str,
+        images:   &Assets<Image>,
+        build_settings: &TileAtlasBuildSettings,
+    ) -> Result<bool, TileAtlasManifestError> {
+        if !self.handles().all(|h| images.contains(h)) { return Ok(false); }
+
+        // Images are converted to the builder's pixel format up front, so they can be borrowed while inserting
+        let format    = builder.format();
+        let converted = self.handles()
+            .map(|handle| {
+                let image = images.get(handle).unwrap();
+                let data  = format.image_data(image).ok_or(TileAtlasManifestError::ImageFormat(format))?;
+                Ok((handle.id(), (data, [image.width(), image.height()])))
+            })
+            .collect::<Result<HashMap<_, _, FixedHasher>, TileAtlasManifestError>>()?;
+        let image_data = |handle: &Handle<Image>| converted.get(&handle.id()).map(|(data, size)| (&**data, *size));
+
+        let mut scratch = builder.empty_like();
+        match self {
+            Self::Image{ handle, settings, mips } => {
+                let (src, src_size) = image_data(handle).unwrap();
+                settings.check_bounds(scratch.tile_size(), src_size)?;
+                scratch.insert_tileset(group_id, tile_id, 0, src, src_size[0], *settings);
+                build_settings.apply_mips(*mips, &mut scratch, group_id, tile_id);
+            },
+            Self::Manifest(item) => {
+                item.tile.insert_into(&mut scratch, group_id, tile_id, |path| image_data(item.handles.get(path)?))?;
+                build_settings.apply_mips(item.mips, &mut scratch, group_id, tile_id);
+            },
+            Self::Sheet{ handle, frames, mips } => {
+                let (src, src_size) = image_data(handle).unwrap();
+                scratch.insert_rects(group_id, tile_id, 0, src, src_size[0], &frames.iter().collect::<Vec<_>>())?;
+                build_settings.apply_mips(*mips, &mut scratch, group_id, tile_id);
+            },
+        }
+
+        let set = scratch.remove(group_id, tile_id).ok_or_else(|| TileAtlasManifestError::EmptyTile{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned() })?;
+        builder.insert_set(group_id, tile_id, set);
+        Ok(true)
+    }
+
+}
+
+/**
+ * The builders and sources of atlases created by a `TileAtlasBuildQueue` with hot reloading enabled.
+ */
+#[derive(Debug, Default, Resource)]
+pub struct TileAtlasHotReload {
+    atlases: HashMap<AssetId<TileAtlas>, TileAtlasHotReloadEntry, FixedHasher>,
+}
+
+#[derive(Debug)]
+struct TileAtlasHotReloadEntry {
+    builder: TileAtlasBuilder,
+    sources: HashMap<String, HashMap<String, TileAtlasSource, FixedHasher>, FixedHasher>,
+    settings: TileAtlasBuildSettings,
+}
+
+impl TileAtlasHotReload {
+
+    /**
+     * Starts watching the `sources` of an atlas, the `builder` and `settings` must be the ones the atlas was built with.
+     */
+    pub fn insert(
+        &mut self,
+        atlas:   AssetId<TileAtlas>,
+        builder: TileAtlasBuilder,
+        sources: HashMap<String, HashMap<String, TileAtlasSource, FixedHasher>, FixedHasher>,
+        settings: TileAtlasBuildSettings,
+    ) {
+        self.atlases.insert(atlas, TileAtlasHotReloadEntry{ builder, sources, settings });
+    }
+
+    pub fn remove(&mut self, atlas: AssetId<TileAtlas>) {
+        self.atlases.remove(&atlas);
+    }
+
+}
+
+/**
+ * Rebuilds the tiles of watched atlases whose source images were modified.
+ * - Tiles with the same sequence length are written to the existing texture with `TileAtlasPatches`.
+ * - Otherwise the atlas is rebuilt with every other tile pinned to its existing index, as are tiles sharing slots with another tile.
+ */
+pub fn hot_reload_tile_atlas_sources(
+    mut e_images:  MessageReader<AssetEvent<Image>>,
+    mut e_atlas:   MessageReader<AssetEvent<TileAtlas>>,
+    mut r_reload:  ResMut<TileAtlasHotReload>,
+    mut r_images:  ResMut<Assets<Image>>,
+    mut r_atlas:   ResMut<Assets<TileAtlas>>,
+    mut r_patches: ResMut<TileAtlasPatches>,
+    r_device:  Option<Res<RenderDevice>>,
+    r_support: Option<Res<CompressedImageFormatSupport>>,
+) {
+    let supported = r_support.map_or(CompressedImageFormats::NONE, |support| support.0);
+    for event in e_atlas.read() {
+        if let AssetEvent::Removed{ id } = event {
+            r_reload.remove(*id);
+        }
+    }
+
+    let modified = e_images.read()
+        .filter_map(|e| if let AssetEvent::Modified{ id } = e { Some(*id) } else { None })
+        .collect::<Vec<_>>();
+    if modified.is_empty() || r_reload.atlases.is_empty() { return; }
+
+    for (&atlas_id, entry) in &mut r_reload.atlases {
+        let Some(atlas) = r_atlas.get(atlas_id) else { continue; };
+
+        let affected = entry.sources.iter()
+            .flat_map(|(group_id, group)| group.iter().map(move |(tile_id, source)| (group_id, tile_id, source)))
+            .filter(|(_, _, source)| source.handles().any(|h| modified.contains(&h.id())))
+            .collect::<Vec<_>>();
+        if affected.is_empty() { continue; }
+
+        let layout = entry.builder.build_layout();
+        let mut relayout = false;
+        let mut moved    = Vec::new();
+
+        for (group_id, tile_id, source) in affected {
+            match source.reload(&mut entry.builder, group_id, tile_id, &r_images, &entry.settings) {
+                Ok(true) => {},
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("Failed to reload tile atlas tile: {err}");
+                    continue;
+                },
+            }
+
+            if let Some(limit_levels) = entry.settings.limit_levels {
+                entry.builder.remove_levels(group_id, tile_id, limit_levels..u32::MAX);
+            }
+
+            let old_entry = layout.get(group_id, tile_id);
+            let set       = entry.builder.get_tile(group_id, tile_id);
+            match (old_entry, set) {
+                (Some(_), Some(_)) if layout.is_shared(group_id, tile_id) => {
+                    relayout = true;
+                    moved.push((group_id, tile_id));
+                },
+                (Some(old_entry), Some(set)) if old_entry.count as usize == set.levels[0].data.len() => {
+                    r_patches.write_builder_tile(atlas, old_entry, set);
+                },
+                (Some(old_entry), Some(set)) if (old_entry.count as usize) < set.levels[0].data.len() => {
+                    relayout = true;
+                    moved.push((group_id, tile_id));
+                },
+                _ => relayout = true,
+            }
+        }
+
+        if relayout {
+            // Grown tiles would push their neighbours out of place, and shared slots would change their other tiles, so they move instead
+            entry.builder.pin_all(&layout.to_index_manifest());
+            for (group_id, tile_id) in moved {
+                entry.builder.unpin(group_id, tile_id);
+            }
+
+            if let Err(err) = entry.settings.apply_limits(&mut entry.builder, r_device.as_deref().map(TileAtlasLimits::from_render_device)) {
+                error!("Failed to rebuild tile atlas: {err}");
+                continue;
+            }
+
+            let image          = entry.settings.build_image(&entry.builder, supported);
+            let texture_format = image.texture_descriptor.format;
+            let atlas = TileAtlas::from_builder(r_images.add(image), &entry.builder)
+                .with_texture_format(texture_format);
+            r_atlas.insert(atlas_id, atlas).unwrap();
+        }
+    }
+}
@@ -0,0 +1,495 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use core::hash::BuildHasher;
+use core::ops::Range;
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::platform::hash::FixedHasher;
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use super::{TileAtlasBuilder, TileAtlasBuilderSet, TileAtlasLimitsError};
+use crate::atlas::TileAtlasEntry;
+
+/**
+ * The conventional file extension of a tile atlas index manifest.
+ */
+pub const TILE_ATLAS_INDEX_MANIFEST_EXTENSION: &str = "atlas.index.ron";
+
+/**
+ * The number of addressable slots in an atlas, the last slot value is reserved for the empty tile.
+ */
+pub const TILE_ATLAS_SLOT_COUNT: u32 = u16::MAX as u32;
+
+/**
+ * The default number of tiles along each side of a page.
+ */
+pub const TILE_ATLAS_PAGE_TILES: u32 = 16;
+
+/**
+ * The largest number of tiles along each side of a page, which fits every slot on a single page.
+ */
+pub const TILE_ATLAS_PAGE_TILES_MAX: u32 = 256;
+
+/**
+ * Records the slot index of every tile in an atlas, so later builds can keep
+ * existing tiles in place and only give new tiles free slots.
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TileAtlasIndexManifest {
+    pub groups: BTreeMap<String, BTreeMap<String, u16>>,
+}
+
+impl TileAtlasIndexManifest {
+
+    pub fn from_ron(src: &[u8]) -> Result<Self, ron::error::SpannedError> {
+        ron::de::from_bytes(src)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    #[must_use]
+    pub fn get(&self, group_id: &str, tile_id: &str) -> Option<u16> {
+        self.groups.get(group_id).and_then(|g| g.get(tile_id)).copied()
+    }
+
+    pub fn insert(&mut self, group_id: &str, tile_id: &str, index: u16) {
+        self.groups.entry(group_id.to_owned()).or_default().insert(tile_id.to_owned(), index);
+    }
+
+    /**
+     * Iterates over the entries as (`group_id`, `tile_id`, `index`) sorted by group and tile.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str, u16)> {
+        self.groups.iter().flat_map(|(group_id, group)| group.iter().map(move |(tile_id, &index)| (group_id.as_str(), tile_id.as_str(), index)))
+    }
+
+}
+
+/**
+ * The placement of every tile sequence in the atlas.
+ * - Pinned tiles are placed at their pinned index, unless they would overlap another pinned tile or the end of the atlas.
+ * - The remaining tiles are placed in the first free range large enough to hold them, sorted by group and tile.
+ * - When deduplicating, tiles whose frames are already in the atlas share those slots instead, see `TileAtlasBuilder::with_dedup`.
+ */
+#[derive(Debug, Clone)]
+pub struct TileAtlasLayout {
+    /// Sorted by index, never overlapping.
+    entries: Vec<TileAtlasLayoutEntry>,
+    /// Sorted by index, each within the slots of the entries.
+    shared: Vec<TileAtlasLayoutEntry>,
+    /// Pinned tiles that couldn't be placed at their pinned index.
+    conflicts: Vec<(String, String, u16)>,
+    page_slots: u32,
+}
+
+impl Default for TileAtlasLayout {
+    fn default() -> Self {
+        Self {
+            entries:    Vec::new(),
+            shared:     Vec::new(),
+            conflicts:  Vec::new(),
+            page_slots: TILE_ATLAS_PAGE_TILES*TILE_ATLAS_PAGE_TILES,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TileAtlasLayoutEntry {
+    pub group_id: String,
+    pub tile_id:  String,
+    pub entry:    TileAtlasEntry,
+}
+
+impl TileAtlasLayout {
+
+    /**
+     * The tiles with slots of their own, sorted by index.
+     */
+    #[must_use]
+    pub fn entries(&self) -> &[TileAtlasLayoutEntry] {
+        &self.entries
+    }
+
+    /**
+     * The tiles duplicating frames of other tiles, which share their slots rather than having their own, sorted by index.
+     */
+    #[must_use]
+    pub fn shared(&self) -> &[TileAtlasLayoutEntry] {
+        &self.shared
+    }
+
+    /**
+     * Iterates over every tile, those with slots of their own followed by the shared tiles.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = &TileAtlasLayoutEntry> {
+        self.entries.iter().chain(&self.shared)
+    }
+
+    /**
+     * The number of slots saved by sharing, the total frame count of the shared tiles.
+     */
+    #[must_use]
+    pub fn shared_slot_count(&self) -> u32 {
+        self.shared.iter().map(|e| e.entry.count as u32).sum()
+    }
+
+    /**
+     * Checks if the slots of the tile are used by any other tile, so writing to them changes both.
+     */
+    #[must_use]
+    pub fn is_shared(&self, group_id: &str, tile_id: &str) -> bool {
+        let Some(entry) = self.get(group_id, tile_id) else { return false; };
+        let (start, end) = (entry.index as u32, entry.index as u32 + entry.count as u32);
+        self.iter().any(|e| (e.group_id != group_id || e.tile_id != tile_id) && (e.entry.index as u32) < end && start < e.entry.index as u32 + e.entry.count as u32)
+    }
+
+    /**
+     * The pinned tiles as (`group_id`, `tile_id`, `pinned_index`) that were moved to a different index.
+     */
+    #[must_use]
+    pub fn conflicts(&self) -> &[(String, String, u16)] {
+        &self.conflicts
+    }
+
+    #[must_use]
+    pub fn get(&self, group_id: &str, tile_id: &str) -> Option<TileAtlasEntry> {
+        self.iter().find(|e| e.group_id == group_id && e.tile_id == tile_id).map(|e| e.entry)
+    }
+
+    /**
+     * The number of slots spanned by the layout, including unused slots between tiles.
+     * - Shared tiles lie within other tiles, so never extend the layout.
+     */
+    #[must_use]
+    pub fn slot_count(&self) -> u32 {
+        self.entries.last().map_or(0, |e| e.entry.index as u32 + e.entry.count as u32)
+    }
+
+    #[must_use]
+    pub fn page_count(&self) -> u32 {
+        self.slot_count().div_ceil(self.page_slots)
+    }
+
+    /**
+     * The number of slots in a page.
+     */
+    #[must_use]
+    pub const fn page_slots(&self) -> u32 {
+        self.page_slots
+    }
+
+    /**
+     * Creates an index manifest recording the index of every tile in the layout.
+     */
+    #[must_use]
+    pub fn to_index_manifest(&self) -> TileAtlasIndexManifest {
+        let mut result = TileAtlasIndexManifest::default();
+        for e in self.iter() {
+            result.insert(&e.group_id, &e.tile_id, e.entry.index);
+        }
+        result
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Pins the tile with the given `group_id` and `tile_id` to a slot `index`, see `TileAtlasLayout`.
+     * - Pins are kept for tiles that haven't been inserted yet and are stored in the atlas file.
+     */
+    pub fn pin(&mut self, group_id: &str, tile_id: &str, index: u16) {
+        self.mark_dirty();
+        self.pins.insert(group_id, tile_id, index);
+    }
+
+    pub fn unpin(&mut self, group_id: &str, tile_id: &str) -> Option<u16> {
+        self.mark_dirty();
+        let group = self.pins.groups.get_mut(group_id)?;
+        let result = group.remove(tile_id);
+        if group.is_empty() { self.pins.groups.remove(group_id); }
+        result
+    }
+
+    #[must_use]
+    pub const fn pins(&self) -> &TileAtlasIndexManifest {
+        &self.pins
+    }
+
+    /**
+     * Pins every tile in the index manifest, keeping the indices of a previous build.
+     */
+    pub fn pin_all(&mut self, indices: &TileAtlasIndexManifest) {
+        for (group_id, tile_id, index) in indices.iter() {
+            self.pin(group_id, tile_id, index);
+        }
+    }
+
+    /**
+     * Shares slots between identical frames when `dedup` is set, so repeated tiles are only stored once in the atlas.
+     * - Tiles share a range of slots, so a tile only shares when all of its frames are found in order, such as a
+     *   duplicate tile or an animation starting partway through another, frames repeated within a sequence are kept.
+     * - Patching a shared slot changes every tile using it, hot reloading moves changed tiles to slots of their own, see `TileAtlasLayout::is_shared`.
+     */
+    #[must_use]
+    pub const fn with_dedup(mut self, dedup: bool) -> Self {
+        self.set_dedup(dedup);
+        self
+    }
+
+    /**
+     * Shares slots between identical frames when `dedup` is set, see `Self::with_dedup`.
+     */
+    pub const fn set_dedup(&mut self, dedup: bool) {
+        if self.dedup != dedup { self.mark_dirty(); }
+        self.dedup = dedup;
+    }
+
+    #[must_use]
+    pub const fn dedup(&self) -> bool {
+        self.dedup
+    }
+
+    /**
+     * Assigns every tile a range of slots, this is deterministic and independent of insertion order.
+     * - Panics when the tiles don't fit in `TILE_ATLAS_SLOT_COUNT` slots, see `Self::try_build_layout`.
+     */
+    #[must_use]
+    pub fn build_layout(&self) -> TileAtlasLayout {
+        self.try_build_layout().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /**
+     * Assigns every tile a range of slots, see `Self::build_layout`.
+     * - Fails when the tiles don't fit in `TILE_ATLAS_SLOT_COUNT` slots, counting the gaps left by pins and the slots shared by deduplication.
+     */
+    pub fn try_build_layout(&self) -> Result<TileAtlasLayout, TileAtlasLimitsError> {
+        let mut tiles = self.tiles()
+            .filter_map(|(group_id, tile_id, set)| Some((group_id, tile_id, set, set.levels.first()?.data.len() as u32)))
+            .filter(|&(_, _, _, count)| count > 0)
+            .map(|(group_id, tile_id, set, count)| (group_id, tile_id, set, count, self.pins.get(group_id, tile_id)))
+            .collect::<Vec<_>>();
+        tiles.sort_unstable_by_key(|&(group_id, tile_id, _, _, pin)| (pin.is_none(), pin, group_id, tile_id));
+
+        let mut result   = TileAtlasLayout{ page_slots: self.page_slots(), ..TileAtlasLayout::default() };
+        let mut occupied = Vec::<Range<u32>>::new();
+        let mut slots    = TileAtlasLayoutSlots::default();
+
+        for (group_id, tile_id, set, count, pin) in tiles {
+            let shares = |index: u32| self.dedup && slots.contains(index, set);
+            let pinned = pin.map(u32::from).filter(|&index| {
+                let range = index..index+count;
+                let free  = range.end <= TILE_ATLAS_SLOT_COUNT && !occupied.iter().any(|o| o.start < range.end && range.start < o.end);
+                free || shares(index)
+            });
+
+            if let (Some(pin), None) = (pin, pinned) {
+                result.conflicts.push((group_id.to_owned(), tile_id.to_owned(), pin));
+            }
+
+            let shared = match pinned {
+                Some(index) => shares(index).then_some(index),
+                None if self.dedup => slots.find(set),
+                None => None,
+            };
+
+            let index = shared.or(pinned).unwrap_or_else(|| find_free_range(&occupied, count));
+            if index + count > TILE_ATLAS_SLOT_COUNT {
+                return Err(TileAtlasLimitsError::TooManySlots{ slot_count: index + count });
+            }
+
+            let entry = TileAtlasLayoutEntry {
+                group_id: group_id.to_owned(),
+                tile_id:  tile_id.to_owned(),
+                entry:    TileAtlasEntry{ index: index as u16, count: count as u16 },
+            };
+
+            if shared.is_some() {
+                result.shared.push(entry);
+                continue;
+            }
+
+            let position = occupied.partition_point(|o| o.start < index);
+            occupied.insert(position, index..index+count);
+            if self.dedup { slots.insert(index, set); }
+            result.entries.push(entry);
+        }
+
+        result.entries.sort_unstable_by_key(|e| e.entry.index);
+        result.shared.sort_unstable_by(|a, b| (a.entry.index, &a.group_id, &a.tile_id).cmp(&(b.entry.index, &b.group_id, &b.tile_id)));
+        Ok(result)
+    }
+
+}
+
+/**
+ * Finds the start of the first gap in the sorted `occupied` ranges that can hold `count` slots.
+ */
+fn find_free_range(occupied: &[Range<u32>], count: u32) -> u32 {
+    let mut start = 0;
+    for range in occupied {
+        if range.start >= start + count { break; }
+        start = start.max(range.end);
+    }
+    start
+}
+
+/**
+ * The frame held by each placed slot, used to find tiles that can share them.
+ */
+#[derive(Default)]
+struct TileAtlasLayoutSlots<'a> {
+    /// The set and frame in each slot.
+    frames: HashMap<u32, (&'a TileAtlasBuilderSet, usize), FixedHasher>,
+    /// The slots holding frames with each hash, in the order they were placed.
+    hashes: HashMap<u64, Vec<u32>, FixedHasher>,
+}
+
+impl<'a> TileAtlasLayoutSlots<'a> {
+
+    fn insert(&mut self, index: u32, set: &'a TileAtlasBuilderSet) {
+        for frame in 0..set.levels[0].data.len() {
+            let slot = index + frame as u32;
+            self.frames.insert(slot, (set, frame));
+            self.hashes.entry(frame_hash(set, frame)).or_default().push(slot);
+        }
+    }
+
+    /**
+     * Checks if the slots from `index` onwards hold every frame of the `set`, in order.
+     */
+    fn contains(&self, index: u32, set: &TileAtlasBuilderSet) -> bool {
+        (0..set.levels[0].data.len()).all(|frame| {
+            self.frames.get(&(index + frame as u32)).is_some_and(|&(other, other_frame)| frame_eq(set, frame, other, other_frame))
+        })
+    }
+
+    /**
+     * Finds the lowest slot from which the placed slots hold every frame of the `set`, in order.
+     */
+    fn find(&self, set: &TileAtlasBuilderSet) -> Option<u32> {
+        let candidates = self.hashes.get(&frame_hash(set, 0))?;
+        candidates.iter().copied().filter(|&index| self.contains(index, set)).min()
+    }
+
+}
+
+/**
+ * Hashes a frame of the `set` across every mip level.
+ */
+fn frame_hash(set: &TileAtlasBuilderSet, frame: usize) -> u64 {
+    FixedHasher.hash_one(set.levels.iter().map(|level| level.data.get(frame)).collect::<Vec<_>>())
+}
+
+/**
+ * Checks if a frame of `a` and a frame of `b` are identical in every mip level.
+ */
+fn frame_eq(a: &TileAtlasBuilderSet, a_frame: usize, b: &TileAtlasBuilderSet, b_frame: usize) -> bool {
+    a.levels.len() == b.levels.len() && a.levels.iter().zip(&b.levels).all(|(a, b)| a.data.get(a_frame) == b.data.get(b_frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atlas::builder::TILE_ATLAS_SLOT_COUNT;
+
+    /**
+     * Inserts a tile of single pixel frames, each filled with its value in `frames`.
+     */
+    fn insert(builder: &mut TileAtlasBuilder, group_id: &str, tile_id: &str, frames: &[u8]) {
+        let frames = frames.iter().map(|&v| [v; 4]).collect::<Vec<_>>();
+        builder.insert(group_id, tile_id, 0, frames.iter().map(|f| (f.as_slice(), 1, [0, 0])));
+    }
+
+    fn entries(layout: &TileAtlasLayout) -> Vec<(&str, &str, u16, u16)> {
+        layout.iter().map(|e| (e.group_id.as_str(), e.tile_id.as_str(), e.entry.index, e.entry.count)).collect()
+    }
+
+    #[test]
+    fn insertion_order() {
+        let tiles = [("walls", "brick", &[1, 2][..]), ("walls", "stone", &[3]), ("floors", "grass", &[4, 5, 6])];
+
+        let mut forward = TileAtlasBuilder::new(1);
+        for (group_id, tile_id, frames) in tiles { insert(&mut forward, group_id, tile_id, frames); }
+        let mut reverse = TileAtlasBuilder::new(1);
+        for (group_id, tile_id, frames) in tiles.into_iter().rev() { insert(&mut reverse, group_id, tile_id, frames); }
+
+        let layout = forward.build_layout();
+        assert_eq!(entries(&layout), entries(&reverse.build_layout()));
+        assert_eq!(entries(&layout), [("floors", "grass", 0, 3), ("walls", "brick", 3, 2), ("walls", "stone", 5, 1)]);
+    }
+
+    #[test]
+    fn pinned() {
+        let mut builder = TileAtlasBuilder::new(1);
+        insert(&mut builder, "walls", "brick", &[1, 2]);
+        insert(&mut builder, "walls", "stone", &[3]);
+        insert(&mut builder, "floors", "grass", &[4, 5, 6]);
+        builder.pin("walls", "brick", 40);
+        builder.pin("walls", "stone", 1);
+
+        // Unpinned tiles fill the first gap large enough for them, around the pinned tiles
+        let layout = builder.build_layout();
+        assert!(layout.conflicts().is_empty());
+        assert_eq!(layout.get("walls", "brick").map(|e| e.index), Some(40));
+        assert_eq!(layout.get("walls", "stone").map(|e| e.index), Some(1));
+        assert_eq!(layout.get("floors", "grass").map(|e| e.index), Some(2));
+        assert_eq!(layout.slot_count(), 42);
+    }
+
+    #[test]
+    fn pin_conflicts() {
+        let mut builder = TileAtlasBuilder::new(1);
+        insert(&mut builder, "walls", "brick", &[1, 2, 3]);
+        insert(&mut builder, "walls", "stone", &[4]);
+        insert(&mut builder, "walls", "tiled", &[5, 6]);
+        builder.pin("walls", "brick", 10);
+        builder.pin("walls", "stone", 11);
+        builder.pin("walls", "tiled", u16::MAX - 1);
+
+        // The lowest pin wins an overlap, the others are moved to the first free slots
+        let layout = builder.build_layout();
+        assert_eq!(layout.conflicts(), [("walls".to_owned(), "stone".to_owned(), 11), ("walls".to_owned(), "tiled".to_owned(), u16::MAX - 1)]);
+        assert_eq!(layout.get("walls", "brick").map(|e| e.index), Some(10));
+        assert_eq!(layout.get("walls", "stone").map(|e| e.index), Some(0));
+        assert_eq!(layout.get("walls", "tiled").map(|e| e.index), Some(1));
+    }
+
+    #[test]
+    fn dedup() {
+        let mut builder = TileAtlasBuilder::new(1).with_page_tiles(2);
+        insert(&mut builder, "walls", "brick", &[1, 2]);
+        insert(&mut builder, "walls", "copy",  &[1, 2]);
+        insert(&mut builder, "walls", "half",  &[2]);
+        insert(&mut builder, "walls", "stone", &[3, 3]);
+        assert_eq!(builder.build_layout().page_count(), 2);
+
+        // Frames repeated within a tile are kept, only whole runs of frames are shared
+        let layout = builder.with_dedup(true).build_layout();
+        assert_eq!(layout.page_count(), 1);
+        assert_eq!(layout.shared_slot_count(), 3);
+        assert_eq!(entries(&layout), [
+            ("walls", "brick", 0, 2), ("walls", "stone", 2, 2),
+            ("walls", "copy",  0, 2), ("walls", "half",  1, 1),
+        ]);
+        assert!(layout.is_shared("walls", "brick"));
+        assert!(!layout.is_shared("walls", "stone"));
+    }
+
+    #[test]
+    fn too_many_slots() {
+        let mut builder = TileAtlasBuilder::new(1);
+        insert(&mut builder, "walls", "brick", &[1]);
+        let frame = [2_u8; 4];
+        builder.insert("walls", "stone", 0, core::iter::repeat_n((frame.as_slice(), 1, [0, 0]), TILE_ATLAS_SLOT_COUNT as usize));
+
+        let result = builder.try_build_layout();
+        assert!(matches!(result, Err(TileAtlasLimitsError::TooManySlots{ slot_count }) if slot_count == TILE_ATLAS_SLOT_COUNT + 1));
+        assert!(builder.check_slot_count().is_err());
+    }
+
+}
@@ -0,0 +1,170 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use bevy::render::{renderer::RenderDevice, settings::WgpuLimits};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{TileAtlasBuilder, TileAtlasBuilderImageSequence, TILE_ATLAS_PAGE_TILES_MAX, TILE_ATLAS_SLOT_COUNT};
+
+/**
+ * The texture limits an atlas has to fit within, so an atlas that's too large is reported
+ * when it's built rather than failing when it's uploaded.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileAtlasLimits {
+    /// The largest width and height of a page, in pixels.
+    pub max_texture_dimension_2d: u32,
+    /// The largest number of pages.
+    pub max_texture_array_layers: u32,
+}
+
+impl Default for TileAtlasLimits {
+    fn default() -> Self {
+        Self::from_wgpu(&WgpuLimits::default())
+    }
+}
+
+impl TileAtlasLimits {
+
+    #[must_use]
+    pub const fn from_wgpu(limits: &WgpuLimits) -> Self {
+        Self {
+            max_texture_dimension_2d: limits.max_texture_dimension_2d,
+            max_texture_array_layers: limits.max_texture_array_layers,
+        }
+    }
+
+    /**
+     * The limits of the current device.
+     */
+    #[must_use]
+    pub fn from_render_device(device: &RenderDevice) -> Self {
+        Self::from_wgpu(&device.limits())
+    }
+
+    /**
+     * The limits supported by practically every device, including WebGL2.
+     */
+    #[must_use]
+    pub const fn downlevel_defaults() -> Self {
+        Self::from_wgpu(&WgpuLimits::downlevel_defaults())
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TileAtlasLimitsError {
+    #[error("tile atlas needs {slot_count} slots but at most {TILE_ATLAS_SLOT_COUNT} are addressable, split the atlas")]
+    TooManySlots {
+        slot_count: u32,
+    },
+
+    #[error("tile \"{group_id}/{tile_id}\" has {frame_count} frames but an atlas holds at most {max_slots} slots")]
+    SequenceTooLong {
+        group_id:    String,
+        tile_id:     String,
+        frame_count: u32,
+        max_slots:   u32,
+    },
+
+    #[error("tile atlas pages are {page_size}px wide but at most {max}px are supported, use fewer tiles per page")]
+    PageTooLarge {
+        page_size: u32,
+        max:       u32,
+    },
+
+    #[error("tile atlas has {page_count} pages but at most {max} are supported, use more tiles per page or split the atlas")]
+    TooManyPages {
+        page_count: u32,
+        max:        u32,
+    },
+
+    #[error("tile atlas tiles are {tile_size}px wide but at most {max}px are supported, and not every tile has the mip level to shrink to")]
+    TileTooLarge {
+        tile_size: u32,
+        max:       u32,
+    },
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Checks the atlas texture would fit within the `limits`.
+     */
+    pub fn check_limits(&self, limits: &TileAtlasLimits) -> Result<(), TileAtlasLimitsError> {
+        let layout = self.try_build_layout()?;
+        if self.page_size() > limits.max_texture_dimension_2d {
+            return Err(TileAtlasLimitsError::PageTooLarge{ page_size: self.page_size(), max: limits.max_texture_dimension_2d });
+        }
+
+        let page_count = layout.page_count();
+        if page_count > limits.max_texture_array_layers {
+            return Err(TileAtlasLimitsError::TooManyPages{ page_count, max: limits.max_texture_array_layers });
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Changes the atlas to fit within the `limits`.
+     * - Tiles larger than a page are shrunk by discarding their largest mip levels, see `Self::drop_base_levels`.
+     * - The tiles per page are reduced until a page fits, then increased until the pages fit.
+     */
+    pub fn fit_limits(&mut self, limits: &TileAtlasLimits) -> Result<(), TileAtlasLimitsError> {
+        let slot_count = self.try_build_layout()?.slot_count();
+        let max_size = limits.max_texture_dimension_2d;
+        if self.size > max_size {
+            let count = (1..self.mip_levels_max()).find(|&level| self.mip_level_size(level) <= max_size);
+            match count {
+                Some(count) if self.find_mip_level_common_max() > count => self.drop_base_levels(count),
+                _ => return Err(TileAtlasLimitsError::TileTooLarge{ tile_size: self.size, max: max_size }),
+            }
+        }
+
+        let max_page_tiles = (max_size/self.size).clamp(1, TILE_ATLAS_PAGE_TILES_MAX);
+        self.set_page_tiles(self.page_tiles.min(max_page_tiles));
+
+        let max_layers = limits.max_texture_array_layers.max(1);
+        if slot_count.div_ceil(self.page_slots()) > max_layers {
+            let page_slots = slot_count.div_ceil(max_layers);
+            let page_tiles = (self.page_tiles..=max_page_tiles).find(|n| n*n >= page_slots).ok_or(TileAtlasLimitsError::TooManyPages {
+                page_count: slot_count.div_ceil(max_page_tiles*max_page_tiles),
+                max:        limits.max_texture_array_layers,
+            })?;
+            self.set_page_tiles(page_tiles);
+        }
+
+        self.check_limits(limits)
+    }
+
+    /**
+     * Checks the tiles fit within the addressable slots once laid out, see `Self::try_build_layout` and `Self::split`.
+     */
+    pub fn check_slot_count(&self) -> Result<(), TileAtlasLimitsError> {
+        self.try_build_layout().map(drop)
+    }
+
+    /**
+     * Discards the largest `count` mip levels of every tile, halving the tile size and border each time.
+     * - Every tile must have the mip level `count`, see `Self::find_mip_level_common_max`.
+     */
+    pub fn drop_base_levels(&mut self, count: u32) {
+        assert!(self.find_mip_level_common_max() > count, "Attempt to drop mip levels without a replacement level");
+
+        self.mark_dirty();
+        self.size      = self.size.unbounded_shr(count);
+        self.tile_size = self.tile_size.div_ceil(1 << count);
+        self.border    = self.border.unbounded_shr(count);
+
+        let level_count = self.mip_levels_max() as usize;
+        for group in self.groups.values_mut() {
+            for set in group.tile_sets.values_mut() {
+                let mut levels = core::mem::take(&mut set.levels).into_vec();
+                levels.drain(..count as usize);
+                levels.resize_with(level_count, TileAtlasBuilderImageSequence::default);
+                set.levels = levels.into_boxed_slice();
+            }
+        }
+    }
+
+}
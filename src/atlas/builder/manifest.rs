@@ -0,0 +1,401 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{DownsampleAlgorithm, DownsampleAlphaCoverage, DownsampleBilinearSRGB, TileAtlasBuilder, TileAtlasCompression, TileAtlasCompressionError, TileAtlasIndexManifest, TileAtlasPixelFormat, TileAtlasSheetError, TileSetError, TileSetSettings, TILE_ATLAS_PAGE_TILES_MAX};
+
+/**
+ * The conventional file extension of a tile atlas manifest.
+ */
+pub const TILE_ATLAS_MANIFEST_EXTENSION: &str = "atlas.ron";
+
+#[derive(Debug, Error)]
+pub enum TileAtlasManifestError {
+    #[error("tile atlas manifest references missing image \"{0}\"")]
+    MissingImage(String),
+
+    #[error("tile atlas manifest has no tile size and no tiles to infer it from")]
+    UnknownTileSize,
+
+    #[error("tile \"{group_id}/{tile_id}\" has no images at mip level 0")]
+    EmptyTile {
+        group_id: String,
+        tile_id:  String,
+    },
+
+    #[error("tile \"{group_id}/{tile_id}\" overrides mip level {level} but the atlas only has {level_count} levels")]
+    InvalidLevel {
+        group_id:    String,
+        tile_id:     String,
+        level:       u32,
+        level_count: u32,
+    },
+
+    #[error("tile \"{group_id}/{tile_id}\" has {found} frames at mip level {level}, expected {expected} as at mip level 0")]
+    MismatchedFrames {
+        group_id: String,
+        tile_id:  String,
+        level:    u32,
+        expected: usize,
+        found:    usize,
+    },
+
+    #[error("tile \"{group_id}/{tile_id}\" slices a {size}px tile at {offset:?} from \"{path}\", which is only {image_size:?}")]
+    TileOutOfBounds {
+        group_id:   String,
+        tile_id:    String,
+        path:       String,
+        offset:     [u32; 2],
+        size:       u32,
+        image_size: [u32; 2],
+    },
+
+    #[error("tile atlas manifest has {0} tiles per page side, expected 1 to {TILE_ATLAS_PAGE_TILES_MAX}")]
+    InvalidPageTiles(u32),
+
+    #[error("tile atlas manifest has a {0}px tile size, expected at least 1px")]
+    InvalidTileSize(u32),
+
+    #[error("tile atlas manifest has a {0}px tile border, expected zero or a power of two")]
+    InvalidBorder(u32),
+
+    #[error("tile atlas source image can't be converted to {0:?}")]
+    ImageFormat(TileAtlasPixelFormat),
+
+    #[error(transparent)]
+    Compression(#[from] TileAtlasCompressionError),
+
+    #[error(transparent)]
+    Sheet(#[from] TileAtlasSheetError),
+
+    #[error(transparent)]
+    TileSet(#[from] TileSetError),
+}
+
+/**
+ * Describes the contents of a tile atlas, the source images it's built from and
+ * how those images are sliced into tiles.
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileAtlasManifest {
+    /// Size of a tile at mip level 0, inferred from the first tile when missing.
+    pub tile_size: Option<u32>,
+    /// How mip levels are generated for tiles that don't override it.
+    pub mips: TileAtlasManifestMips,
+    /// Discards all mip levels from this level onwards, `None` keeps every level.
+    pub limit_levels: Option<u32>,
+    /// Number of tiles along each side of a page, `None` uses `TILE_ATLAS_PAGE_TILES`.
+    pub page_tiles: Option<u32>,
+    /// The format tiles are stored in, source images are converted to it.
+    pub format: TileAtlasPixelFormat,
+    /// Width of the extruded border around every tile, see `TileAtlasBuilder::with_border`.
+    pub border: u32,
+    /// Pads tiles up to the next power of two, see `TileAtlasBuilder::with_padding`.
+    pub padded: bool,
+    /// Stores identical frames once, see `TileAtlasBuilder::with_dedup`.
+    pub dedup: bool,
+    /// Encodes the atlas image ahead of time, see `TileAtlasBuilder::compress`.
+    pub compression: TileAtlasCompression,
+    /// Path to an index manifest, relative to the manifest, whose indices are pinned.
+    pub indices: Option<String>,
+    pub groups: BTreeMap<String, BTreeMap<String, TileAtlasManifestTile>>,
+}
+
+/**
+ * Controls how missing mip levels of a tile are created.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileAtlasManifestMips {
+    /// Generates missing mip levels by downsampling the level above.
+    pub generate: bool,
+    /// Regenerates every mip level, replacing any levels provided by the manifest.
+    pub force: bool,
+    /// Preserves the fraction of pixels with an alpha above this cutoff, see `DownsampleAlphaCoverage`.
+    pub alpha_coverage: Option<f32>,
+}
+
+/**
+ * A tile in the manifest, the tile's image sequence at mip level 0 is formed from the
+ * tiles sliced from `path`, followed by each of the `frames` in order.
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileAtlasManifestTile {
+    /// Path to the source image, relative to the manifest.
+    pub path: Option<String>,
+    pub tileset: TileSetSettings,
+    /// Additional frames of the tile's sequence, appended after the tiles from `path`.
+    pub frames: Vec<TileAtlasManifestFrame>,
+    /// Explicitly provided images for mip levels other than 0.
+    pub levels: BTreeMap<u32, TileAtlasManifestLevel>,
+    /// Overrides the manifest's mip policy for this tile.
+    pub mips: Option<TileAtlasManifestMips>,
+    /// Pins the tile to a slot index, overriding the index manifest.
+    pub index: Option<u16>,
+}
+
+/**
+ * Explicitly provided images for a single mip level of a tile, which
+ * should slice the same number of frames as level 0.
+ */
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileAtlasManifestLevel {
+    pub path: Option<String>,
+    pub tileset: TileSetSettings,
+    pub frames: Vec<TileAtlasManifestFrame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileAtlasManifestFrame {
+    /// Path to the source image, relative to the manifest.
+    pub path: String,
+    #[serde(default)]
+    pub tileset: TileSetSettings,
+}
+
+impl Default for TileAtlasManifestMips {
+    fn default() -> Self {
+        Self {
+            generate: true,
+            force:    false,
+            alpha_coverage: None,
+        }
+    }
+}
+
+impl TileAtlasManifest {
+
+    #[must_use]
+    pub fn new(tile_size: Option<u32>) -> Self {
+        Self {
+            tile_size,
+            ..Self::default()
+        }
+    }
+
+    /**
+     * Parses a manifest from RON, optional values may be written without wrapping them in `Some`.
+     */
+    pub fn from_ron(src: &[u8]) -> Result<Self, ron::error::SpannedError> {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_bytes(src)
+    }
+
+    /**
+     * Iterates over the tiles in the manifest as (`group_id`, `tile_id`, `tile`).
+     */
+    pub fn tiles(&self) -> impl Iterator<Item = (&str, &str, &TileAtlasManifestTile)> {
+        self.groups.iter().flat_map(|(group_id, group)| group.iter().map(move |(tile_id, tile)| (group_id.as_str(), tile_id.as_str(), tile)))
+    }
+
+    /**
+     * Iterates over the unique source image paths referenced by the manifest.
+     */
+    pub fn sources(&self) -> impl Iterator<Item = &str> {
+        let mut sources = self.tiles().flat_map(|(_, _, t)| t.sources()).collect::<Vec<_>>();
+        sources.sort_unstable();
+        sources.dedup();
+        sources.into_iter()
+    }
+
+    /**
+     * Determines the tile size of the atlas, inferring it from the first tile if it isn't specified.
+     */
+    pub fn find_tile_size<'a>(
+        &self,
+        mut images: impl FnMut(&str) -> Option<(&'a [u8], [u32; 2])>,
+    ) -> Result<u32, TileAtlasManifestError> {
+        if let Some(tile_size) = self.tile_size { return Ok(tile_size); }
+        let (path, tileset) = self.tiles().find_map(|(_, _, t)| t.frames(0).into_iter().next()).ok_or(TileAtlasManifestError::UnknownTileSize)?;
+        let (_, src_size) = images(path).ok_or_else(|| TileAtlasManifestError::MissingImage(path.to_owned()))?;
+        Ok(tileset.tile_size(src_size)?)
+    }
+
+}
+
+impl TileAtlasManifest {
+
+    /**
+     * Builds an atlas from the manifest.
+     * - `images` provides the data and size of the source image at the given path, in the pixel format of `Self::format`.
+     */
+    pub fn build<'a>(
+        &self,
+        images: impl FnMut(&str) -> Option<(&'a [u8], [u32; 2])>,
+    ) -> Result<TileAtlasBuilder, TileAtlasManifestError> {
+        self.build_with_indices(&TileAtlasIndexManifest::default(), images)
+    }
+
+    /**
+     * Builds an atlas from the manifest, pinning the tiles in `indices`.
+     * - `indices` is usually the index manifest referenced by `Self::indices`.
+     * - Indices pinned by a tile in the manifest take priority.
+     */
+    pub fn build_with_indices<'a>(
+        &self,
+        indices: &TileAtlasIndexManifest,
+        mut images: impl FnMut(&str) -> Option<(&'a [u8], [u32; 2])>,
+    ) -> Result<TileAtlasBuilder, TileAtlasManifestError> {
+        if self.border != 0 && !self.border.is_power_of_two() {
+            return Err(TileAtlasManifestError::InvalidBorder(self.border));
+        }
+
+        let tile_size = self.find_tile_size(&mut images)?;
+        if tile_size == 0 {
+            return Err(TileAtlasManifestError::InvalidTileSize(tile_size));
+        }
+
+        let mut builder = TileAtlasBuilder::new(tile_size)
+            .with_format(self.format)
+            .with_padding(self.padded)
+            .with_border(self.border)
+            .with_dedup(self.dedup);
+        builder.pin_all(indices);
+
+        if let Some(page_tiles) = self.page_tiles {
+            if !(1..=TILE_ATLAS_PAGE_TILES_MAX).contains(&page_tiles) {
+                return Err(TileAtlasManifestError::InvalidPageTiles(page_tiles));
+            }
+            builder.set_page_tiles(page_tiles);
+        }
+
+        for (group_id, tile_id, tile) in self.tiles() {
+            tile.insert_into(&mut builder, group_id, tile_id, &mut images)?;
+            tile.mips.unwrap_or(self.mips).apply(&mut builder, group_id, tile_id);
+            if let Some(index) = tile.index {
+                builder.pin(group_id, tile_id, index);
+            }
+        }
+
+        if let Some(limit_levels) = self.limit_levels {
+            builder.limit_levels(limit_levels);
+        }
+
+        builder.compress(self.compression)?;
+        Ok(builder)
+    }
+
+}
+
+impl TileAtlasManifestTile {
+
+    /**
+     * Iterates over the source image paths referenced by this tile, including duplicates.
+     */
+    pub fn sources(&self) -> impl Iterator<Item = &str> {
+        let levels = self.levels.values().flat_map(|l| l.path.iter().map(String::as_str).chain(l.frames.iter().map(|f| f.path.as_str())));
+        self.path.iter().map(String::as_str)
+            .chain(self.frames.iter().map(|f| f.path.as_str()))
+            .chain(levels)
+    }
+
+    /**
+     * Gets the images that form the sequence at the given mip `level`, as (`path`, `tileset`).
+     * - Returns an empty list when the level isn't provided.
+     */
+    #[must_use]
+    pub fn frames(&self, level: u32) -> Vec<(&str, TileSetSettings)> {
+        let (path, tileset, frames) = if level == 0 {
+            (&self.path, self.tileset, &self.frames)
+        } else if let Some(level) = self.levels.get(&level) {
+            (&level.path, level.tileset, &level.frames)
+        } else {
+            return Vec::new();
+        };
+
+        path.iter().map(|p| (p.as_str(), tileset))
+            .chain(frames.iter().map(|f| (f.path.as_str(), f.tileset)))
+            .collect()
+    }
+
+    /**
+     * Slices the tile's images and inserts them into the `builder`, replacing any existing data
+     * in the provided levels.
+     * - Every slice is checked against the bounds of its image before anything is inserted.
+     * - Overridden levels must have as many frames as level 0.
+     */
+    pub fn insert_into<'a>(
+        &self,
+        builder: &mut TileAtlasBuilder,
+        group_id: &str,
+        tile_id:  &str,
+        mut images: impl FnMut(&str) -> Option<(&'a [u8], [u32; 2])>,
+    ) -> Result<(), TileAtlasManifestError> {
+        let level_count = builder.mip_levels_max();
+        let levels = core::iter::once(0).chain(self.levels.keys().copied().filter(|&l| l != 0));
+
+        let mut inserts = Vec::new();
+        for level in levels {
+            if level >= level_count {
+                return Err(TileAtlasManifestError::InvalidLevel{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned(), level, level_count });
+            }
+
+            let level_size = builder.tile_level_size(level);
+            let mut sources = Vec::new();
+            for (path, tileset) in self.frames(level) {
+                let (src, src_size) = images(path).ok_or_else(|| TileAtlasManifestError::MissingImage(path.to_owned()))?;
+                for offset in tileset.offsets(level_size) {
+                    let fits = |axis: usize| offset[axis].checked_add(level_size).is_some_and(|end| end <= src_size[axis]);
+                    if !fits(0) || !fits(1) {
+                        return Err(TileAtlasManifestError::TileOutOfBounds {
+                            group_id:   group_id.to_owned(),
+                            tile_id:    tile_id.to_owned(),
+                            path:       path.to_owned(),
+                            offset,
+                            size:       level_size,
+                            image_size: src_size,
+                        });
+                    }
+                    sources.push((src, src_size[0], offset));
+                }
+            }
+
+            if sources.is_empty() {
+                if level == 0 { return Err(TileAtlasManifestError::EmptyTile{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned() }); }
+                continue;
+            }
+
+            let expected = inserts.first().map_or(sources.len(), |(_, base): &(u32, Vec<_>)| base.len());
+            if sources.len() != expected {
+                return Err(TileAtlasManifestError::MismatchedFrames{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned(), level, expected, found: sources.len() });
+            }
+            inserts.push((level, sources));
+        }
+
+        for (level, sources) in inserts {
+            builder.insert(group_id, tile_id, level, sources.into_iter());
+        }
+
+        Ok(())
+    }
+
+}
+
+impl TileAtlasManifestMips {
+
+    /**
+     * Applies the mip policy to the tile with the given `group_id` and `tile_id`.
+     */
+    pub fn apply(&self, builder: &mut TileAtlasBuilder, group_id: &str, tile_id: &str) {
+        self.apply_with(builder, group_id, tile_id, DownsampleBilinearSRGB);
+    }
+
+    /**
+     * Applies the mip policy with the given `downsampler`.
+     */
+    pub fn apply_with(&self, builder: &mut TileAtlasBuilder, group_id: &str, tile_id: &str, downsampler: impl DownsampleAlgorithm) {
+        match (self.generate, self.alpha_coverage) {
+            (false, _) => {},
+            (true, None) => builder.downsample_levels_for(group_id, tile_id, 0..u32::MAX, self.force, downsampler),
+            (true, Some(cutoff)) => builder.downsample_levels_for(group_id, tile_id, 0..u32::MAX, self.force, DownsampleAlphaCoverage::new(downsampler, cutoff)),
+        }
+    }
+
+}
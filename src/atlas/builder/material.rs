@@ -0,0 +1,267 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::HashMap;
+
+use bevy::{asset::Assets, image::{CompressedImageFormats, Image}, platform::hash::FixedHasher};
+use thiserror::Error;
+
+use super::{TileAtlasBuildSettings, TileAtlasBuilder, TileAtlasIndexManifest, TileAtlasLayout, TileAtlasPixelFormat};
+use crate::atlas::{TileAtlas, TileAtlasGroup};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TileAtlasMaterialError {
+    #[error("tile atlas channel \"{channel}\" has {found}px tiles in {found_slot}px slots, expected {expected}px tiles in {expected_slot}px slots")]
+    MismatchedSize {
+        channel:       String,
+        expected:      u32,
+        expected_slot: u32,
+        found:         u32,
+        found_slot:    u32,
+    },
+
+    #[error("tile atlas channel \"{channel}\" has a {found}px tile border, expected {expected}px")]
+    MismatchedBorder {
+        channel:  String,
+        expected: u32,
+        found:    u32,
+    },
+
+    #[error("tile atlas channel \"{channel}\" has {found} tiles per page side, expected {expected}")]
+    MismatchedPageTiles {
+        channel:  String,
+        expected: u32,
+        found:    u32,
+    },
+
+    #[error("tile atlas channel \"{channel}\" deduplicates tiles, which channels can't share a layout with")]
+    Deduplicated {
+        channel: String,
+    },
+
+    #[error("tile atlas channel \"{channel}\" pins different indices than the primary channel")]
+    MismatchedPins {
+        channel: String,
+    },
+
+    #[error("tile atlas channel \"{channel}\" is missing tile \"{group_id}/{tile_id}\"")]
+    MissingTile {
+        channel:  String,
+        group_id: String,
+        tile_id:  String,
+    },
+
+    #[error("tile atlas channel \"{channel}\" has tile \"{group_id}/{tile_id}\", which the primary channel doesn't")]
+    UnexpectedTile {
+        channel:  String,
+        group_id: String,
+        tile_id:  String,
+    },
+
+    #[error("tile atlas channel \"{channel}\" has {found} frames of tile \"{group_id}/{tile_id}\", expected {expected}")]
+    MismatchedSequence {
+        channel:  String,
+        group_id: String,
+        tile_id:  String,
+        expected: usize,
+        found:    usize,
+    },
+}
+
+/**
+ * Builds an atlas with several named channels per tile, such as albedo, normal and emissive maps,
+ * where every channel uses exactly the same slots.
+ * - Each channel is a `TileAtlasBuilder` with its own pixel format and mip levels, the first channel is the primary channel.
+ * - Channels are validated to hold the same tiles with the same sequence lengths, which gives them the same layout.
+ * - Pins are applied to every channel, other changes are made to each channel, see `Self::channel_mut`.
+ */
+#[derive(Debug, Clone)]
+pub struct TileAtlasMaterialBuilder {
+    channels: Vec<(String, TileAtlasBuilder)>,
+}
+
+impl TileAtlasMaterialBuilder {
+
+    /**
+     * Creates a material with the `primary` channel, which becomes `TileAtlas::image`.
+     */
+    #[must_use]
+    pub fn new(primary: impl Into<String>, builder: TileAtlasBuilder) -> Self {
+        Self{ channels: vec![(primary.into(), builder)] }
+    }
+
+    /**
+     * Adds an empty companion channel in the pixel `format`, matching the slot size, padding, border, page size and pins of the primary channel.
+     */
+    #[must_use]
+    pub fn with_channel(mut self, name: impl Into<String>, format: TileAtlasPixelFormat) -> Self {
+        self.add_channel(name, format);
+        self
+    }
+
+    /**
+     * Adds an empty companion channel in the pixel `format`, matching the slot size, padding, border, page size and pins of the primary channel.
+     */
+    pub fn add_channel(&mut self, name: impl Into<String>, format: TileAtlasPixelFormat) {
+        let primary = self.primary();
+        let mut builder = primary.empty_like().with_format(format);
+        builder.pin_all(primary.pins());
+        self.insert_channel(name, builder);
+    }
+
+    /**
+     * Adds a companion channel built separately, see `Self::validate`.
+     */
+    pub fn insert_channel(&mut self, name: impl Into<String>, builder: TileAtlasBuilder) {
+        let name = name.into();
+        assert!(self.channel(&name).is_none(), "Tile atlas channel \"{name}\" already exists");
+        self.channels.push((name, builder));
+    }
+
+    #[must_use]
+    pub fn primary(&self) -> &TileAtlasBuilder {
+        &self.channels[0].1
+    }
+
+    #[must_use]
+    pub fn primary_mut(&mut self) -> &mut TileAtlasBuilder {
+        &mut self.channels[0].1
+    }
+
+    #[must_use]
+    pub fn channel(&self, name: &str) -> Option<&TileAtlasBuilder> {
+        self.channels.iter().find(|(n, _)| n == name).map(|(_, builder)| builder)
+    }
+
+    #[must_use]
+    pub fn channel_mut(&mut self, name: &str) -> Option<&mut TileAtlasBuilder> {
+        self.channels.iter_mut().find(|(n, _)| n == name).map(|(_, builder)| builder)
+    }
+
+    /**
+     * Iterates over the channels as (`name`, `builder`), starting with the primary channel.
+     */
+    pub fn channels(&self) -> impl Iterator<Item = (&str, &TileAtlasBuilder)> {
+        self.channels.iter().map(|(name, builder)| (name.as_str(), builder))
+    }
+
+    /**
+     * Pins the tile in every channel, see `TileAtlasBuilder::pin`.
+     */
+    pub fn pin(&mut self, group_id: &str, tile_id: &str, index: u16) {
+        for (_, builder) in &mut self.channels {
+            builder.pin(group_id, tile_id, index);
+        }
+    }
+
+    /**
+     * Pins every tile in the index manifest in every channel, see `TileAtlasBuilder::pin_all`.
+     */
+    pub fn pin_all(&mut self, indices: &TileAtlasIndexManifest) {
+        for (_, builder) in &mut self.channels {
+            builder.pin_all(indices);
+        }
+    }
+
+}
+
+impl TileAtlasMaterialBuilder {
+
+    /**
+     * Checks every companion channel against the primary channel, they must have the same tile and slot size,
+     * border, tiles per page side, pins, and tiles with the same number of frames.
+     * - Deduplication would share slots by the contents of a single channel, so no channel may enable it, see `TileAtlasBuilder::with_dedup`.
+     */
+    pub fn validate(&self) -> Result<(), TileAtlasMaterialError> {
+        if let Some((name, _)) = self.channels.iter().find(|(_, builder)| builder.dedup()) {
+            return Err(TileAtlasMaterialError::Deduplicated{ channel: name.clone() });
+        }
+
+        let primary = self.primary();
+        for (name, builder) in &self.channels[1..] {
+            if (builder.tile_size(), builder.slot_size()) != (primary.tile_size(), primary.slot_size()) {
+                return Err(TileAtlasMaterialError::MismatchedSize {
+                    channel:       name.clone(),
+                    expected:      primary.tile_size(),
+                    expected_slot: primary.slot_size(),
+                    found:         builder.tile_size(),
+                    found_slot:    builder.slot_size(),
+                });
+            }
+
+            if builder.border() != primary.border() {
+                return Err(TileAtlasMaterialError::MismatchedBorder{ channel: name.clone(), expected: primary.border(), found: builder.border() });
+            }
+
+            if builder.page_tiles() != primary.page_tiles() {
+                return Err(TileAtlasMaterialError::MismatchedPageTiles{ channel: name.clone(), expected: primary.page_tiles(), found: builder.page_tiles() });
+            }
+
+            if builder.pins() != primary.pins() {
+                return Err(TileAtlasMaterialError::MismatchedPins{ channel: name.clone() });
+            }
+
+            for (group_id, tile_id, set) in primary.tiles() {
+                let Some(other) = builder.get_tile(group_id, tile_id) else {
+                    return Err(TileAtlasMaterialError::MissingTile{ channel: name.clone(), group_id: group_id.to_owned(), tile_id: tile_id.to_owned() });
+                };
+                let (expected, found) = (set.levels[0].data.len(), other.levels[0].data.len());
+                if expected != found {
+                    return Err(TileAtlasMaterialError::MismatchedSequence{ channel: name.clone(), group_id: group_id.to_owned(), tile_id: tile_id.to_owned(), expected, found });
+                }
+            }
+
+            if let Some((group_id, tile_id, _)) = builder.tiles().find(|(group_id, tile_id, _)| primary.get_tile(group_id, tile_id).is_none()) {
+                return Err(TileAtlasMaterialError::UnexpectedTile{ channel: name.clone(), group_id: group_id.to_owned(), tile_id: tile_id.to_owned() });
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * The layout shared by every channel, which is only valid once the channels are, see `Self::validate`.
+     */
+    #[must_use]
+    pub fn build_layout(&self) -> TileAtlasLayout {
+        self.primary().build_layout()
+    }
+
+    #[must_use]
+    pub fn build_lookup(&self) -> HashMap<String, TileAtlasGroup, FixedHasher> {
+        self.primary().build_lookup()
+    }
+
+    /**
+     * Validates the channels and builds the image of each as (`name`, `image`), starting with the primary channel.
+     * - Images are built as configured by the `settings`, compressed when it's one of the `supported` formats.
+     */
+    pub fn build_images(&self, settings: &TileAtlasBuildSettings, supported: CompressedImageFormats) -> Result<Vec<(String, Image)>, TileAtlasMaterialError> {
+        self.validate()?;
+        Ok(self.channels.iter().map(|(name, builder)| (name.clone(), settings.build_image(builder, supported))).collect())
+    }
+
+    /**
+     * Validates the channels and builds an atlas with the primary channel as `TileAtlas::image`, and the others as its channels.
+     */
+    pub fn build_atlas(&self, images: &mut Assets<Image>) -> Result<TileAtlas, TileAtlasMaterialError> {
+        self.build_atlas_with_settings(images, &TileAtlasBuildSettings::default(), CompressedImageFormats::NONE)
+    }
+
+    /**
+     * Validates the channels and builds an atlas as configured by the `settings`, see `Self::build_images`.
+     */
+    pub fn build_atlas_with_settings(
+        &self,
+        images:    &mut Assets<Image>,
+        settings:  &TileAtlasBuildSettings,
+        supported: CompressedImageFormats,
+    ) -> Result<TileAtlas, TileAtlasMaterialError> {
+        let mut channels = self.build_images(settings, supported)?.into_iter();
+        let (_, image) = channels.next().unwrap();
+
+        let texture_format = image.texture_descriptor.format;
+        let atlas = TileAtlas::from_builder(images.add(image), self.primary())
+            .with_texture_format(texture_format);
+        Ok(channels.fold(atlas, |atlas, (name, image)| atlas.with_channel(name, images.add(image))))
+    }
+
+}
@@ -1,28 +1,67 @@
 // Copyright 2025 Natalie Baker // Apache License v2 //
 
 use core::ops::Range;
-use std::{collections::{hash_map::Entry, HashMap}, io::{Read, Write}};
+use std::collections::{hash_map::Entry, HashMap};
 
 use bitcode::{Decode, Encode};
 
-use bevy::{asset::RenderAssetUsages, image::{Image, ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor}, platform::hash::FixedHasher, render::render_resource::{Extent3d, TextureAspect, TextureDataOrder, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor, TextureViewDimension}};
+use bevy::{asset::{Asset, RenderAssetUsages}, reflect::TypePath, image::{Image, ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor}, platform::hash::FixedHasher, tasks::{ComputeTaskPool, ParallelSliceMut, TaskPool}, render::render_resource::{Extent3d, TextureAspect, TextureDataOrder, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor, TextureViewDimension}};
 
 mod tile_set_settings;
 pub use tile_set_settings::*;
 
+mod sheet;
+pub use sheet::*;
+
+mod timing;
+pub use timing::*;
+
+#[cfg(feature = "animation")]
+mod animation;
+#[cfg(feature = "animation")]
+pub use animation::*;
+
 mod queue;
 pub use queue::*;
 
 mod downsample;
 pub use downsample::*;
 
-use crate::atlas::{TileAtlasEntry, TileAtlasGroup};
+mod format;
+pub use format::*;
 
-#[derive(Debug)]
-pub enum TileAtlasBuilderReadError {
-    Io(std::io::Error),
-    Decode(bitcode::Error),
-}
+mod manifest;
+pub use manifest::*;
+
+mod diff;
+pub use diff::*;
+
+mod layout;
+pub use layout::*;
+
+mod hot_reload;
+pub use hot_reload::*;
+
+mod settings;
+pub use settings::*;
+
+mod limits;
+pub use limits::*;
+
+mod split;
+
+mod compress;
+pub use compress::*;
+
+mod pixel_format;
+pub use pixel_format::*;
+
+mod material;
+pub use material::*;
+
+mod color;
+
+use crate::atlas::TileAtlasGroup;
 
 #[derive(Debug, Default, Clone, Encode, Decode)]
 pub struct TileAtlasBuilderGroup {
@@ -49,25 +88,243 @@ pub struct TileAtlasBuilderImageSequence {
     pub data: Vec<Box<[u8]>>,
 }
 
-#[derive(Debug, Clone, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode, Asset, TypePath)]
 pub struct TileAtlasBuilder {
-    size:   u32,
+    size:       u32,
+    tile_size:  u32,
+    page_tiles: u32,
     groups: HashMap<String, TileAtlasBuilderGroup, FixedHasher>,
+    pins:   TileAtlasIndexManifest,
+    overflow: u32,
+    compressed: Option<TileAtlasCompressedImage>,
+    format: TileAtlasPixelFormat,
+    border: u32,
+    padded: bool,
+    dedup: bool,
+    timings: HashMap<String, HashMap<String, TileAtlasFrameTiming, FixedHasher>, FixedHasher>,
 }
 
 impl TileAtlasBuilder {
 
     /**
      * Creates a new tile atlas build with the given mip level 0 size.
+     * - Non power of two sizes will only get as many mip levels as the size can be halved evenly, see `Self::with_padding`.
      */
     #[must_use]
     pub fn new(size: u32) -> Self {
         Self{
             size,
-            groups: HashMap::default()
+            tile_size:  size,
+            page_tiles: TILE_ATLAS_PAGE_TILES,
+            groups:     HashMap::default(),
+            pins:       TileAtlasIndexManifest::default(),
+            overflow:   0,
+            compressed: None,
+            format:     TileAtlasPixelFormat::Rgba8Srgb,
+            border:     0,
+            padded:     false,
+            dedup:      false,
+            timings:    HashMap::default(),
         }
     }
 
+    /**
+     * Creates an empty builder with the same tile size, padding, border, page size, format and deduplication.
+     * - Tiles, pins, timings and compression aren't copied.
+     */
+    #[must_use]
+    pub fn empty_like(&self) -> Self {
+        Self{
+            size:       self.size,
+            page_tiles: self.page_tiles,
+            format:     self.format,
+            border:     self.border,
+            padded:     self.padded,
+            dedup:      self.dedup,
+            ..Self::new(self.tile_size)
+        }
+    }
+
+    /**
+     * The size of a tile's content in mip level 0, excluding padding.
+     */
+    #[must_use]
+    pub const fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /**
+     * The size of a tile's slot in mip level 0, including padding.
+     */
+    #[must_use]
+    pub const fn slot_size(&self) -> u32 {
+        self.size
+    }
+
+    /**
+     * Pads every slot up to the next power of two when `padded` is set, which must be set before inserting any tiles.
+     * - Padded tiles keep a full mip chain down to 1x1, at the cost of the unused pixels in each slot, see `Self::uv_scale`.
+     * - The tile's edges are extruded into the padding.
+     */
+    #[must_use]
+    pub fn with_padding(mut self, padded: bool) -> Self {
+        self.set_padding(padded);
+        self
+    }
+
+    /**
+     * Pads every slot up to the next power of two when `padded` is set, see `Self::with_padding`.
+     */
+    pub fn set_padding(&mut self, padded: bool) {
+        assert!(self.groups.is_empty() || self.padded == padded, "Padding can't be changed once tiles are inserted");
+        self.padded = padded;
+        self.update_slot_size();
+    }
+
+    /**
+     * If slots are padded up to the next power of two, see `Self::with_padding`.
+     */
+    #[must_use]
+    pub const fn is_padded(&self) -> bool {
+        self.padded
+    }
+
+    /**
+     * The fraction of a slot covered by a tile's content, carried into the shader by `TileAtlas::uv_scale`.
+     */
+    #[must_use]
+    pub fn uv_scale(&self) -> f32 {
+        self.tile_size as f32 / self.size as f32
+    }
+
+    /**
+     * Sets the format tiles are stored in, which must be set before inserting any tiles.
+     */
+    #[must_use]
+    pub fn with_format(mut self, format: TileAtlasPixelFormat) -> Self {
+        self.set_format(format);
+        self
+    }
+
+    /**
+     * Sets the format tiles are stored in, which must be set before inserting any tiles.
+     */
+    pub fn set_format(&mut self, format: TileAtlasPixelFormat) {
+        assert!(self.groups.is_empty() || self.format == format, "Pixel format can't be changed once tiles are inserted");
+        self.format = format;
+    }
+
+    /**
+     * The format tiles are stored in, `TileAtlasPixelFormat::Rgba8Srgb` by default.
+     */
+    #[must_use]
+    pub const fn format(&self) -> TileAtlasPixelFormat {
+        self.format
+    }
+
+    /**
+     * Surrounds every tile with a `border` of extruded edge pixels, which must be set before inserting any tiles.
+     * - The border halves with each mip level, so it must be a power of two, levels where it's under a pixel are sampled clamped to the tile.
+     * - Slots grow by twice the border, see `Self::uv_offset`, and keep a full mip chain when that's a power of two, such as a 30px tile with a 1px border, or when padded.
+     */
+    #[must_use]
+    pub fn with_border(mut self, border: u32) -> Self {
+        self.set_border(border);
+        self
+    }
+
+    /**
+     * Surrounds every tile with a `border` of extruded edge pixels, which must be set before inserting any tiles, see `Self::with_border`.
+     */
+    pub fn set_border(&mut self, border: u32) {
+        assert!(self.groups.is_empty() || self.border == border, "Border can't be changed once tiles are inserted");
+        assert!(border == 0 || border.is_power_of_two(), "Border must be zero or a power of two");
+        self.border = border;
+        self.update_slot_size();
+    }
+
+    /**
+     * The width of the extruded border around every tile in mip level 0, 0 when tiles have no border.
+     */
+    #[must_use]
+    pub const fn border(&self) -> u32 {
+        self.border
+    }
+
+    /**
+     * Calculates the width of the extruded border in the given mip level.
+     */
+    #[must_use]
+    pub const fn tile_level_border(&self, level: u32) -> u32 {
+        self.border.unbounded_shr(level)
+    }
+
+    /**
+     * The offset of a tile's content from the corner of its slot, as a fraction of the slot, carried into the shader by `TileAtlas::uv_offset`.
+     */
+    #[must_use]
+    pub fn uv_offset(&self) -> f32 {
+        self.border as f32 / self.size as f32
+    }
+
+    /**
+     * Sizes slots to hold the tile and its border, padded as configured.
+     */
+    const fn update_slot_size(&mut self) {
+        let size = self.tile_size + 2*self.border;
+        self.size = if self.padded { size.next_power_of_two() } else { size };
+    }
+
+    /**
+     * Sets the number of tiles along each side of a page, see `TILE_ATLAS_PAGE_TILES` for the default.
+     */
+    #[must_use]
+    pub fn with_page_tiles(mut self, page_tiles: u32) -> Self {
+        self.set_page_tiles(page_tiles);
+        self
+    }
+
+    /**
+     * Sets the number of tiles along each side of a page, see `TILE_ATLAS_PAGE_TILES` for the default.
+     */
+    pub fn set_page_tiles(&mut self, page_tiles: u32) {
+        assert!((1..=TILE_ATLAS_PAGE_TILES_MAX).contains(&page_tiles), "Tiles per page side must be within 1..={TILE_ATLAS_PAGE_TILES_MAX}");
+        if self.page_tiles != page_tiles { self.mark_dirty(); }
+        self.page_tiles = page_tiles;
+    }
+
+    /**
+     * Reduces the tiles per page so a page is at most `max_size` pixels wide, keeping at least one tile per page.
+     * - Devices commonly limit 2D textures to 2048, 4096 or 8192 pixels.
+     */
+    pub fn limit_page_size(&mut self, max_size: u32) {
+        self.set_page_tiles(self.page_tiles.min(max_size/self.size).max(1));
+    }
+
+    /**
+     * The number of tiles along each side of a page.
+     */
+    #[must_use]
+    pub const fn page_tiles(&self) -> u32 {
+        self.page_tiles
+    }
+
+    /**
+     * The number of slots in a page.
+     */
+    #[must_use]
+    pub const fn page_slots(&self) -> u32 {
+        self.page_tiles*self.page_tiles
+    }
+
+    /**
+     * The width and height of a page in pixels at mip level 0.
+     */
+    #[must_use]
+    pub const fn page_size(&self) -> u32 {
+        self.size*self.page_tiles
+    }
+
 }
 
 impl TileAtlasBuilder {
@@ -88,6 +345,20 @@ impl TileAtlasBuilder {
         self.groups.get(group_id).and_then(|v| v.tile_sets.get(tile_id))
     }
 
+    /**
+     * Iterates over the groups as (`group_id`, `group`) in an unspecified order.
+     */
+    pub fn groups(&self) -> impl Iterator<Item = (&str, &TileAtlasBuilderGroup)> {
+        self.groups.iter().map(|(group_id, group)| (group_id.as_str(), group))
+    }
+
+    /**
+     * Iterates over the tiles as (`group_id`, `tile_id`, `tile`) in an unspecified order.
+     */
+    pub fn tiles(&self) -> impl Iterator<Item = (&str, &str, &TileAtlasBuilderSet)> {
+        self.groups().flat_map(|(group_id, group)| group.tile_sets.iter().map(move |(tile_id, set)| (group_id, tile_id.as_str(), set)))
+    }
+
 }
 
 impl TileAtlasBuilder {
@@ -95,7 +366,7 @@ impl TileAtlasBuilder {
     /**
      * Inserts a single image `src` at the specified mip `level` for the given `group_id` and `tile_id`.
      * - Clears the sequence of images at the specified mip level but does not affect any other level of the tile.
-     * - Image data is expected to be in RGBA8 format and in row-major order.
+     * - Image data is expected to be in the builder's pixel format and in row-major order, see `Self::format`.
      * - `src_width` is the width of a row in the `src` image data
      * - A slice of pixels the size of the selected mip level will be extracted from the given `src_offset` into the `src` image
      */
@@ -119,7 +390,7 @@ impl TileAtlasBuilder {
     /**
      * Inserts multiple X by Y slices from a single image `src` at the specified mip `level` for the given `group_id` and `tile_id`.
      * - Clears the sequence of images at the specified mip level but does not affect any other level of the tile.
-     * - Image data is expected to be in RGBA8 format and in row-major order.
+     * - Image data is expected to be in the builder's pixel format and in row-major order, see `Self::format`.
      * - `src_width` is the width of a row in the `src` image data.
      * - See `src_settings` for information regarding how slicing is performed.
      */
@@ -134,24 +405,12 @@ impl TileAtlasBuilder {
         src_width: u32,
         src_settings: TileSetSettings,
     ) {
-        let level_size = self.mip_level_size(level);
-        let tile_count = src_settings.count[0]*src_settings.count[1];
+        let level_size = self.tile_level_size(level);
         self.insert(
             group_id,
             tile_id,
             level,
-            (0..tile_count).map(|i| {
-                let x = i % src_settings.count[0];
-                let y = i / src_settings.count[0];
-                (
-                    src,
-                    src_width,
-                    [
-                        src_settings.offset[0] + x*src_settings.spacing[0] + x*level_size,
-                        src_settings.offset[1] + y*src_settings.spacing[1] + y*level_size,
-                    ]
-                )
-            })
+            src_settings.offsets(level_size).map(|offset| (src, src_width, offset))
         );
     }
 
@@ -165,7 +424,9 @@ impl TileAtlasBuilder {
      * - Images provided using an iterator, see `Self::insert_single` and `Self::insert_tileset` for helpers.
      * - Iterator provides (`image_data`, `image_row_width_pixels`, [`offset_x`, `offset_y`]).
      *     - A section of pixels the size of the specified level will be cut out from the given xy-offset into the image data.
-     *     - Data is expected to be in RGBA8 format and in row-major order.
+     *     - Data is expected to be in the builder's pixel format and in row-major order.
+     * - Padded tiles are cut out at `Self::tile_level_size`, and their edges extruded to fill the slot.
+     * - Tiles with a border are placed `Self::tile_level_border` pixels from the corner of the slot, and extruded on every side.
      */
     pub fn insert<'a>(
         &mut self, 
@@ -181,27 +442,33 @@ impl TileAtlasBuilder {
 
         let dst_width   = self.mip_level_size(level);
         let dst_len     = self.mip_level_data_len(level) as usize;
+        let src_len     = self.tile_level_size(level);
+        let border      = self.tile_level_border(level);
+        let pixel_len   = self.format.pixel_len();
 
+        self.mark_dirty();
         let group = self.groups.entry(group_id.to_owned()).or_default();
         let set   = group.tile_sets.entry(tile_id.to_owned()).or_insert_with(|| TileAtlasBuilderSet::new(level_count));
         let sequence = &mut set.levels[level as usize];
         sequence.data.clear();
 
         for (src, src_width, [src_x, src_y]) in images {
-            let data = if src_x == 0 && src_width == dst_width {
+            let data = if src_x == 0 && src_width == dst_width && src_len == dst_width {
                 // Fast copy, widths match
-                let y_off = (src_y*src_width*4) as usize;
+                let y_off = (src_y*src_width*pixel_len) as usize;
                 Box::from(&src[y_off..y_off+dst_len])
             } else {
                 // Row-by-row copy
                 let mut dst = vec![0_u8; dst_len].into_boxed_slice();
-                for dst_y in 0..dst_width {
-                    rgba8_image_copy_row(
-                        dst_y,
-                             src, src_width, src_x, src_y, 
-                        &mut dst, dst_width,     0, dst_y,
+                for dst_y in 0..src_len {
+                    image_copy_row(
+                        pixel_len,
+                        src_len,
+                             src, src_width, src_x, src_y + dst_y, 
+                        &mut dst, dst_width, border,   border + dst_y,
                     );
                 }
+                image_extrude(&mut dst, pixel_len, dst_width, border, src_len);
                 dst
             };
             sequence.data.push(data);
@@ -213,9 +480,22 @@ impl TileAtlasBuilder {
 impl TileAtlasBuilder {
     
     /**
-     * Merges another atlas builder into this one, overwriting all shared entries.
+     * Merges another atlas builder into this one, overwriting all shared entries and pins.
+     * - An empty builder takes the configuration of `other`, otherwise their sizes, page size, format and border must match.
      */
     pub fn merge(&mut self, other: TileAtlasBuilder) {
+        if self.groups.is_empty() {
+            (self.size, self.tile_size, self.page_tiles) = (other.size, other.tile_size, other.page_tiles);
+            (self.format, self.border, self.padded, self.dedup) = (other.format, other.border, other.padded, other.dedup);
+        } else if !other.groups.is_empty() {
+            assert!(self.size       == other.size,       "Can't merge atlases with different slot sizes");
+            assert!(self.tile_size  == other.tile_size,  "Can't merge atlases with different tile sizes");
+            assert!(self.page_tiles == other.page_tiles, "Can't merge atlases with different page sizes");
+            assert!(self.format     == other.format,     "Can't merge atlases with different pixel formats");
+            assert!(self.border     == other.border,     "Can't merge atlases with different borders");
+        }
+        self.mark_dirty();
+        self.pin_all(&other.pins);
         for (group_id, group) in other.groups {
             match self.groups.entry(group_id) {
                 Entry::Occupied(mut o) => {
@@ -228,6 +508,9 @@ impl TileAtlasBuilder {
             }
 
         }
+        for (group_id, timings) in other.timings {
+            self.timings.entry(group_id).or_default().extend(timings);
+        }
     }
 
 }
@@ -236,9 +519,11 @@ impl TileAtlasBuilder {
 
     /**
      * Removes the tile with given `tile_id` and `group_id`, returning the removed value.
-     * The group will also be removed if it has no tiles.
+     * The group will also be removed if it has no tiles, as is the tile's timing.
      */
     pub fn remove(&mut self, group_id: &str, tile_id: &str) -> Option<TileAtlasBuilderSet> {
+        self.mark_dirty();
+        self.remove_timing(group_id, tile_id);
         if let Some(group) = self.groups.get_mut(group_id) {
             let result = group.tile_sets.remove(tile_id);
             if group.tile_sets.is_empty() { self.groups.remove(group_id); }
@@ -248,12 +533,23 @@ impl TileAtlasBuilder {
         }
     }
 
+    /**
+     * Inserts a tile's `set` of mip levels, replacing any existing tile with the given `tile_id` and `group_id`.
+     * - The set must have a sequence for every mip level, see `Self::mip_levels_max`.
+     */
+    pub fn insert_set(&mut self, group_id: &str, tile_id: &str, set: TileAtlasBuilderSet) {
+        assert_eq!(set.levels.len(), self.mip_levels_max() as usize, "Tile set must have a sequence for every mip level");
+        self.mark_dirty();
+        self.groups.entry(group_id.to_owned()).or_default().tile_sets.insert(tile_id.to_owned(), set);
+    }
+
     /**
      * Removes a single mip `level` from a tile with given `tile_id` and `group_id`, returning the removed value.
      * The tile will also be removed if it has no data on any level.
      */
     pub fn remove_level(&mut self, group_id: &str, tile_id: &str, level: u32) -> Option<TileAtlasBuilderImageSequence> {
         if level >= self.mip_levels_max() { return None; }
+        self.mark_dirty();
         if let Some(set) = self.groups.get_mut(group_id).and_then(|g| g.tile_sets.get_mut(tile_id)) {
             let result = Some(core::mem::take(&mut set.levels[level as usize]));
             if set.levels.iter().all(|s| s.data.is_empty()) { self.remove(group_id, tile_id); }
@@ -268,6 +564,7 @@ impl TileAtlasBuilder {
      * The tile will also be removed if it has no data on any level.
      */
     pub fn remove_levels(&mut self, group_id: &str, tile_id: &str, range: Range<u32>) {
+        self.mark_dirty();
         let Some(set) = self.groups.get_mut(group_id).and_then(|g| g.tile_sets.get_mut(tile_id)) else { return; };
         for level in range {
             if level as usize >= set.levels.len() { break; }
@@ -280,6 +577,8 @@ impl TileAtlasBuilder {
 impl TileAtlasBuilder {
 
     pub fn limit_levels(&mut self, max_level: u32) {
+        let max_level = max_level.min(self.mip_levels_max());
+        if max_level < self.mip_levels_max() { self.mark_dirty(); }
         for group in self.groups.values_mut() {
             for set in group.tile_sets.values_mut() {
                 set.levels[(max_level as usize)..].fill_with(TileAtlasBuilderImageSequence::default);
@@ -287,17 +586,30 @@ impl TileAtlasBuilder {
         }
     }
 
+    /**
+     * Creates mip `levels` for the given range in every tile, using the
+     * provided `downsampler` implementation.
+     * - Tiles are downsampled in parallel on the `ComputeTaskPool`.
+     */
     pub fn downsample_levels(
         &mut self,
         levels: Range<u32>,
         force: bool,
-        downsampler: impl DownsampleAlgorithm,
+        downsampler: impl DownsampleAlgorithm + Sync,
     ) {
+        self.mark_dirty();
+        let format         = self.format;
+        let base_size      = self.size;
+        let (tile_size, border) = (self.tile_size, self.border);
         let mip_levels_max = self.mip_levels_max();
-        for group in self.groups.values_mut() {
-            for set in group.tile_sets.values_mut() {
+        let mut sets = self.groups.values_mut().flat_map(|g| g.tile_sets.values_mut()).collect::<Vec<_>>();
+        sets.par_splat_map_mut(compute_task_pool(), None, |_, sets| {
+            for set in sets {
                 Self::downsample_levels_in_set(
-                    self.size,
+                    format,
+                    base_size,
+                    tile_size,
+                    border,
                     mip_levels_max,
                     set,
                     levels.clone(),
@@ -305,7 +617,7 @@ impl TileAtlasBuilder {
                     &downsampler
                 );
             }
-        }
+        });
     }
     /**
      * Creates mip `levels` for the given range in the tile with the
@@ -322,8 +634,12 @@ impl TileAtlasBuilder {
 
         downsampler: impl DownsampleAlgorithm,
     ) {
+        self.mark_dirty();
         Self::downsample_levels_in_set(
+            self.format,
             self.size,
+            self.tile_size,
+            self.border,
             self.mip_levels_max(),
             self.groups.get_mut(group_id).and_then(|g| g.tile_sets.get_mut(tile_id)).unwrap(),
             levels,
@@ -332,8 +648,43 @@ impl TileAtlasBuilder {
         );
     }
 
+    /**
+     * Creates mip `levels` for the given range in every tile of the
+     * group with the given `group_id`, using the provided `downsampler`
+     * implementation.
+     */
+    pub fn downsample_levels_for_group(
+        &mut self,
+
+        group_id: &str,
+        levels:   Range<u32>,
+        force: bool,
+
+        downsampler: impl DownsampleAlgorithm,
+    ) {
+        self.mark_dirty();
+        let mip_levels_max = self.mip_levels_max();
+        let Some(group) = self.groups.get_mut(group_id) else { return; };
+        for set in group.tile_sets.values_mut() {
+            Self::downsample_levels_in_set(
+                self.format,
+                self.size,
+                self.tile_size,
+                self.border,
+                mip_levels_max,
+                set,
+                levels.clone(),
+                force,
+                &downsampler
+            );
+        }
+    }
+
     fn downsample_levels_in_set(
+        format: TileAtlasPixelFormat,
         base_size: u32,
+        tile_size: u32,
+        border: u32,
         mip_levels_max: u32,
 
         set: &mut TileAtlasBuilderSet,
@@ -345,17 +696,24 @@ impl TileAtlasBuilder {
         for level in levels.skip(1) {
             if level >= mip_levels_max { return; }
 
-            let [prev, current] = set.levels.get_disjoint_mut([(level-1) as usize, level as usize]).unwrap();
+            let (head, tail) = set.levels.split_at_mut(level as usize);
+            let (base, prev, current) = (&head[0], &head[(level-1) as usize], &mut tail[0]);
             let src_size = mip_level_size(base_size, level-1) as usize;
-            let dst_len  = mip_level_data_len(base_size, level) as usize;
+            let dst_size = mip_level_size(base_size, level);
+            let dst_len  = mip_level_data_len(base_size, level, format.pixel_len()) as usize;
 
             if force {
                 current.data.clear();
             }
 
-            for src in prev.data.iter().skip(current.data.len()) {
+            for (frame, src) in prev.data.iter().enumerate().skip(current.data.len()) {
                 let mut dst = vec![0_u8; dst_len].into_boxed_slice();
-                downsampler.downsample(src, src_size, &mut dst);
+                let base = base.data.get(frame).unwrap_or(src);
+                downsampler.downsample_format(format, base, src, src_size, &mut dst);
+                // Borders are extruded again, as downsampling blends them with the tile's inner pixels
+                if border > 0 {
+                    image_extrude(&mut dst, format.pixel_len(), dst_size, border >> level, tile_size.div_ceil(1 << level));
+                }
                 current.data.push(dst);
             }
         }
@@ -367,22 +725,14 @@ impl TileAtlasBuilder {
 
     #[must_use]
     pub fn build_lookup(&self) -> HashMap<String, TileAtlasGroup, FixedHasher> {
-        let mut index = 0;
-        let mut lookup = HashMap::<String, TileAtlasGroup, FixedHasher>::default();
-        for (group_name, group_src) in &self.groups {
-            let mut group_dst = TileAtlasGroup::default();
-            for (set_name, set) in &group_src.tile_sets {
-                if set.levels[0].data.is_empty() { continue; }
-
-                group_dst.insert(set_name.clone(), TileAtlasEntry{
-                    index,
-                    count: set.levels[0].data.len() as u16
-                });
-                index += set.levels[0].data.len() as u16;
-            }
+        Self::build_lookup_with_layout(&self.build_layout())
+    }
 
-            if group_dst.is_empty() { continue; }
-            lookup.insert(group_name.clone(), group_dst);
+    #[must_use]
+    pub fn build_lookup_with_layout(layout: &TileAtlasLayout) -> HashMap<String, TileAtlasGroup, FixedHasher> {
+        let mut lookup = HashMap::<String, TileAtlasGroup, FixedHasher>::default();
+        for e in layout.iter() {
+            lookup.entry(e.group_id.clone()).or_default().insert(e.tile_id.clone(), e.entry);
         }
         lookup
     }
@@ -393,23 +743,32 @@ impl TileAtlasBuilder {
             Some("tile_atlas_texture"),
             TextureUsages::TEXTURE_BINDING,
             RenderAssetUsages::RENDER_WORLD,
-            ImageSampler::Descriptor(ImageSamplerDescriptor{
-                label: Some("tile_atlas_texture_sampler".to_owned()),
-                address_mode_u: ImageAddressMode::ClampToEdge,
-                address_mode_v: ImageAddressMode::ClampToEdge,
-                address_mode_w: ImageAddressMode::ClampToEdge,
-                mag_filter:    ImageFilterMode::Nearest,
-                min_filter:    ImageFilterMode::Linear,
-                mipmap_filter: ImageFilterMode::Linear,
-                lod_min_clamp: 0.0,
-                lod_max_clamp: 32.0,
-                compare: None,
-                anisotropy_clamp: 1,
-                border_color: None,
-            })
+            ImageSampler::Descriptor(Self::default_sampler_descriptor())
         )
     }
 
+    /**
+     * The sampler used by `Self::build_image`, filters linearly between mip levels but
+     * uses nearest filtering when magnified.
+     */
+    #[must_use]
+    pub fn default_sampler_descriptor() -> ImageSamplerDescriptor {
+        ImageSamplerDescriptor{
+            label: Some("tile_atlas_texture_sampler".to_owned()),
+            address_mode_u: ImageAddressMode::ClampToEdge,
+            address_mode_v: ImageAddressMode::ClampToEdge,
+            address_mode_w: ImageAddressMode::ClampToEdge,
+            mag_filter:    ImageFilterMode::Nearest,
+            min_filter:    ImageFilterMode::Linear,
+            mipmap_filter: ImageFilterMode::Linear,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+            compare: None,
+            anisotropy_clamp: 1,
+            border_color: None,
+        }
+    }
+
     #[must_use]
     pub fn build_image_with_settings(
         &self,
@@ -419,31 +778,64 @@ impl TileAtlasBuilder {
         sampler:       ImageSampler
     ) -> Image {
         let mip_level_count = self.find_mip_level_common_max();
+        let layout = self.build_layout();
+        let data   = self.build_image_data(&layout, mip_level_count);
+        self.image_from_data(data, self.format.texture_format(), layout.page_count(), mip_level_count, texture_label, texture_usage, asset_usage, sampler)
+    }
 
-        let page_count = self.page_count();
-        let total_len  = (page_count as usize) * (0..mip_level_count).map(|i| (self.mip_level_data_len(i)*16*16) as usize).sum::<usize>();
-        let mut dst    = Vec::<u8>::with_capacity(total_len);
-
+    /**
+     * Builds the uncompressed data of the first `mip_level_count` levels of every page in the `layout`, in layer major order.
+     */
+    fn build_image_data(&self, layout: &TileAtlasLayout, mip_level_count: u32) -> Vec<u8> {
+        let page_count = layout.page_count();
+        let total_len  = (page_count as usize) * (0..mip_level_count).map(|i| self.page_len(i) as usize).sum::<usize>();
+        let mut dst    = vec![0_u8; total_len];
+
+        // Every page and level is a disjoint region of the image, so they're written in parallel
+        let mut jobs = Vec::with_capacity((page_count*mip_level_count) as usize);
+        let mut rest = dst.as_mut_slice();
         for page in 0..page_count {
             for level in 0..mip_level_count {
-                self.build_page(&mut dst, level, page);
+                let (dst_page, next) = rest.split_at_mut(self.page_len(level) as usize);
+                jobs.push((page, level, dst_page));
+                rest = next;
             }
         }
 
+        jobs.par_splat_map_mut(compute_task_pool(), None, |_, jobs| {
+            for (page, level, dst_page) in jobs {
+                self.write_page_with_layout(layout, dst_page, *level, *page);
+            }
+        });
+
+        dst
+    }
+
+    const fn image_from_data(
+        &self,
+        data:            Vec<u8>,
+        format:          TextureFormat,
+        page_count:      u32,
+        mip_level_count: u32,
+        texture_label:   Option<&'static str>,
+        texture_usage:   TextureUsages,
+        asset_usage:     RenderAssetUsages,
+        sampler:         ImageSampler
+    ) -> Image {
         Image{
-            data: Some(dst),
+            data: Some(data),
             data_order: TextureDataOrder::LayerMajor,
             texture_descriptor: TextureDescriptor { 
                 label: texture_label, 
                 size: Extent3d {
-                    width:  self.size * 16,
-                    height: self.size * 16,
+                    width:  self.page_size(),
+                    height: self.page_size(),
                     depth_or_array_layers: page_count,
                 }, 
                 mip_level_count, 
                 sample_count: 1, 
                 dimension: TextureDimension::D2, 
-                format: TextureFormat::Rgba8UnormSrgb, 
+                format, 
                 usage: texture_usage, 
                 view_formats: &[],
             },
@@ -462,7 +854,6 @@ impl TileAtlasBuilder {
             sampler,
             copy_on_resize: false,
         }
-
     }
 
     #[must_use]
@@ -475,42 +866,68 @@ impl TileAtlasBuilder {
 
     #[must_use]
     pub fn image_count(&self) -> u32 {
-        self.groups.values().flat_map(|g| g.tile_sets.values().map(|s| s.levels[0].data.len())).sum::<usize>() as u32
+        self.groups.values().flat_map(|g| g.tile_sets.values().map(|s| s.levels.first().map_or(0, |l| l.data.len()))).sum::<usize>() as u32
     }
 
+    /**
+     * The number of pages in the built atlas, this includes any unused slots between pinned tiles.
+     * - Lays out the atlas on every call, use `TileAtlasLayout::page_count` when a layout has already been built.
+     */
     #[must_use]
     pub fn page_count(&self) -> u32 {
-        self.image_count().div_ceil(16*16)
+        self.build_layout().page_count()
     }
 
     #[must_use]
     pub const fn page_len(&self, level: u32) -> u32 {
-        self.mip_level_data_len(level)*16*16
+        self.mip_level_data_len(level)*self.page_slots()
     }
 
     pub fn build_page(&self, dst: &mut Vec<u8>, level: u32, page: u32) {
-        // Allocate Page //
-        // TODO OPT version where these values are provided.
+        self.build_page_with_layout(&self.build_layout(), dst, level, page);
+    }
+
+    /**
+     * Builds a page using a precomputed `layout`, which must have been built from this builder.
+     * - Unused slots are left transparent.
+     */
+    pub fn build_page_with_layout(&self, layout: &TileAtlasLayout, dst: &mut Vec<u8>, level: u32, page: u32) {
         let page_start = dst.len();
         let page_len   = self.page_len(level) as usize;
         dst.resize(dst.len() + page_len, 0);
-        let dst_page = &mut dst[page_start..page_start+page_len];
+        self.write_page_with_layout(layout, &mut dst[page_start..page_start+page_len], level, page);
+    }
 
-        // Copy Images //
-        let images     = self.groups.values().flat_map(|g| g.tile_sets.values().flat_map(|s| s.levels[level as usize].data.iter())).skip((page*16*16) as usize).take(16*16);
+    /**
+     * Copies the tiles of a page into `dst_page`, which must be zeroed and exactly `Self::page_len` long.
+     */
+    fn write_page_with_layout(&self, layout: &TileAtlasLayout, dst_page: &mut [u8], level: u32, page: u32) {
+        // Entries are sorted and don't overlap, so the tiles on the page are a contiguous run, shared tiles are already written by them
+        let page_range = (page*self.page_slots())..((page+1)*self.page_slots());
+        let entries    = layout.entries();
+        let first      = entries.partition_point(|e| (e.entry.index as u32 + e.entry.count as u32) <= page_range.start);
+        let images     = entries[first..].iter()
+            .take_while(|e| (e.entry.index as u32) < page_range.end)
+            .flat_map(|e| {
+                let set = self.get_tile(&e.group_id, &e.tile_id).unwrap();
+                set.levels[level as usize].data.iter().enumerate().map(move |(i, src)| (e.entry.index as u32 + i as u32, src))
+            })
+            .filter(|(slot, _)| page_range.contains(slot));
         let level_size = self.mip_level_size(level);
-        for (idx, src) in images.enumerate() {
-            let x = (idx &  0x0F) as u32;
-            let y = (idx >>    4) as u32;
+        for (slot, src) in images {
+            let idx = slot - page_range.start;
+            let x = idx % self.page_tiles;
+            let y = idx / self.page_tiles;
 
             let dst_x = x*level_size;
             let dst_y = y*level_size;
 
             for row in 0..level_size {
-                rgba8_image_copy_row(
-                    level_size, 
+                image_copy_row(
+                    self.format.pixel_len(),
+                    level_size,
                     src,         level_size,     0, row, 
-                    dst_page, 16*level_size, dst_x, row+dst_y
+                    dst_page, self.page_tiles*level_size, dst_x, row+dst_y
                 );
             }
         }
@@ -548,12 +965,20 @@ impl TileAtlasBuilder {
         mip_level_size(self.size, level)
     }
 
+    /**
+     * Calculates the size of a tile's content in the given mip level, excluding padding
+     */
+    #[must_use]
+    pub const fn tile_level_size(&self, level: u32) -> u32 {
+        self.tile_size.div_ceil(1 << level)
+    }
+
     /**
      * Calculates the size of a tile's image in the given mip level
      */
     #[must_use]
     pub const fn mip_level_data_len(&self, level: u32) -> u32 {
-        mip_level_data_len(self.size, level)
+        mip_level_data_len(self.size, level, self.format.pixel_len())
     }
 
     /**
@@ -568,7 +993,7 @@ impl TileAtlasBuilder {
         let page  = page as usize;
         let level = level as usize;
         
-        let size_mips = (0..mip_levels_max).map(|i| (self.mip_level_data_len(i)*16*16) as usize).collect::<Vec<_>>();
+        let size_mips = (0..mip_levels_max).map(|i| self.page_len(i) as usize).collect::<Vec<_>>();
 
         let page_offset_base  = size_mips.iter().sum::<usize>()*page;
         let page_offset_inner = size_mips.iter().take(level).sum::<usize>();
@@ -579,31 +1004,11 @@ impl TileAtlasBuilder {
     
 }
 
-const COMPRESSION_BUFFER_SIZE: usize = 8*1024*1024; // 8 MiB
-
-impl TileAtlasBuilder {
-
-    pub fn write_compressed_to(&self, sink: impl Write) -> Result<(), std::io::Error> {
-        brotli::CompressorWriter::new(sink, COMPRESSION_BUFFER_SIZE, 7, 24)
-            .write_all(&bitcode::encode(self))
-    }
-
-    pub fn read_compressed_from(source: impl Read) -> Result<Self, TileAtlasBuilderReadError> {
-        let mut vec = Vec::with_capacity(COMPRESSION_BUFFER_SIZE);
-        brotli::Decompressor::new(source, COMPRESSION_BUFFER_SIZE).read_to_end(&mut vec).map_err(TileAtlasBuilderReadError::Io)?;
-        bitcode::decode(&vec).map_err(TileAtlasBuilderReadError::Decode)
-    }
-
-    pub fn write_uncompressed_to(&self, mut sink: impl Write) -> Result<(), std::io::Error> {
-        sink.write_all(&bitcode::encode(self))
-    }
-
-    pub fn read_uncompressed_from(mut source: impl Read) -> Result<Self, TileAtlasBuilderReadError> {
-        let mut vec = Vec::with_capacity(COMPRESSION_BUFFER_SIZE);
-        source.read_to_end(&mut vec).map_err(TileAtlasBuilderReadError::Io)?;
-        bitcode::decode(&vec).map_err(TileAtlasBuilderReadError::Decode)
-    }
-
+/**
+ * The pool used to build atlases, initialized with the default settings when used outside of a Bevy app.
+ */
+fn compute_task_pool() -> &'static TaskPool {
+    ComputeTaskPool::get_or_init(TaskPool::default)
 }
 
 /**
@@ -626,15 +1031,16 @@ const fn mip_level_size(size: u32, level: u32) -> u32 {
  * Calculates the size of a tile's image in the given mip level
  */
 #[must_use]
-const fn mip_level_data_len(size: u32, level: u32) -> u32 {
-    mip_level_size(size, level).pow(2) * 4
+const fn mip_level_data_len(size: u32, level: u32, pixel_len: u32) -> u32 {
+    mip_level_size(size, level).pow(2) * pixel_len
 }
 
 /**
  * Copies a row of the destination width from the source image at the given position into the destination image in the specified row.
- * Assumes each pixel is 4-bytes long.
+ * Each pixel is `pixel_len` bytes long.
  */
-fn rgba8_image_copy_row(
+fn image_copy_row(
+    pixel_len: u32,
     copy_width: u32,
 
     src: &[u8],
@@ -653,11 +1059,133 @@ fn rgba8_image_copy_row(
     assert!(dst_x + copy_width <= dst_width,       "Attempt to copy to image out of bounds (x-axis)");
     assert!(dst_y * dst_width  < dst.len() as u32, "Attempt to copy to image out of bounds (y-axis)");
 
-    let src_start = 4*(src_y*src_width + src_x) as usize;
-    let dst_start = 4*(dst_y*dst_width + dst_x) as usize;
+    let pixel_len = pixel_len as usize;
+    let src_start = pixel_len*(src_y*src_width + src_x) as usize;
+    let dst_start = pixel_len*(dst_y*dst_width + dst_x) as usize;
 
-    let src_end = src_start + (copy_width as usize)*4;
-    let dst_end = dst_start + (copy_width as usize)*4;
+    let src_end = src_start + (copy_width as usize)*pixel_len;
+    let dst_end = dst_start + (copy_width as usize)*pixel_len;
 
     dst[dst_start..dst_end].copy_from_slice(&src[src_start..src_end]);
 }
+
+/**
+ * Fills a `width` sized image around the `content` pixels starting at `offset` in both axes, by repeating the outermost columns and rows.
+ * Each pixel is `pixel_len` bytes long.
+ */
+pub(crate) fn image_extrude(dst: &mut [u8], pixel_len: u32, width: u32, offset: u32, content: u32) {
+    let (pixel_len, width, offset, content) = (pixel_len as usize, width as usize, offset as usize, content as usize);
+    if offset == 0 && content >= width { return; }
+    let row_len = width*pixel_len;
+
+    for row in dst.chunks_exact_mut(row_len).skip(offset).take(content) {
+        let (left, rest)    = row.split_at_mut(offset*pixel_len);
+        let (middle, right) = rest.split_at_mut(content*pixel_len);
+        let (first, last)   = (&middle[..pixel_len], &middle[middle.len()-pixel_len..]);
+        for pixel in left.chunks_exact_mut(pixel_len) {
+            pixel.copy_from_slice(first);
+        }
+        for pixel in right.chunks_exact_mut(pixel_len) {
+            pixel.copy_from_slice(last);
+        }
+    }
+
+    let (above, rest)   = dst.split_at_mut(offset*row_len);
+    let (middle, below) = rest.split_at_mut(content*row_len);
+    let (first, last)   = (&middle[..row_len], &middle[(content-1)*row_len..]);
+    for row in above.chunks_exact_mut(row_len) {
+        row.copy_from_slice(first);
+    }
+    for row in below.chunks_exact_mut(row_len) {
+        row.copy_from_slice(last);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * An atlas of 23 frames on pages of 3x3 slots, so the last page is partly empty.
+     */
+    fn builder() -> TileAtlasBuilder {
+        let mut builder = TileAtlasBuilder::new(4).with_page_tiles(3);
+        let mut value = 0_u8;
+        for (group_id, tile_id, frames) in [("walls", "brick", 5), ("walls", "stone", 1), ("floors", "grass", 9), ("floors", "dirt", 8)] {
+            let src = (0..frames*4*4*4).map(|_| { value = value.wrapping_mul(31).wrapping_add(7); value }).collect::<Vec<_>>();
+            builder.insert(group_id, tile_id, 0, (0..frames).map(|frame| (src.as_slice(), 4, [0, frame*4])));
+        }
+        builder
+    }
+
+    /**
+     * Downsamples every tile one at a time from the previous level, as before tiles were downsampled in parallel.
+     */
+    fn downsample_serial(builder: &mut TileAtlasBuilder, downsampler: &impl DownsampleAlgorithm) {
+        let (base_size, mip_levels_max) = (builder.size, builder.mip_levels_max());
+        for set in builder.groups.values_mut().flat_map(|g| g.tile_sets.values_mut()) {
+            for level in 1..mip_levels_max {
+                let [prev, current] = set.levels.get_disjoint_mut([(level-1) as usize, level as usize]).unwrap();
+                current.data = prev.data.iter().map(|src| {
+                    let mut dst = vec![0_u8; mip_level_data_len(base_size, level, 4) as usize].into_boxed_slice();
+                    downsampler.downsample(src, mip_level_size(base_size, level-1) as usize, &mut dst);
+                    dst
+                }).collect();
+            }
+        }
+    }
+
+    /**
+     * Assembles every page one at a time by counting frames in tile order, as before tiles were laid out.
+     */
+    fn build_image_serial(builder: &TileAtlasBuilder) -> Vec<u8> {
+        let mut tiles = builder.tiles().collect::<Vec<_>>();
+        tiles.sort_unstable_by_key(|&(group_id, tile_id, _)| (group_id, tile_id));
+
+        let (page_tiles, page_slots) = (builder.page_tiles as usize, builder.page_slots() as usize);
+        let frame_count = tiles.iter().map(|(_, _, set)| set.levels[0].data.len()).sum::<usize>();
+        let mut dst = Vec::new();
+        for page in 0..frame_count.div_ceil(page_slots) {
+            for level in 0..builder.find_mip_level_common_max() {
+                let level_size = builder.mip_level_size(level) as usize;
+                let row_len    = level_size*4;
+                let mut page_data = vec![0_u8; builder.page_len(level) as usize];
+                let images = tiles.iter().flat_map(|(_, _, set)| set.levels[level as usize].data.iter()).skip(page*page_slots).take(page_slots);
+                for (idx, src) in images.enumerate() {
+                    let (x, y) = (idx % page_tiles, idx / page_tiles);
+                    for row in 0..level_size {
+                        let start = ((y*level_size + row)*page_tiles + x)*row_len;
+                        page_data[start..start + row_len].copy_from_slice(&src[row*row_len..][..row_len]);
+                    }
+                }
+                dst.extend(page_data);
+            }
+        }
+        dst
+    }
+
+    #[test]
+    fn downsample_matches_serial() {
+        let mut builder = builder();
+        let mut serial  = builder.clone();
+        builder.downsample_levels(0..u32::MAX, false, DownsampleBilinearSRGB);
+        downsample_serial(&mut serial, &DownsampleBilinearSRGB);
+
+        assert!(builder.mip_levels_complete());
+        for (group_id, tile_id, set) in builder.tiles() {
+            let reference = serial.get_tile(group_id, tile_id).unwrap();
+            assert!(set.levels.iter().zip(&reference.levels).all(|(a, b)| a.data == b.data), "Mismatched mips for {group_id}/{tile_id}");
+        }
+    }
+
+    #[test]
+    fn build_image_matches_serial() {
+        let mut builder = builder();
+        builder.downsample_levels(0..u32::MAX, false, DownsampleBilinearSRGB);
+        assert_eq!(builder.page_count(), 3);
+
+        let image = builder.build_image();
+        assert!(image.data.unwrap() == build_image_serial(&builder), "Mismatched atlas image");
+    }
+
+}
@@ -0,0 +1,170 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::borrow::Cow;
+
+use bevy::{image::Image, render::render_resource::TextureFormat};
+use bitcode::{Decode, Encode};
+use half::f16;
+use serde::{Deserialize, Serialize};
+
+use super::color::{linear_to_srgba, srgba_to_linear};
+
+/**
+ * The format tiles are stored in by the builder and the atlas texture.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode, Serialize, Deserialize)]
+pub enum TileAtlasPixelFormat {
+    /// `Rgba8UnormSrgb`, colour tiles filtered in linear space.
+    #[default]
+    Rgba8Srgb,
+    /// `Rgba8Unorm`, data such as normal maps, stored and filtered as is.
+    Rgba8Unorm,
+    /// `R8Unorm`, a single channel for masks, sampled as `(r, 0, 0, 1)`.
+    R8Unorm,
+    /// `Rgba16Float`, linear half floats for HDR tiles such as emissive.
+    Rgba16Float,
+}
+
+impl TileAtlasPixelFormat {
+
+    #[must_use]
+    pub const fn texture_format(self) -> TextureFormat {
+        match self {
+            Self::Rgba8Srgb   => TextureFormat::Rgba8UnormSrgb,
+            Self::Rgba8Unorm  => TextureFormat::Rgba8Unorm,
+            Self::R8Unorm     => TextureFormat::R8Unorm,
+            Self::Rgba16Float => TextureFormat::Rgba16Float,
+        }
+    }
+
+    /**
+     * The pixel format of an atlas texture, including compressed textures, see `TileAtlasCompression`.
+     */
+    #[must_use]
+    pub const fn from_texture_format(format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::Rgba8UnormSrgb | TextureFormat::Bc7RgbaUnormSrgb => Some(Self::Rgba8Srgb),
+            TextureFormat::Rgba8Unorm     | TextureFormat::Bc7RgbaUnorm     => Some(Self::Rgba8Unorm),
+            TextureFormat::R8Unorm     => Some(Self::R8Unorm),
+            TextureFormat::Rgba16Float => Some(Self::Rgba16Float),
+            _ => None,
+        }
+    }
+
+    /**
+     * The length of a pixel in bytes.
+     */
+    #[must_use]
+    pub const fn pixel_len(self) -> u32 {
+        match self {
+            Self::Rgba8Srgb | Self::Rgba8Unorm => 4,
+            Self::R8Unorm     => 1,
+            Self::Rgba16Float => 8,
+        }
+    }
+
+    #[must_use]
+    pub const fn is_srgb(self) -> bool {
+        matches!(self, Self::Rgba8Srgb)
+    }
+
+    #[must_use]
+    pub const fn is_float(self) -> bool {
+        matches!(self, Self::Rgba16Float)
+    }
+
+    #[must_use]
+    pub const fn has_alpha(self) -> bool {
+        !matches!(self, Self::R8Unorm)
+    }
+
+    /**
+     * The largest value a channel can store, normalized formats store up to 1.
+     */
+    #[must_use]
+    pub const fn max_value(self) -> f32 {
+        if self.is_float() { 65504.0 } else { 1.0 }
+    }
+
+    /**
+     * Reads the pixel at `offset` as stored, normalized to 0 to 1 for integer formats.
+     * - Single channel pixels are read as `(r, r, r, 1)`.
+     */
+    pub(super) fn read(self, src: &[u8], offset: usize) -> [f32; 4] {
+        let len   = self.pixel_len() as usize;
+        let pixel = &src[offset*len..][..len];
+        match self {
+            Self::Rgba8Srgb | Self::Rgba8Unorm => core::array::from_fn(|c| (pixel[c] as f32)/255.0),
+            Self::R8Unorm     => { let r = (pixel[0] as f32)/255.0; [r, r, r, 1.0] },
+            Self::Rgba16Float => core::array::from_fn(|c| f16::from_le_bytes([pixel[c*2], pixel[c*2+1]]).to_f32()),
+        }
+    }
+
+    /**
+     * Writes the pixel at `offset` as stored, integer formats are either truncated or `rounded`.
+     * - Single channel pixels keep the red channel.
+     */
+    pub(super) fn write(self, dst: &mut [u8], offset: usize, value: [f32; 4], rounded: bool) {
+        let len   = self.pixel_len() as usize;
+        let pixel = &mut dst[offset*len..][..len];
+        let to_u8 = |v: f32| if rounded { (v*255.0).round() as u8 } else { (v*255.0) as u8 };
+        match self {
+            Self::Rgba8Srgb | Self::Rgba8Unorm => pixel.copy_from_slice(&value.map(to_u8)),
+            Self::R8Unorm     => pixel[0] = to_u8(value[0]),
+            Self::Rgba16Float => for (dst, v) in pixel.chunks_exact_mut(2).zip(value) {
+                // Transparent pixels divide by zero when demultiplied, and have no meaningful colour
+                dst.copy_from_slice(&f16::from_f32(if v.is_nan() { 0.0 } else { v }).to_le_bytes());
+            },
+        }
+    }
+
+    /**
+     * Converts pixels stored in the texture format `src_format` to this format, borrowing `src` when they're stored alike.
+     * - Values are kept as stored, except between sRGB and float formats, which convert between sRGB and linear.
+     * - `Rgba32Float` sources are supported as well, returns `None` for any other format.
+     */
+    #[must_use]
+    pub fn convert(self, src: &[u8], src_format: TextureFormat) -> Option<Cow<'_, [u8]>> {
+        // RGBA8 formats only differ in how they're sampled
+        let src_format = match src_format {
+            TextureFormat::Rgba32Float => None,
+            format => Some(Self::from_texture_format(format).filter(|f| f.texture_format() == format)?),
+        };
+        let stored_alike = |f: Self| f == self || (f.pixel_len() == 4 && self.pixel_len() == 4 && !f.is_float() && !self.is_float());
+        if src_format.is_some_and(stored_alike) { return Some(Cow::Borrowed(src)); }
+
+        let src_len   = src_format.map_or(16, Self::pixel_len) as usize;
+        let src_srgb  = src_format.is_some_and(Self::is_srgb);
+        let src_float = src_format.is_none_or(Self::is_float);
+        let count     = src.len()/src_len;
+
+        let mut dst = vec![0_u8; count*self.pixel_len() as usize];
+        for i in 0..count {
+            let value = match src_format {
+                Some(format) => format.read(src, i),
+                None => core::array::from_fn(|c| f32::from_le_bytes(src[i*16 + c*4..][..4].try_into().unwrap())),
+            };
+            let value = match (src_srgb, src_float, self.is_srgb(), self.is_float()) {
+                (true, _, _, true)  => srgba_to_linear(value),
+                (_, true, true, _)  => linear_to_srgba(value.map(|v| v.clamp(0.0, 1.0))),
+                _ => value,
+            };
+            self.write(&mut dst, i, value.map(|v| v.clamp(0.0, self.max_value())), true);
+        }
+        Some(Cow::Owned(dst))
+    }
+
+    /**
+     * The data of an `Image` in this format, see `Self::convert`.
+     * - Images in other formats are converted to sRGB RGBA8 by bevy first, if they can be.
+     */
+    #[must_use]
+    pub fn image_data(self, image: &Image) -> Option<Cow<'_, [u8]>> {
+        let data = image.data.as_deref()?;
+        self.convert(data, image.texture_descriptor.format).or_else(|| {
+            let image = image.convert(TextureFormat::Rgba8UnormSrgb)?;
+            self.convert(image.data.as_deref()?, TextureFormat::Rgba8UnormSrgb).map(|data| Cow::Owned(data.into_owned()))
+        })
+    }
+
+}
@@ -1,10 +1,11 @@
 // Copyright 2025 Natalie Baker // Apache License v2 //
 
+use core::time::Duration;
 use std::collections::HashMap;
 
-use crate::atlas::{builder::{DownsampleBilinearSRGB, TileAtlasBuilder, TileSetSettings}, TileAtlas};
+use crate::atlas::{builder::{TileAtlasBuildSettings, TileAtlasBuilder, TileAtlasCompression, TileAtlasHotReload, TileAtlasLimits, TileAtlasSource, TileAtlasIndexManifest, TileAtlasManifest, TileAtlasManifestError, TileAtlasManifestMips, TileAtlasManifestTile, TileAtlasPixelFormat, TileAtlasSheet, TileAtlasSheetError, TileSetError, TileSetSettings}, TileAtlas};
 
-use bevy::{platform::hash::FixedHasher, prelude::*};
+use bevy::{image::{CompressedImageFormatSupport, CompressedImageFormats}, platform::{hash::FixedHasher, time::Instant}, prelude::*, render::renderer::RenderDevice, tasks::{futures::check_ready, AsyncComputeTaskPool, Task}};
 
 #[derive(Debug, Component)]
 pub struct TileAtlasBuildQueueTarget {
@@ -21,13 +22,115 @@ impl TileAtlasBuildQueueTarget {
 
 }
 
-#[derive(Debug, Default, Component)]
+/**
+ * The time `proccess_tile_atlas_build_queue` may spend loading tiles each frame, shared by every queue.
+ * - At least one tile is loaded each frame regardless of the budget.
+ */
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct TileAtlasBuildBudget {
+    pub per_frame: Duration,
+}
+
+impl Default for TileAtlasBuildBudget {
+    fn default() -> Self {
+        Self {
+            per_frame: Duration::from_millis(4),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TileAtlasBuildStage {
+    /// The queue isn't locked yet.
+    #[default]
+    Queued,
+    /// Tiles are being sliced and downsampled as their images become available.
+    Loading,
+    /// The atlas image is being built on the `AsyncComputeTaskPool`.
+    Building,
+    /// The atlas didn't fit within its limits even when split, the queue has been removed, see `TileAtlasBuildSettings::limits`.
+    Failed,
+}
+
+/**
+ * The progress of a `TileAtlasBuildQueue`, kept up to date on the queue entity.
+ */
+#[derive(Debug, Default, Clone, Copy, Component)]
+pub struct TileAtlasBuildProgress {
+    pub stage:  TileAtlasBuildStage,
+    pub loaded: usize,
+    pub total:  usize,
+}
+
+impl TileAtlasBuildProgress {
+
+    /**
+     * The fraction of tiles loaded, from 0 to 1.
+     */
+    #[must_use]
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 { return 0.0; }
+        (self.loaded as f32 / self.total as f32).min(1.0)
+    }
+
+}
+
+/**
+ * The atlas being built from a completed `TileAtlasBuildQueue`.
+ */
+#[derive(Component)]
+pub struct TileAtlasBuildTask {
+    task: Task<Vec<(TileAtlasBuilder, Image)>>,
+    hot_reload: Option<(HashMap<String, HashMap<String, TileAtlasSource, FixedHasher>, FixedHasher>, TileAtlasBuildSettings)>,
+}
+
+#[derive(Debug, Component)]
+#[require(TileAtlasBuildProgress)]
+#[allow(clippy::struct_excessive_bools)] // Independent settings, not a state machine
 pub struct TileAtlasBuildQueue {
     builder: Option<TileAtlasBuilder>,
     queue: HashMap<String, HashMap<String, TileAtlasBuildQueueImageItem, FixedHasher>, FixedHasher>,
+    queue_manifest: HashMap<String, HashMap<String, TileAtlasBuildQueueManifestItem, FixedHasher>, FixedHasher>,
+    queue_sheet: Vec<TileAtlasBuildQueueSheetItem>,
+    mips: TileAtlasManifestMips,
+    limit_levels: Option<u32>,
+    page_tiles:   Option<u32>,
+    compression:  Option<TileAtlasCompression>,
+    format: TileAtlasPixelFormat,
+    border: u32,
+    padded: bool,
+    dedup:  bool,
+    pins: TileAtlasIndexManifest,
     count_loaded: usize,
     count_total:  usize,
     queue_locked: bool,
+    hot_reload:   bool,
+    sources: HashMap<String, HashMap<String, TileAtlasSource, FixedHasher>, FixedHasher>,
+}
+
+impl Default for TileAtlasBuildQueue {
+    fn default() -> Self {
+        Self {
+            builder: None,
+            queue: HashMap::default(),
+            queue_manifest: HashMap::default(),
+            queue_sheet: Vec::new(),
+            mips: TileAtlasManifestMips::default(),
+            limit_levels: None,
+            page_tiles:   None,
+            compression:  None,
+            format: TileAtlasPixelFormat::Rgba8Srgb,
+            border: 0,
+            padded: false,
+            dedup:  false,
+            pins: TileAtlasIndexManifest::default(),
+            count_loaded: 0,
+            count_total:  0,
+            queue_locked: false,
+            hot_reload:   cfg!(debug_assertions),
+            sources: HashMap::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +139,21 @@ pub struct TileAtlasBuildQueueImageItem {
     pub settings: TileSetSettings,
 }
 
+#[derive(Debug, Clone)]
+pub struct TileAtlasBuildQueueManifestItem {
+    pub tile:    TileAtlasManifestTile,
+    pub mips:    TileAtlasManifestMips,
+    /// Handles of the source images, keyed by their path in the manifest.
+    pub handles: HashMap<String, Handle<Image>, FixedHasher>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TileAtlasBuildQueueSheetItem {
+    pub group_id: String,
+    pub handle:   Handle<Image>,
+    pub sheet:    TileAtlasSheet,
+}
+
 impl TileAtlasBuildQueue {
 
     #[must_use]
@@ -51,6 +169,45 @@ impl TileAtlasBuildQueue {
         }
     }
 
+    /**
+     * Creates a queue from a manifest, `load` is called once for every source image.
+     * - The queue is locked and ready to be processed.
+     * - The tiles in `indices` are pinned, see `TileAtlasManifest::build_with_indices`.
+     */
+    #[must_use]
+    pub fn from_manifest(
+        manifest: &TileAtlasManifest,
+        indices:  &TileAtlasIndexManifest,
+        mut load: impl FnMut(&str) -> Handle<Image>,
+    ) -> Self {
+        let mut result = Self {
+            builder: None,
+            mips:    manifest.mips,
+            limit_levels: manifest.limit_levels,
+            page_tiles:   manifest.page_tiles,
+            compression:  Some(manifest.compression),
+            format:  manifest.format,
+            border:  manifest.border,
+            padded:  manifest.padded,
+            dedup:   manifest.dedup,
+            pins:    indices.clone(),
+            ..Self::default()
+        };
+        result.builder = manifest.tile_size.map(|size| result.new_builder(size));
+
+        let handles = manifest.sources().map(|path| (path, load(path))).collect::<HashMap<_, _, FixedHasher>>();
+        for (group_id, tile_id, tile) in manifest.tiles() {
+            let handles = tile.sources().map(|path| (path.to_owned(), handles[path].clone())).collect();
+            if let Some(index) = tile.index {
+                result.pin(group_id, tile_id, index);
+            }
+            result.insert_manifest_tile(group_id, tile_id, tile.clone(), tile.mips.unwrap_or(manifest.mips), handles);
+        }
+
+        result.lock_queue();
+        result
+    }
+
 }
 
 impl TileAtlasBuildQueue {
@@ -77,6 +234,94 @@ impl TileAtlasBuildQueue {
         }
     }
 
+    /**
+     * Queues a manifest tile, which is loaded once all of its source images are available.
+     */
+    pub fn insert_manifest_tile(
+        &mut self,
+        group_id: &str,
+        tile_id:  &str,
+        tile:     TileAtlasManifestTile,
+        mips:     TileAtlasManifestMips,
+        handles:  HashMap<String, Handle<Image>, FixedHasher>,
+    ) {
+        assert!(!self.queue_locked);
+
+        let increment_count = self.queue_manifest.entry(group_id.to_owned()).or_default().insert(
+            tile_id.to_owned(),
+            TileAtlasBuildQueueManifestItem{
+                tile,
+                mips,
+                handles,
+            }
+        ).is_none();
+
+        if increment_count {
+            self.count_total += 1;
+        }
+    }
+
+    /**
+     * Queues every tile of a sprite `sheet` into the group with the given `group_id`, which are loaded once the image is available.
+     */
+    pub fn insert_sheet(
+        &mut self,
+        group_id: &str,
+        handle:   Handle<Image>,
+        sheet:    TileAtlasSheet,
+    ) {
+        assert!(!self.queue_locked);
+
+        self.count_total += sheet.tiles().len();
+        self.queue_sheet.push(TileAtlasBuildQueueSheetItem{
+            group_id: group_id.to_owned(),
+            handle,
+            sheet,
+        });
+    }
+
+    /**
+     * Pins a tile to a slot index once the queue completes, see `TileAtlasBuilder::pin`.
+     */
+    pub fn pin(&mut self, group_id: &str, tile_id: &str, index: u16) {
+        self.pins.insert(group_id, tile_id, index);
+    }
+
+    /**
+     * Sets the mip policy for images queued with `Self::insert_image`.
+     */
+    pub const fn set_mips(&mut self, mips: TileAtlasManifestMips) {
+        self.mips = mips;
+    }
+
+    /**
+     * Discards all mip levels from `limit_levels` onwards once the queue completes.
+     */
+    pub const fn set_limit_levels(&mut self, limit_levels: Option<u32>) {
+        self.limit_levels = limit_levels;
+    }
+
+    /**
+     * Sets the number of tiles along each side of a page once the queue completes, see `TileAtlasBuilder::set_page_tiles`.
+     */
+    pub const fn set_page_tiles(&mut self, page_tiles: Option<u32>) {
+        self.page_tiles = page_tiles;
+    }
+
+    /**
+     * Keeps the builder and source handles once the queue completes, so the atlas is updated
+     * when a source image is modified, see `TileAtlasHotReload`.
+     * - Enabled by default in debug builds.
+     */
+    pub const fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    #[must_use]
+    pub const fn hot_reload(&self) -> bool {
+        self.hot_reload
+    }
+
     pub const fn lock_queue(&mut self) {
         self.queue_locked = true;
     }
@@ -100,29 +345,133 @@ impl TileAtlasBuildQueue {
         self.count_loaded
     }
 
-    pub fn load(&mut self, group_id: &str, tile_id: &str, src: &[u8], src_size: [u32; 2], src_settings: TileSetSettings) {
+    pub fn load(&mut self, group_id: &str, tile_id: &str, src: &[u8], src_size: [u32; 2], src_settings: TileSetSettings) -> Result<(), TileSetError> {
+        self.load_with_settings(group_id, tile_id, src, src_size, src_settings, &TileAtlasBuildSettings::default())
+    }
+
+    /**
+     * Loads a queued image, creating its mip levels as configured by the `build_settings`.
+     * - Fails when the first image's tile size can't be inferred, see `TileSetSettings::tile_size`, or the tiles don't fit in the image.
+     */
+    #[allow(clippy::panic_in_result_fn)] // Loading before the queue is locked is a bug, not an error
+    pub fn load_with_settings(
+        &mut self,
+        group_id: &str,
+        tile_id:  &str,
+        src:      &[u8],
+        src_size: [u32; 2],
+        src_settings:   TileSetSettings,
+        build_settings: &TileAtlasBuildSettings,
+    ) -> Result<(), TileSetError> {
         assert!(self.queue_locked);
 
         let was_queued = self.queue.get_mut(group_id).and_then(|g| g.remove(tile_id)).is_some();
         if was_queued { self.count_loaded += 1; }
 
-        let builder = self.builder.get_or_insert_with(|| {
-            let tile_size = [0,1].map(|i| (src_size[i] - src_settings.offset[i])/src_settings.count[i] - src_settings.spacing[i]);
-            let tile_size = tile_size[0].min(tile_size[1]);
-            TileAtlasBuilder::new(tile_size)
-        });
+        if self.builder.is_none() {
+            self.builder = Some(self.new_builder(src_settings.tile_size(src_size)?));
+        }
+        let builder = self.builder.as_mut().unwrap();
 
+        src_settings.check_bounds(builder.tile_size(), src_size)?;
         builder.insert_tileset(group_id, tile_id, 0, src, src_size[0], src_settings);
+        build_settings.apply_mips(self.mips, builder, group_id, tile_id);
+        Ok(())
+    }
+
+    /**
+     * Loads a queued manifest tile, `images` provides the data and size of the source image at the given path, in the pixel format of `Self::format`.
+     */
+    pub fn load_manifest_tile<'a>(
+        &mut self,
+        group_id: &str,
+        tile_id:  &str,
+        images:   impl FnMut(&str) -> Option<(&'a [u8], [u32; 2])>,
+    ) -> Result<(), TileAtlasManifestError> {
+        self.load_manifest_tile_with_settings(group_id, tile_id, images, &TileAtlasBuildSettings::default())
+    }
+
+    /**
+     * Loads a queued manifest tile, creating its mip levels as configured by the `build_settings`.
+     */
+    pub fn load_manifest_tile_with_settings<'a>(
+        &mut self,
+        group_id: &str,
+        tile_id:  &str,
+        mut images: impl FnMut(&str) -> Option<(&'a [u8], [u32; 2])>,
+        build_settings: &TileAtlasBuildSettings,
+    ) -> Result<(), TileAtlasManifestError> {
+        let Some(item) = self.queue_manifest.get_mut(group_id).and_then(|g| g.remove(tile_id)) else { return Ok(()); };
+        self.count_loaded += 1;
+
+        if self.hot_reload {
+            self.insert_source(group_id, tile_id, TileAtlasSource::Manifest(item.clone()));
+        }
+
+        if self.builder.is_none() {
+            let (path, tileset) = item.tile.frames(0).into_iter().next().ok_or_else(|| TileAtlasManifestError::EmptyTile{ group_id: group_id.to_owned(), tile_id: tile_id.to_owned() })?;
+            let (_, src_size) = images(path).ok_or_else(|| TileAtlasManifestError::MissingImage(path.to_owned()))?;
+            self.builder = Some(self.new_builder(tileset.tile_size(src_size)?));
+        }
+
+        let builder = self.builder.as_mut().unwrap();
+
+        item.tile.insert_into(builder, group_id, tile_id, &mut images)?;
+        build_settings.apply_mips(item.mips, builder, group_id, tile_id);
+        Ok(())
+    }
+
+    /**
+     * Loads every tile of a sprite `sheet`, `src` is the sheet's image data in the pixel format of `Self::format`.
+     */
+    pub fn load_sheet(&mut self, group_id: &str, src: &[u8], src_size: [u32; 2], sheet: &TileAtlasSheet) -> Result<(), TileAtlasSheetError> {
+        self.load_sheet_with_settings(group_id, src, src_size, sheet, &TileAtlasBuildSettings::default())
+    }
+
+    /**
+     * Loads every tile of a sprite `sheet`, creating their mip levels as configured by the `build_settings`.
+     * - The tile size is inferred from the sheet when the queue has no tiles yet, see `TileAtlasSheet::tile_size`.
+     */
+    pub fn load_sheet_with_settings(
+        &mut self,
+        group_id: &str,
+        src:      &[u8],
+        src_size: [u32; 2],
+        sheet:    &TileAtlasSheet,
+        build_settings: &TileAtlasBuildSettings,
+    ) -> Result<(), TileAtlasSheetError> {
+        let tiles = sheet.tiles();
+        self.count_loaded += tiles.len();
+
+        if self.builder.is_none() {
+            self.builder = Some(self.new_builder(sheet.tile_size()));
+        }
+        let builder = self.builder.as_mut().unwrap();
+        builder.insert_sheet(group_id, 0, src, src_size[0], sheet)?;
+        for (tile_id, _) in tiles {
+            build_settings.apply_mips(self.mips, builder, group_id, tile_id);
+        }
+        Ok(())
+    }
+
+    /**
+     * Records the source of a loaded tile, used for hot reloading.
+     */
+    pub fn insert_source(&mut self, group_id: &str, tile_id: &str, source: TileAtlasSource) {
+        self.sources.entry(group_id.to_owned()).or_default().insert(tile_id.to_owned(), source);
     }
 
     pub fn skip(&mut self, group_id: &str, tile_id: &str) {
         let was_queued = self.queue.get_mut(group_id).and_then(|g| g.remove(tile_id)).is_some();
+        let was_queued = was_queued | self.queue_manifest.get_mut(group_id).and_then(|g| g.remove(tile_id)).is_some();
         if was_queued { self.count_loaded += 1; }
     }
 
     pub fn skip_remaining(&mut self) {
         self.count_loaded = self.count_total;
         self.queue.clear();
+        self.queue_manifest.clear();
+        self.queue_sheet.clear();
     }
 
 }
@@ -136,69 +485,333 @@ impl TileAtlasBuildQueue {
 
     #[must_use] 
     pub fn get_size(&self) -> Option<u32> {
-        self.builder.as_ref().map(|b| b.mip_level_size(0))
+        self.builder.as_ref().map(TileAtlasBuilder::tile_size)
     }
 
     #[must_use]
     pub fn reset(&mut self, size: Option<u32>) -> TileAtlasBuilder {
-        let builder = core::mem::replace(&mut self.builder, size.map(TileAtlasBuilder::new)).unwrap();
+        let next        = size.map(|size| self.new_builder(size));
+        let mut builder = core::mem::replace(&mut self.builder, next).unwrap();
+        builder.pin_all(&core::mem::take(&mut self.pins));
+        if let Some(limit_levels) = self.limit_levels.take() {
+            builder.limit_levels(limit_levels);
+        }
+        if let Some(page_tiles) = self.page_tiles.take() {
+            builder.set_page_tiles(page_tiles);
+        }
+
         self.queue_locked = false;
         self.count_loaded = 0;
         self.count_total  = 0;
         self.queue.clear();
+        self.queue_manifest.clear();
+        self.queue_sheet.clear();
+        self.sources.clear();
         
         builder
     }
 
+    #[must_use]
+    pub const fn limit_levels(&self) -> Option<u32> {
+        self.limit_levels
+    }
+
+    /**
+     * Sets the compression of the atlas image, when the device supports it, see `TileAtlasBuildSettings::build_image`.
+     */
+    pub const fn set_compression(&mut self, compression: Option<TileAtlasCompression>) {
+        self.compression = compression;
+    }
+
+    #[must_use]
+    pub const fn compression(&self) -> Option<TileAtlasCompression> {
+        self.compression
+    }
+
+    /**
+     * Sets the format tiles are stored in, queued images are converted to it when loaded.
+     * - Must be set before any tiles are loaded, see `TileAtlasBuilder::set_format`.
+     */
+    pub fn set_format(&mut self, format: TileAtlasPixelFormat) {
+        self.format = format;
+        if let Some(builder) = &mut self.builder {
+            builder.set_format(format);
+        }
+    }
+
+    #[must_use]
+    pub const fn format(&self) -> TileAtlasPixelFormat {
+        self.format
+    }
+
+    /**
+     * Sets the extruded border around every tile.
+     * - Must be set before any tiles are loaded, see `TileAtlasBuilder::set_border`.
+     */
+    pub fn set_border(&mut self, border: u32) {
+        self.border = border;
+        if let Some(builder) = &mut self.builder {
+            builder.set_border(border);
+        }
+    }
+
+    #[must_use]
+    pub const fn border(&self) -> u32 {
+        self.border
+    }
+
+    /**
+     * Stores identical frames once, see `TileAtlasBuilder::with_dedup`.
+     */
+    pub const fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = dedup;
+        if let Some(builder) = &mut self.builder {
+            builder.set_dedup(dedup);
+        }
+    }
+
+    #[must_use]
+    pub const fn dedup(&self) -> bool {
+        self.dedup
+    }
+
+    /**
+     * Pads tiles up to the next power of two, see `TileAtlasBuilder::with_padding`.
+     * - Must be set before any tiles are loaded, see `TileAtlasBuilder::set_padding`.
+     */
+    pub fn set_padding(&mut self, padded: bool) {
+        self.padded = padded;
+        if let Some(builder) = &mut self.builder {
+            builder.set_padding(padded);
+        }
+    }
+
+    #[must_use]
+    pub const fn is_padded(&self) -> bool {
+        self.padded
+    }
+
+    /**
+     * Creates an empty builder with the queue's tile settings.
+     */
+    fn new_builder(&self, size: u32) -> TileAtlasBuilder {
+        TileAtlasBuilder::new(size)
+            .with_format(self.format)
+            .with_padding(self.padded)
+            .with_border(self.border)
+            .with_dedup(self.dedup)
+    }
+
+    /**
+     * Takes the recorded sources, which `Self::reset` discards.
+     */
+    #[must_use]
+    pub fn take_sources(&mut self) -> HashMap<String, HashMap<String, TileAtlasSource, FixedHasher>, FixedHasher> {
+        core::mem::take(&mut self.sources)
+    }
+
 }
 
 pub fn proccess_tile_atlas_build_queue(
-    mut q_build_queues: Query<&mut TileAtlasBuildQueue>,
+    mut q_build_queues: Query<(&mut TileAtlasBuildQueue, &mut TileAtlasBuildProgress, Option<&TileAtlasBuildSettings>)>,
     r_images: Res<Assets<Image>>,
+    r_budget: Res<TileAtlasBuildBudget>,
 ) {
-    for mut build_queue in &mut q_build_queues {
+    let start = Instant::now();
+    let mut loaded_any = false;
+    let default_settings = TileAtlasBuildSettings::default();
+
+    for (mut build_queue, mut progress, settings) in &mut q_build_queues {
+        let settings = settings.unwrap_or(&default_settings);
+        if build_queue.queue_locked() {
+            let queue = build_queue.queue.iter()
+                .flat_map(|(group_id, group)| group.iter().map(move |(tile_id, item)| (group_id, tile_id, item)))
+                .filter(|(_, _, item)| r_images.contains(&item.handle))
+                .map(|(group_id, tile_id, item)| (group_id.clone(), tile_id.clone(), item.clone()))
+                .collect::<Vec<_>>();
+
+            for (group_id, tile_id, entry) in queue {
+                if loaded_any && start.elapsed() >= r_budget.per_frame { break; }
+                loaded_any = true;
+
+                let image = r_images.get(&entry.handle).unwrap();
+                let Some(data) = build_queue.format().image_data(image) else {
+                    error!("Failed to load tile atlas tile \"{group_id}/{tile_id}\": its image can't be converted to {:?}", build_queue.format());
+                    build_queue.skip(&group_id, &tile_id);
+                    continue;
+                };
+                let result = build_queue.load_with_settings(
+                    &group_id, 
+                    &tile_id, 
+                    &data, 
+                    [image.width(), image.height()], 
+                    entry.settings,
+                    settings
+                );
 
-        if !build_queue.queue_locked() { return; }
+                if let Err(err) = result {
+                    error!("Failed to load tile atlas tile \"{group_id}/{tile_id}\": {err}");
+                    continue;
+                }
 
-        // TODO OPT this is horrid
-        let queue = build_queue.queue.clone();
-        let queue = queue.iter().flat_map(|(group_id, group)| group.iter().map(move |(tile_id, item)| (group_id, tile_id, item)));
+                if build_queue.hot_reload {
+                    let source = TileAtlasSource::Image{ handle: entry.handle, settings: entry.settings, mips: build_queue.mips };
+                    build_queue.insert_source(&group_id, &tile_id, source);
+                }
+            }
 
-        for (group_id, tile_id, entry) in queue {
-            if let Some(image) = r_images.get(&entry.handle) {
-                build_queue.load(
-                    group_id, 
-                    tile_id, 
-                    image.data.as_ref().unwrap(), 
-                    [image.width(), image.height()], 
-                    entry.settings
-                );
+            let queue = build_queue.queue_manifest.iter()
+                .flat_map(|(group_id, group)| group.iter().map(move |(tile_id, item)| (group_id, tile_id, item)))
+                .filter(|(_, _, item)| item.handles.values().all(|h| r_images.contains(h)))
+                .map(|(group_id, tile_id, item)| (group_id.clone(), tile_id.clone(), item.handles.clone()))
+                .collect::<Vec<_>>();
+
+            for (group_id, tile_id, handles) in queue {
+                if loaded_any && start.elapsed() >= r_budget.per_frame { break; }
+                loaded_any = true;
+
+                let format = build_queue.format();
+                let images = handles.iter()
+                    .filter_map(|(path, handle)| r_images.get(handle).map(|image| (path.as_str(), image)))
+                    .map(|(path, image)| Some((path, (format.image_data(image)?, [image.width(), image.height()]))))
+                    .collect::<Option<HashMap<_, _, FixedHasher>>>();
+                let Some(images) = images else {
+                    error!("Failed to load tile atlas tile \"{group_id}/{tile_id}\": its images can't be converted to {format:?}");
+                    build_queue.skip(&group_id, &tile_id);
+                    continue;
+                };
+
+                let result = build_queue.load_manifest_tile_with_settings(&group_id, &tile_id, |path| {
+                    images.get(path).map(|(data, size)| (&**data, *size))
+                }, settings);
+
+                if let Err(err) = result {
+                    error!("Failed to load tile atlas tile: {err}");
+                }
+            }
+
+            while let Some(index) = build_queue.queue_sheet.iter().position(|item| r_images.contains(&item.handle)) {
+                if loaded_any && start.elapsed() >= r_budget.per_frame { break; }
+                loaded_any = true;
+
+                let item  = build_queue.queue_sheet.remove(index);
+                let image = r_images.get(&item.handle).unwrap();
+                let Some(data) = build_queue.format().image_data(image) else {
+                    error!("Failed to load tile atlas sheet in group \"{}\": its image can't be converted to {:?}", item.group_id, build_queue.format());
+                    build_queue.count_loaded += item.sheet.tiles().len();
+                    continue;
+                };
+
+                if let Err(err) = build_queue.load_sheet_with_settings(&item.group_id, &data, [image.width(), image.height()], &item.sheet, settings) {
+                    error!("Failed to load tile atlas sheet in group \"{}\": {err}", item.group_id);
+                    continue;
+                }
+
+                if build_queue.hot_reload {
+                    for (tile_id, frames) in item.sheet.tiles() {
+                        let source = TileAtlasSource::Sheet{ handle: item.handle.clone(), frames: frames.into_iter().cloned().collect(), mips: build_queue.mips };
+                        build_queue.insert_source(&item.group_id, tile_id, source);
+                    }
+                }
             }
         }
+
+        if progress.stage != TileAtlasBuildStage::Building {
+            *progress = TileAtlasBuildProgress {
+                stage:  if build_queue.queue_locked() { TileAtlasBuildStage::Loading } else { TileAtlasBuildStage::Queued },
+                loaded: build_queue.count_loaded(),
+                total:  build_queue.count_total(),
+            };
+        }
     }
 
 }
 
+/**
+ * Starts building the atlas of every completed queue on the `AsyncComputeTaskPool`.
+ * - Atlases with more tiles than a single atlas can address are split, see `TileAtlasBuildSettings::split_limits`.
+ * - Atlases that don't fit within their limits fail, see `TileAtlasBuildStage::Failed`.
+ */
 pub fn process_tile_atlas_build_queues_with_target(
     mut commands: Commands,
-    mut q_build_queues: Query<(Entity, &TileAtlasBuildQueueTarget, &mut TileAtlasBuildQueue)>,
-    mut r_images: ResMut<Assets<Image>>,
-    mut r_atlas:  ResMut<Assets<TileAtlas>>,
+    mut q_build_queues: Query<(Entity, &mut TileAtlasBuildQueue, &mut TileAtlasBuildProgress, Option<&TileAtlasBuildSettings>), (With<TileAtlasBuildQueueTarget>, Without<TileAtlasBuildTask>)>,
+    r_device:  Option<Res<RenderDevice>>,
+    r_support: Option<Res<CompressedImageFormatSupport>>,
 ) {
-    for (entity, result, mut build_queue) in &mut q_build_queues {
-        if !build_queue.is_complete() { return; }
-
-        let (image, lookup) = {
-            let mut builder = build_queue.reset(None);
-            builder.downsample_levels(0..u32::MAX, false, DownsampleBilinearSRGB); // TODO HACK configurable
-            (
-                builder.build_image(),
-                builder.build_lookup()
-            )
+    let supported = r_support.map_or(CompressedImageFormats::NONE, |support| support.0);
+    for (entity, mut build_queue, mut progress, settings) in &mut q_build_queues {
+        if !build_queue.is_complete() { continue; }
+
+        let mut settings = settings.cloned().unwrap_or_default();
+        if settings.limit_levels.is_some() {
+            build_queue.set_limit_levels(settings.limit_levels);
+        }
+        settings.limit_levels = build_queue.limit_levels();
+        if settings.page_tiles.is_some() {
+            build_queue.set_page_tiles(settings.page_tiles);
+        }
+        settings.compression = settings.compression.or(build_queue.compression());
+
+        let mut hot_reload = build_queue.hot_reload.then(|| (build_queue.take_sources(), settings.clone()));
+        let builder        = build_queue.reset(None);
+        let slot_size      = builder.slot_size();
+        let parts = match settings.split_limits(builder, r_device.as_deref().map(TileAtlasLimits::from_render_device)) {
+            Ok(parts) => parts,
+            Err(err)  => {
+                error!("Failed to build tile atlas: {err}");
+                progress.stage = TileAtlasBuildStage::Failed;
+                commands.entity(entity).remove::<(TileAtlasBuildQueue, TileAtlasBuildQueueTarget)>();
+                continue;
+            },
         };
 
-        let image = r_images.add(image);
-        r_atlas.insert(&result.target, TileAtlas::new(image, lookup)).unwrap();
+        // Reloaded sources would be sliced at the reduced size, or into the wrong atlas
+        if parts[0].slot_size() != slot_size && hot_reload.take().is_some() {
+            warn!("Tile atlas tiles were shrunk to fit its limits, hot reloading is disabled");
+        }
+        if parts.len() > 1 && hot_reload.take().is_some() {
+            warn!("Tile atlas was split into {} atlases, hot reloading is disabled", parts.len());
+        }
+
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            parts.into_iter().map(|builder| {
+                let image = settings.build_image(&builder, supported);
+                (builder, image)
+            }).collect()
+        });
+
+        progress.stage = TileAtlasBuildStage::Building;
+        commands.entity(entity).insert(TileAtlasBuildTask{ task, hot_reload });
+    }
+}
+
+/**
+ * Inserts the atlas of every finished build task into its target and despawns the queue.
+ * - Overflow atlases are added as new assets, see `TileAtlas::overflow`.
+ */
+pub fn poll_tile_atlas_build_tasks(
+    mut commands: Commands,
+    mut q_build_tasks: Query<(Entity, &TileAtlasBuildQueueTarget, &mut TileAtlasBuildTask)>,
+    mut r_images: ResMut<Assets<Image>>,
+    mut r_atlas:  ResMut<Assets<TileAtlas>>,
+    mut r_reload: ResMut<TileAtlasHotReload>,
+) {
+    for (entity, result, mut build_task) in &mut q_build_tasks {
+        let Some(parts) = check_ready(&mut build_task.task) else { continue; };
+
+        let mut atlases = parts.into_iter().map(|(builder, image)| {
+            let texture_format = image.texture_descriptor.format;
+            let atlas = TileAtlas::from_builder(r_images.add(image), &builder)
+                .with_texture_format(texture_format);
+            (builder, atlas)
+        }).collect::<Vec<_>>();
+
+        let (builder, atlas) = atlases.remove(0);
+        let overflow = atlases.into_iter().map(|(_, atlas)| r_atlas.add(atlas)).collect();
+        r_atlas.insert(&result.target, atlas.with_overflow(overflow)).unwrap();
+        if let Some((sources, settings)) = build_task.hot_reload.take() {
+            r_reload.insert(result.target.id(), builder, sources, settings);
+        }
         commands.entity(entity).despawn();
     }
-}
\ No newline at end of file
+}
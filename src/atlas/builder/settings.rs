@@ -0,0 +1,221 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::sync::Arc;
+
+use bevy::{asset::RenderAssetUsages, image::{CompressedImageFormats, ImageFilterMode, ImageSampler, ImageSamplerDescriptor}, prelude::*, render::render_resource::TextureUsages};
+
+use super::{DownsampleAlgorithm, DownsampleBilinearSRGB, TileAtlasBuilder, TileAtlasCompression, TileAtlasLimits, TileAtlasLimitsError, TileAtlasManifestMips, TILE_ATLAS_SLOT_COUNT};
+
+/**
+ * Controls how a `TileAtlasBuildQueue` on the same entity builds its atlas, queues without
+ * this component use the default settings, which match `TileAtlasBuilder::build_image`.
+ */
+#[derive(Clone, Component)]
+pub struct TileAtlasBuildSettings {
+    /// Creates missing mip levels.
+    pub downsampler: Arc<dyn DownsampleAlgorithm + Send + Sync>,
+    /// Overrides the mip policy of every tile, including tiles from a manifest.
+    pub mips: Option<TileAtlasManifestMips>,
+    /// Overrides the queue's level limit, see `TileAtlasBuildQueue::set_limit_levels`.
+    pub limit_levels: Option<u32>,
+    /// Overrides the queue's tiles per page side, see `TileAtlasBuildQueue::set_page_tiles`.
+    pub page_tiles: Option<u32>,
+    /// The limits the atlas is checked against, queues use the limits of the `RenderDevice` when missing.
+    pub limits: Option<TileAtlasLimits>,
+    /// Changes the atlas to fit the limits instead of failing, see `TileAtlasBuilder::fit_limits`.
+    pub fit_limits: bool,
+    /// Overrides the queue's compression, see `TileAtlasBuildQueue::set_compression`.
+    pub compression: Option<TileAtlasCompression>,
+    pub sampler: ImageSampler,
+    pub texture_label:  Option<&'static str>,
+    pub texture_usages: TextureUsages,
+    pub asset_usages:   RenderAssetUsages,
+}
+
+impl Default for TileAtlasBuildSettings {
+    fn default() -> Self {
+        Self {
+            downsampler:    Arc::new(DownsampleBilinearSRGB),
+            mips:           None,
+            limit_levels:   None,
+            page_tiles:     None,
+            limits:         None,
+            fit_limits:     false,
+            compression:    None,
+            sampler:        ImageSampler::Descriptor(TileAtlasBuilder::default_sampler_descriptor()),
+            texture_label:  Some("tile_atlas_texture"),
+            texture_usages: TextureUsages::TEXTURE_BINDING,
+            asset_usages:   RenderAssetUsages::RENDER_WORLD,
+        }
+    }
+}
+
+impl core::fmt::Debug for TileAtlasBuildSettings {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TileAtlasBuildSettings")
+            .field("mips", &self.mips)
+            .field("limit_levels", &self.limit_levels)
+            .field("page_tiles", &self.page_tiles)
+            .field("limits", &self.limits)
+            .field("fit_limits", &self.fit_limits)
+            .field("compression", &self.compression)
+            .field("sampler", &self.sampler)
+            .field("texture_label", &self.texture_label)
+            .field("texture_usages", &self.texture_usages)
+            .field("asset_usages", &self.asset_usages)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TileAtlasBuildSettings {
+
+    /**
+     * Settings for pixel art, no mip levels are generated and the texture is sampled with nearest filtering.
+     */
+    #[must_use]
+    pub fn pixel_art() -> Self {
+        Self {
+            mips:    Some(TileAtlasManifestMips{ generate: false, ..TileAtlasManifestMips::default() }),
+            sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
+                min_filter:    ImageFilterMode::Nearest,
+                mipmap_filter: ImageFilterMode::Nearest,
+                ..TileAtlasBuilder::default_sampler_descriptor()
+            }),
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn with_downsampler(self, downsampler: impl DownsampleAlgorithm + Send + Sync + 'static) -> Self {
+        Self {
+            downsampler: Arc::new(downsampler),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_mips(self, mips: TileAtlasManifestMips) -> Self {
+        Self {
+            mips: Some(mips),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_limit_levels(self, limit_levels: u32) -> Self {
+        Self {
+            limit_levels: Some(limit_levels),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_page_tiles(self, page_tiles: u32) -> Self {
+        Self {
+            page_tiles: Some(page_tiles),
+            ..self
+        }
+    }
+
+    /**
+     * Fails the build when the atlas doesn't fit within the `limits`.
+     */
+    #[must_use]
+    pub fn with_limits(self, limits: TileAtlasLimits) -> Self {
+        Self {
+            limits: Some(limits),
+            fit_limits: false,
+            ..self
+        }
+    }
+
+    /**
+     * Changes the atlas to fit within the `limits`, only failing when it can't.
+     */
+    #[must_use]
+    pub fn with_fit_limits(self, limits: TileAtlasLimits) -> Self {
+        Self {
+            limits: Some(limits),
+            fit_limits: true,
+            ..self
+        }
+    }
+
+    /**
+     * Compresses the atlas image when the device supports the `compression`, see `TileAtlasBuilder::build_image_with_compression`.
+     */
+    #[must_use]
+    pub fn with_compression(self, compression: TileAtlasCompression) -> Self {
+        Self {
+            compression: Some(compression),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_sampler(self, sampler: ImageSampler) -> Self {
+        Self {
+            sampler,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_usages(self, texture_usages: TextureUsages, asset_usages: RenderAssetUsages) -> Self {
+        Self {
+            texture_usages,
+            asset_usages,
+            ..self
+        }
+    }
+
+}
+
+impl TileAtlasBuildSettings {
+
+    /**
+     * Applies the mip policy to a tile with the downsampler, `mips` is used unless overridden by `Self::mips`.
+     */
+    pub fn apply_mips(&self, mips: TileAtlasManifestMips, builder: &mut TileAtlasBuilder, group_id: &str, tile_id: &str) {
+        self.mips.unwrap_or(mips).apply_with(builder, group_id, tile_id, &*self.downsampler);
+    }
+
+    /**
+     * Checks or fits the atlas to `Self::limits`, or the `fallback` limits when missing.
+     * - Without any limits only the number of slots is checked, see `TileAtlasBuilder::check_slot_count`.
+     */
+    pub fn apply_limits(&self, builder: &mut TileAtlasBuilder, fallback: Option<TileAtlasLimits>) -> Result<(), TileAtlasLimitsError> {
+        let Some(limits) = self.limits.or(fallback) else { return builder.check_slot_count(); };
+        if self.fit_limits {
+            builder.fit_limits(&limits)
+        } else {
+            builder.check_limits(&limits)
+        }
+    }
+
+    /**
+     * Splits the atlas into as many atlases as it needs to fit `Self::limits`, or the `fallback` limits when missing,
+     * see `TileAtlasBuilder::split_limits`.
+     */
+    pub fn split_limits(&self, builder: TileAtlasBuilder, fallback: Option<TileAtlasLimits>) -> Result<Vec<TileAtlasBuilder>, TileAtlasLimitsError> {
+        match self.limits.or(fallback) {
+            Some(limits) => builder.split_limits(&limits, self.fit_limits),
+            None         => builder.split(TILE_ATLAS_SLOT_COUNT),
+        }
+    }
+
+    /**
+     * Builds the atlas image, compressed with `Self::compression` when it's one of the `supported` formats.
+     */
+    #[must_use]
+    pub fn build_image(&self, builder: &TileAtlasBuilder, supported: CompressedImageFormats) -> Image {
+        builder.build_image_with_compression(
+            self.compression.filter(|compression| compression.is_supported(supported)).unwrap_or_default(),
+            self.texture_label,
+            self.texture_usages,
+            self.asset_usages,
+            self.sampler.clone(),
+        )
+    }
+
+}
@@ -0,0 +1,352 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use bevy::{image::TextureAtlasLayout, math::URect};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{image_copy_row, TileAtlasBuilder};
+
+#[derive(Debug, Error)]
+pub enum TileAtlasSheetError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("sprite sheet frame \"{0}\" is rotated, which isn't supported")]
+    Rotated(String),
+
+    #[error("sprite sheet rect \"{name}\" of {size:?} at {offset:?} doesn't fit in a {tile_size}px tile")]
+    TooLarge {
+        name:      String,
+        size:      [u32; 2],
+        offset:    [u32; 2],
+        tile_size: u32,
+    },
+
+    #[error("sprite sheet rect \"{name}\" of {size:?} at {position:?} is outside the {image_size:?} image")]
+    OutOfBounds {
+        name:       String,
+        position:   [u32; 2],
+        size:       [u32; 2],
+        image_size: [u32; 2],
+    },
+}
+
+/**
+ * A named rect in a sprite sheet, which is cut out as a frame of the tile with its name.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileAtlasSheetRect {
+    pub name: String,
+    /// Position of the rect in the sheet.
+    pub position: [u32; 2],
+    /// Size of the rect in the sheet, up to the tile size.
+    pub size: [u32; 2],
+    /// Position of the rect within its tile, for rects trimmed of transparent edges, the rest of the tile is cleared.
+    pub offset: [u32; 2],
+}
+
+/**
+ * Slices a sprite sheet into tiles at arbitrary rects, rather than the uniform grid of `TileSetSettings`.
+ * - Rects with the same name form the frames of one tile's sequence, in order, see `Self::map_names`.
+ * - Created from a `TextureAtlasLayout` or a packer's rect list, see `Self::from_texture_packer_json`.
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TileAtlasSheet {
+    pub rects: Vec<TileAtlasSheetRect>,
+}
+
+impl TileAtlasSheet {
+
+    /**
+     * Creates a sheet from (`name`, `rect`) pairs.
+     */
+    #[must_use]
+    pub fn from_rects(rects: impl IntoIterator<Item = (String, URect)>) -> Self {
+        let rects = rects.into_iter().map(|(name, rect)| TileAtlasSheetRect {
+            name,
+            position: rect.min.to_array(),
+            size:     rect.size().to_array(),
+            offset:   [0, 0],
+        });
+        Self{ rects: rects.collect() }
+    }
+
+    /**
+     * Creates a sheet from the rects of a layout, naming each from its index in the layout.
+     */
+    #[must_use]
+    pub fn from_layout(layout: &TextureAtlasLayout, mut name: impl FnMut(usize) -> String) -> Self {
+        Self::from_rects(layout.textures.iter().enumerate().map(|(index, &rect)| (name(index), rect)))
+    }
+
+    /**
+     * Creates a sheet from the JSON exported by `TexturePacker` and compatible packers, in either the hash or array format.
+     * - Rects are named by their frame names, which usually include the file extension, see `Self::map_names`.
+     * - Trimmed frames are placed at their offset in the untrimmed sprite, rotated frames aren't supported.
+     */
+    pub fn from_texture_packer_json(src: &[u8]) -> Result<Self, TileAtlasSheetError> {
+        let document = serde_json::from_slice::<TexturePackerDocument>(src)?;
+        let frames = match document.frames {
+            TexturePackerFrames::Array(frames) => frames.into_iter().map(|f| (f.filename, f.frame)).collect::<Vec<_>>(),
+            TexturePackerFrames::Hash(frames)  => frames.into_iter()
+                .map(|(name, frame)| Ok((name, serde_json::from_value(frame)?)))
+                .collect::<Result<Vec<_>, serde_json::Error>>()?,
+        };
+
+        let rects = frames.into_iter().map(|(name, frame)| {
+            if frame.rotated { return Err(TileAtlasSheetError::Rotated(name)); }
+            let offset = frame.sprite_source_size.filter(|_| frame.trimmed).map_or([0, 0], |r| [r.x, r.y]);
+            Ok(TileAtlasSheetRect {
+                name,
+                position: [frame.frame.x, frame.frame.y],
+                size:     [frame.frame.w, frame.frame.h],
+                offset,
+            })
+        });
+        Ok(Self{ rects: rects.collect::<Result<_, _>>()? })
+    }
+
+    /**
+     * Renames every rect, such as to strip file extensions, or to give the frames of an animation the same name.
+     */
+    #[must_use]
+    pub fn map_names(mut self, mut name: impl FnMut(&str) -> String) -> Self {
+        for rect in &mut self.rects {
+            rect.name = name(&rect.name);
+        }
+        self
+    }
+
+    /**
+     * Infers the size of the square tiles that hold every rect at its offset.
+     */
+    #[must_use]
+    pub fn tile_size(&self) -> u32 {
+        self.rects.iter().map(|r| r.offset[0].saturating_add(r.size[0]).max(r.offset[1].saturating_add(r.size[1]))).max().unwrap_or(0)
+    }
+
+    /**
+     * Iterates over the tiles as (`tile_id`, `frames`), in the order their names first appear.
+     */
+    #[must_use]
+    pub fn tiles(&self) -> Vec<(&str, Vec<&TileAtlasSheetRect>)> {
+        let mut result = Vec::<(&str, Vec<&TileAtlasSheetRect>)>::new();
+        for rect in &self.rects {
+            match result.iter_mut().find(|(name, _)| *name == rect.name) {
+                Some((_, frames)) => frames.push(rect),
+                None => result.push((&rect.name, vec![rect])),
+            }
+        }
+        result
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Inserts every tile of the `sheet` into the group with the given `group_id` at the specified mip `level`.
+     * - `src_width` is the width of a row in the `src` image data, rects are in the image's pixels.
+     * - Every rect is checked before any tile is inserted.
+     */
+    pub fn insert_sheet(
+        &mut self,
+        group_id: &str,
+        level:    u32,
+        src:      &[u8],
+        src_width: u32,
+        sheet:    &TileAtlasSheet,
+    ) -> Result<(), TileAtlasSheetError> {
+        for rect in &sheet.rects {
+            self.check_rect(src, src_width, level, rect)?;
+        }
+        for (tile_id, frames) in sheet.tiles() {
+            self.insert_rects(group_id, tile_id, level, src, src_width, &frames)?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Inserts a sequence cut from the `frames` rects of a sheet, see `Self::insert`.
+     * - Rects smaller than the tile or offset within it are placed on a cleared tile.
+     */
+    pub fn insert_rects(
+        &mut self,
+        group_id: &str,
+        tile_id:  &str,
+        level:    u32,
+        src:      &[u8],
+        src_width: u32,
+        frames:   &[&TileAtlasSheetRect],
+    ) -> Result<(), TileAtlasSheetError> {
+        for rect in frames {
+            self.check_rect(src, src_width, level, rect)?;
+        }
+
+        let tile_size = self.tile_level_size(level);
+        let pixel_len = self.format().pixel_len();
+        let placed = frames.iter().map(|rect| {
+            if rect.size == [tile_size, tile_size] && rect.offset == [0, 0] { return None; }
+            let mut dst = vec![0_u8; (tile_size*tile_size*pixel_len) as usize];
+            for y in 0..rect.size[1] {
+                image_copy_row(
+                    pixel_len, rect.size[0],
+                    src, src_width, rect.position[0], rect.position[1] + y,
+                    &mut dst, tile_size, rect.offset[0], rect.offset[1] + y,
+                );
+            }
+            Some(dst)
+        }).collect::<Vec<_>>();
+
+        let images = frames.iter().zip(&placed).map(|(rect, placed)| match placed {
+            Some(dst) => (&dst[..], tile_size, [0, 0]),
+            None      => (src, src_width, rect.position),
+        });
+        self.insert(group_id, tile_id, level, images);
+        Ok(())
+    }
+
+    fn check_rect(&self, src: &[u8], src_width: u32, level: u32, rect: &TileAtlasSheetRect) -> Result<(), TileAtlasSheetError> {
+        // Rects come from files, so their ends may not fit in a u32
+        let fits = |start: [u32; 2], size: [u32; 2], end: [u32; 2]| (0..2).all(|axis| start[axis].checked_add(size[axis]).is_some_and(|e| e <= end[axis]));
+
+        let tile_size = self.tile_level_size(level);
+        if !fits(rect.offset, rect.size, [tile_size, tile_size]) {
+            return Err(TileAtlasSheetError::TooLarge{ name: rect.name.clone(), size: rect.size, offset: rect.offset, tile_size });
+        }
+
+        // An image without width has no rows
+        let row_len    = src_width as usize * self.format().pixel_len() as usize;
+        let src_height = src.len().checked_div(row_len).map_or(0, |height| u32::try_from(height).unwrap_or(u32::MAX));
+        if !fits(rect.position, rect.size, [src_width, src_height]) {
+            return Err(TileAtlasSheetError::OutOfBounds{ name: rect.name.clone(), position: rect.position, size: rect.size, image_size: [src_width, src_height] });
+        }
+        Ok(())
+    }
+
+}
+
+#[derive(Deserialize)]
+struct TexturePackerDocument {
+    frames: TexturePackerFrames,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TexturePackerFrames {
+    Array(Vec<TexturePackerArrayFrame>),
+    /// Kept as values, so frames stay in the order of the document.
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+struct TexturePackerArrayFrame {
+    filename: String,
+    #[serde(flatten)]
+    frame: TexturePackerFrame,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TexturePackerFrame {
+    frame: TexturePackerRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<TexturePackerRect>,
+}
+
+#[derive(Deserialize)]
+struct TexturePackerRect {
+    x: u32,
+    y: u32,
+    #[serde(default)]
+    w: u32,
+    #[serde(default)]
+    h: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(name: &str, position: [u32; 2], size: [u32; 2], offset: [u32; 2]) -> TileAtlasSheetRect {
+        TileAtlasSheetRect{ name: name.to_owned(), position, size, offset }
+    }
+
+    #[test]
+    fn texture_packer_hash() {
+        let src = br#"{
+            "frames": {
+                "walk_1.png": { "frame": { "x": 4, "y": 0, "w": 4, "h": 4 }, "rotated": false, "trimmed": false },
+                "walk_0.png": { "frame": { "x": 0, "y": 0, "w": 4, "h": 4 } }
+            },
+            "meta": { "size": { "w": 8, "h": 4 } }
+        }"#;
+        let sheet = TileAtlasSheet::from_texture_packer_json(src).unwrap();
+        assert_eq!(sheet.rects, [
+            rect("walk_1.png", [4, 0], [4, 4], [0, 0]),
+            rect("walk_0.png", [0, 0], [4, 4], [0, 0]),
+        ]);
+    }
+
+    #[test]
+    fn texture_packer_array() {
+        let src = br#"{
+            "frames": [
+                { "filename": "walk_0.png", "frame": { "x": 0, "y": 0, "w": 4, "h": 4 } },
+                { "filename": "walk_1.png", "frame": { "x": 4, "y": 0, "w": 4, "h": 4 } }
+            ]
+        }"#;
+        let sheet = TileAtlasSheet::from_texture_packer_json(src).unwrap().map_names(|name| name.split('_').next().unwrap().to_owned());
+        assert_eq!(sheet.rects, [
+            rect("walk", [0, 0], [4, 4], [0, 0]),
+            rect("walk", [4, 0], [4, 4], [0, 0]),
+        ]);
+        assert_eq!(sheet.tiles().len(), 1);
+        assert_eq!(sheet.tile_size(), 4);
+    }
+
+    #[test]
+    fn texture_packer_trimmed() {
+        let src = br#"{
+            "frames": {
+                "trimmed.png":   { "frame": { "x": 0, "y": 0, "w": 2, "h": 3 }, "trimmed": true,  "spriteSourceSize": { "x": 1, "y": 1, "w": 2, "h": 3 } },
+                "untrimmed.png": { "frame": { "x": 2, "y": 0, "w": 4, "h": 4 }, "trimmed": false, "spriteSourceSize": { "x": 1, "y": 1, "w": 4, "h": 4 } }
+            }
+        }"#;
+        let sheet = TileAtlasSheet::from_texture_packer_json(src).unwrap();
+        assert_eq!(sheet.rects, [
+            rect("trimmed.png",   [0, 0], [2, 3], [1, 1]),
+            rect("untrimmed.png", [2, 0], [4, 4], [0, 0]),
+        ]);
+        assert_eq!(sheet.tile_size(), 4);
+    }
+
+    #[test]
+    fn texture_packer_rotated() {
+        let src = br#"{ "frames": { "rotated.png": { "frame": { "x": 0, "y": 0, "w": 4, "h": 2 }, "rotated": true } } }"#;
+        let result = TileAtlasSheet::from_texture_packer_json(src);
+        assert!(matches!(result, Err(TileAtlasSheetError::Rotated(name)) if name == "rotated.png"));
+    }
+
+    #[test]
+    fn rect_bounds() {
+        let src = vec![0_u8; 8*4*4];
+        let mut builder = TileAtlasBuilder::new(4);
+        let mut insert = |rect: TileAtlasSheetRect| builder.insert_sheet("walls", 0, &src, 8, &TileAtlasSheet{ rects: vec![rect] });
+
+        assert!(insert(rect("brick", [4, 0], [4, 4], [0, 0])).is_ok());
+        assert!(matches!(insert(rect("brick", [6, 0], [4, 4], [0, 0])), Err(TileAtlasSheetError::OutOfBounds{ .. })));
+        assert!(matches!(insert(rect("brick", [u32::MAX, 0], [4, 4], [0, 0])), Err(TileAtlasSheetError::OutOfBounds{ .. })));
+        assert!(matches!(insert(rect("brick", [0, 0], [2, 2], [3, 0])), Err(TileAtlasSheetError::TooLarge{ .. })));
+        assert!(matches!(insert(rect("brick", [0, 0], [2, 2], [u32::MAX, 0])), Err(TileAtlasSheetError::TooLarge{ .. })));
+
+        // Without a width the image has no rows, so only empty rects fit
+        assert!(matches!(builder.insert_sheet("walls", 0, &src, 0, &TileAtlasSheet{ rects: vec![rect("brick", [0, 0], [1, 1], [0, 0])] }), Err(TileAtlasSheetError::OutOfBounds{ .. })));
+        assert!(builder.insert_sheet("walls", 0, &src, 0, &TileAtlasSheet{ rects: vec![rect("brick", [0, 0], [0, 0], [0, 0])] }).is_ok());
+    }
+
+}
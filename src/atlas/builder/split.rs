@@ -0,0 +1,118 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::path::{Path, PathBuf};
+
+use super::{TileAtlasBuilder, TileAtlasLimits, TileAtlasLimitsError, TILE_ATLAS_SLOT_COUNT};
+
+impl TileAtlasBuilder {
+
+    /**
+     * The number of atlases holding the tiles that didn't fit in this one, see `Self::split`.
+     */
+    #[must_use]
+    pub const fn overflow_count(&self) -> u32 {
+        self.overflow
+    }
+
+    /**
+     * The largest number of slots a single atlas can address within the `limits`, at the current tiles per page.
+     */
+    #[must_use]
+    pub const fn max_slots(&self, limits: &TileAtlasLimits) -> u32 {
+        let max_slots = self.page_slots().saturating_mul(limits.max_texture_array_layers);
+        if max_slots < TILE_ATLAS_SLOT_COUNT { max_slots } else { TILE_ATLAS_SLOT_COUNT }
+    }
+
+    /**
+     * Splits the tiles across as many atlases as needed so each uses at most `max_slots` slots, see `TILE_ATLAS_SLOT_COUNT`.
+     * - Returns the atlas unchanged when its layout already fits, including the gaps left by pins.
+     * - Groups are kept within a single atlas, unless the group itself doesn't fit.
+     * - Tiles keep their pins, which index into the atlas the tile is placed in.
+     * - The first atlas records the number of others, see `Self::overflow_count` and `Self::overflow_path`.
+     * - Fails when a single tile has more frames than `max_slots`.
+     */
+    pub fn split(mut self, max_slots: u32) -> Result<Vec<Self>, TileAtlasLimitsError> {
+        let max_slots = max_slots.clamp(1, TILE_ATLAS_SLOT_COUNT);
+        self.overflow = 0;
+        if self.try_build_layout().is_ok_and(|layout| layout.slot_count() <= max_slots) { return Ok(vec![self]); }
+
+        // The encoded image covers every tile, so it's encoded again for each part
+        self.compressed = None;
+        let mut groups = core::mem::take(&mut self.groups).into_iter().collect::<Vec<_>>();
+        groups.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let pins = core::mem::take(&mut self.pins);
+
+        // Places each group in the first atlas with room, keeping the split stable as content is added
+        let mut parts = Vec::<(Self, u32)>::new();
+        let place = |parts: &mut Vec<(Self, u32)>, count: u32| {
+            let index = parts.iter().position(|(_, used)| used + count <= max_slots).unwrap_or_else(|| {
+                parts.push((self.clone(), 0));
+                parts.len() - 1
+            });
+            parts[index].1 += count;
+            index
+        };
+
+        for (group_id, group) in groups {
+            let count = group.tile_sets.values().map(|s| s.levels[0].data.len() as u32).sum::<u32>();
+            if count <= max_slots {
+                let index = place(&mut parts, count);
+                parts[index].0.groups.insert(group_id, group);
+                continue;
+            }
+
+            let mut tiles = group.tile_sets.into_iter().collect::<Vec<_>>();
+            tiles.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+            for (tile_id, set) in tiles {
+                let count = set.levels[0].data.len() as u32;
+                if count > max_slots {
+                    return Err(TileAtlasLimitsError::SequenceTooLong{ group_id, tile_id, frame_count: count, max_slots });
+                }
+                let index = place(&mut parts, count);
+                parts[index].0.groups.entry(group_id.clone()).or_default().tile_sets.insert(tile_id, set);
+            }
+        }
+
+        // Pins of tiles that haven't been inserted yet stay with the first atlas
+        for (group_id, tile_id, index) in pins.iter() {
+            let part = parts.iter().position(|(p, _)| p.get_tile(group_id, tile_id).is_some()).unwrap_or(0);
+            parts[part].0.pin(group_id, tile_id, index);
+        }
+
+        let mut parts = parts.into_iter().map(|(mut part, _)| { part.retain_timings(); part }).collect::<Vec<_>>();
+        // Pins can leave gaps, so a part may still need more slots than it has frames
+        for part in &parts {
+            part.check_slot_count()?;
+        }
+        parts[0].overflow = parts.len() as u32 - 1;
+        Ok(parts)
+    }
+
+    /**
+     * Splits the atlas so each part can address its tiles within the `limits`, then checks or fits each part,
+     * see `Self::check_limits` and `Self::fit_limits`.
+     */
+    pub fn split_limits(mut self, limits: &TileAtlasLimits, fit: bool) -> Result<Vec<Self>, TileAtlasLimitsError> {
+        // Split at the page size fitting would choose, otherwise each part would have too many pages
+        if fit { self.limit_page_size(limits.max_texture_dimension_2d); }
+
+        let max_slots = self.max_slots(limits);
+        self.split(max_slots)?.into_iter().map(|mut part| {
+            if fit { part.fit_limits(limits)?; } else { part.check_limits(limits)?; }
+            Ok(part)
+        }).collect()
+    }
+
+    /**
+     * The path of an overflow atlas, where `index` counts from 1, ie. `tiles.sfa` has overflow atlases `tiles.1.sfa`, `tiles.2.sfa`, ...
+     */
+    #[must_use]
+    pub fn overflow_path(path: &Path, index: u32) -> PathBuf {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        match path.extension() {
+            Some(extension) => path.with_file_name(format!("{stem}.{index}.{}", extension.to_string_lossy())),
+            None            => path.with_file_name(format!("{stem}.{index}")),
+        }
+    }
+
+}
@@ -1,13 +1,43 @@
 // Copyright 2025 Natalie Baker // Apache License v2 //
 
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TileSetError {
+    #[error("tileset has a count of {0:?}, expected at least one tile on each axis")]
+    NoTiles([u32; 2]),
+
+    #[error("tileset with an offset of {offset:?} and spacing of {spacing:?} leaves no room for tiles in a {src_size:?} image")]
+    TooSmall {
+        offset:   [u32; 2],
+        spacing:  [u32; 2],
+        src_size: [u32; 2],
+    },
+
+    #[error("tileset of {count:?} {tile_size}px tiles at {offset:?} doesn't fit in the {src_size:?} image")]
+    OutOfBounds {
+        offset:    [u32; 2],
+        count:     [u32; 2],
+        tile_size: u32,
+        src_size:  [u32; 2],
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TileSetSettings {
     pub offset:  [u32; 2],
     pub spacing: [u32; 2],
     pub count:   [u32; 2],
 }
 
+impl Default for TileSetSettings {
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+
 impl TileSetSettings {
 
     #[must_use] 
@@ -39,4 +69,54 @@ impl TileSetSettings {
         }
     }
 
-}
\ No newline at end of file
+}
+
+impl TileSetSettings {
+
+    /**
+     * Infers the size of the square tiles in an image of `src_size` sliced with these settings.
+     * - Fails when there are no tiles, or the offset and spacing leave no room for them.
+     */
+    pub fn tile_size(&self, src_size: [u32; 2]) -> Result<u32, TileSetError> {
+        if self.count.contains(&0) { return Err(TileSetError::NoTiles(self.count)); }
+        let tile_size = [0,1].map(|i| src_size[i].checked_sub(self.offset[i])
+            .and_then(|len| len.checked_div(self.count[i]))
+            .and_then(|len| len.checked_sub(self.spacing[i]))
+        );
+        match tile_size {
+            [Some(x), Some(y)] if x.min(y) > 0 => Ok(x.min(y)),
+            _ => Err(TileSetError::TooSmall{ offset: self.offset, spacing: self.spacing, src_size }),
+        }
+    }
+
+    /**
+     * Checks that every tile of `tile_size` fits in an image of `src_size`.
+     */
+    pub fn check_bounds(&self, tile_size: u32, src_size: [u32; 2]) -> Result<(), TileSetError> {
+        if self.count.contains(&0) { return Ok(()); }
+        let last = [0,1].map(|i| (self.count[i] - 1).checked_mul(tile_size + self.spacing[i])
+            .and_then(|v| v.checked_add(self.offset[i]))
+            .and_then(|v| v.checked_add(tile_size))
+        );
+        match last {
+            [Some(x), Some(y)] if x <= src_size[0] && y <= src_size[1] => Ok(()),
+            _ => Err(TileSetError::OutOfBounds{ offset: self.offset, count: self.count, tile_size, src_size }),
+        }
+    }
+
+    /**
+     * Iterates over the offsets of each tile of `tile_size` in the tileset, in row-major order.
+     */
+    pub fn offsets(&self, tile_size: u32) -> impl Iterator<Item = [u32; 2]> + use<> {
+        let Self{ offset, spacing, count } = *self;
+        (0..count[0]*count[1]).map(move |i| {
+            let x = i % count[0];
+            let y = i / count[0];
+            [
+                offset[0] + x*spacing[0] + x*tile_size,
+                offset[1] + y*spacing[1] + y*tile_size,
+            ]
+        })
+    }
+
+}
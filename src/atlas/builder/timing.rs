@@ -0,0 +1,114 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::HashMap;
+
+use bevy::platform::hash::FixedHasher;
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::atlas::TileAtlasSlot;
+
+use super::TileAtlasBuilder;
+
+/**
+ * The largest frame count and frame duration a `TileAtlasSlot` can hold.
+ */
+pub const TILE_ATLAS_SLOT_ANIMATION_MAX: u16 = 0x000F;
+
+/**
+ * The display time of each frame of an animated tile in milliseconds, as imported from its source animation.
+ * - Slots play frames for a uniform number of animator ticks, so uneven durations are averaged, see `Self::apply`.
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TileAtlasFrameTiming {
+    pub durations: Vec<u32>,
+}
+
+impl TileAtlasFrameTiming {
+
+    #[must_use]
+    pub fn uniform(frame_count: usize, duration: u32) -> Self {
+        Self{ durations: vec![duration; frame_count] }
+    }
+
+    #[must_use]
+    pub const fn frame_count(&self) -> usize {
+        self.durations.len()
+    }
+
+    /**
+     * The length of one loop of the animation in milliseconds.
+     */
+    #[must_use]
+    pub fn total_duration(&self) -> u32 {
+        self.durations.iter().sum()
+    }
+
+    #[must_use]
+    pub fn is_uniform(&self) -> bool {
+        self.durations.windows(2).all(|w| w[0] == w[1])
+    }
+
+    /**
+     * The mean frame duration in ticks of a `TileGridAnimator` running at `ticks_per_second`, limited to what a slot can hold.
+     */
+    #[must_use]
+    pub fn frame_duration(&self, ticks_per_second: f64) -> u16 {
+        if self.durations.is_empty() { return 1; }
+        let mean  = self.total_duration() as f64 / self.durations.len() as f64;
+        let ticks = (mean * ticks_per_second / 1000.0).round();
+        ticks.clamp(1.0, TILE_ATLAS_SLOT_ANIMATION_MAX as f64) as u16
+    }
+
+    /**
+     * Sets the frame count and duration of the `slot` to play this animation, keeping its delay, see `TileAtlasSlot::with_animation`.
+     * - Sequences longer than a slot can count only play their first frames.
+     */
+    #[must_use]
+    pub fn apply(&self, slot: TileAtlasSlot, ticks_per_second: f64) -> TileAtlasSlot {
+        let frame_count = self.frame_count().min(TILE_ATLAS_SLOT_ANIMATION_MAX as usize) as u16;
+        slot.with_animation(frame_count, self.frame_duration(ticks_per_second), slot.frame_delay())
+    }
+
+}
+
+impl TileAtlasBuilder {
+
+    /**
+     * Records the frame timing of the tile with the given `group_id` and `tile_id`, carried into `TileAtlas::timing`.
+     */
+    pub fn set_timing(&mut self, group_id: &str, tile_id: &str, timing: TileAtlasFrameTiming) {
+        self.timings.entry(group_id.to_owned()).or_default().insert(tile_id.to_owned(), timing);
+    }
+
+    #[must_use]
+    pub fn timing(&self, group_id: &str, tile_id: &str) -> Option<&TileAtlasFrameTiming> {
+        self.timings.get(group_id).and_then(|g| g.get(tile_id))
+    }
+
+    #[must_use]
+    pub const fn timings(&self) -> &HashMap<String, HashMap<String, TileAtlasFrameTiming, FixedHasher>, FixedHasher> {
+        &self.timings
+    }
+
+    pub fn remove_timing(&mut self, group_id: &str, tile_id: &str) -> Option<TileAtlasFrameTiming> {
+        let group  = self.timings.get_mut(group_id)?;
+        let result = group.remove(tile_id);
+        if group.is_empty() { self.timings.remove(group_id); }
+        result
+    }
+
+    /**
+     * Drops the timing of tiles that aren't in the atlas, such as after `Self::split`.
+     */
+    pub(super) fn retain_timings(&mut self) {
+        let groups = &self.groups;
+        self.timings.retain(|group_id, timings| {
+            let Some(group) = groups.get(group_id) else { return false; };
+            timings.retain(|tile_id, _| group.tile_sets.contains_key(tile_id));
+            !timings.is_empty()
+        });
+    }
+
+}
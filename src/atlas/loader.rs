@@ -0,0 +1,152 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use bevy::{asset::{io::Reader, AssetLoader, AssetPath, LoadContext, RenderAssetUsages}, image::{CompressedImageFormats, ImageSampler, ImageSamplerDescriptor}, render::render_resource::TextureUsages};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::atlas::{builder::{TileAtlasBuilder, TileAtlasBuilderReadError, TileAtlasLimits, TileAtlasLimitsError, TILE_ATLAS_FILE_EXTENSION}, TileAtlas};
+
+/**
+ * The label of the atlas texture sub-asset, ie. `tiles.sfa#image`.
+ */
+pub const TILE_ATLAS_IMAGE_LABEL: &str = "image";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileAtlasLoaderSettings {
+    /// Discards all mip levels from this level onwards, `None` keeps every level.
+    pub limit_levels: Option<u32>,
+    /// The limits the atlas is checked against, `None` skips the check.
+    pub limits: Option<TileAtlasLimits>,
+    /// Changes the atlas to fit the limits instead of failing, see `TileAtlasBuilder::fit_limits`.
+    pub fit_limits: bool,
+    pub sampler: ImageSamplerDescriptor,
+    #[serde(with = "serde_texture_usages")]
+    pub texture_usage: TextureUsages,
+    pub asset_usage: RenderAssetUsages,
+}
+
+impl Default for TileAtlasLoaderSettings {
+    fn default() -> Self {
+        Self {
+            limit_levels:  None,
+            limits:        None,
+            fit_limits:    false,
+            sampler:       TileAtlasBuilder::default_sampler_descriptor(),
+            texture_usage: TextureUsages::TEXTURE_BINDING,
+            asset_usage:   RenderAssetUsages::RENDER_WORLD,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TileAtlasLoaderError {
+    #[error("failed to load tile atlas: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Read(#[from] TileAtlasBuilderReadError),
+
+    #[error(transparent)]
+    Limits(#[from] TileAtlasLimitsError),
+}
+
+/**
+ * Loads `.sfa` tile atlas files as a `TileAtlas`, the atlas texture is added
+ * as a labelled sub-asset, see `TILE_ATLAS_IMAGE_LABEL`.
+ * - Overflow atlases are loaded from their files next to the atlas, see `TileAtlasBuilder::overflow_path`.
+ * - Atlases compressed ahead of time keep their compression when the device supports it, see `TileAtlasBuilder::compress`.
+ */
+#[derive(Debug, Default)]
+pub struct TileAtlasLoader {
+    supported: CompressedImageFormats,
+}
+
+impl TileAtlasLoader {
+
+    /**
+     * A loader for devices supporting the `supported` compressed formats, see `CompressedImageFormatSupport`.
+     */
+    #[must_use]
+    pub const fn new(supported: CompressedImageFormats) -> Self {
+        Self{ supported }
+    }
+
+}
+
+impl AssetLoader for TileAtlasLoader {
+    type Asset    = TileAtlas;
+    type Settings = TileAtlasLoaderSettings;
+    type Error    = TileAtlasLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let mut builder = TileAtlasBuilder::read_from_bytes(&bytes)?;
+        if let Some(limit_levels) = settings.limit_levels {
+            builder.limit_levels(limit_levels);
+        }
+
+        match (settings.limits, settings.fit_limits) {
+            (Some(limits), true)  => builder.fit_limits(&limits)?,
+            (Some(limits), false) => builder.check_limits(&limits)?,
+            (None, _) => builder.check_slot_count()?,
+        }
+
+        let compression = builder.compressed()
+            .map(|compressed| compressed.compression)
+            .filter(|compression| compression.is_supported(self.supported))
+            .unwrap_or_default();
+
+        let image = builder.build_image_with_compression(
+            compression,
+            Some("tile_atlas_texture"),
+            settings.texture_usage,
+            settings.asset_usage,
+            ImageSampler::Descriptor(settings.sampler.clone()),
+        );
+
+        // Overflow atlases are written next to this one, and loaded with the same settings
+        let overflow = (1..=builder.overflow_count()).map(|index| {
+            let path = AssetPath::from_path_buf(TileAtlasBuilder::overflow_path(load_context.path(), index))
+                .with_source(load_context.asset_path().source().clone_owned());
+            let settings = settings.clone();
+            load_context.loader()
+                .with_settings(move |dst: &mut TileAtlasLoaderSettings| *dst = settings.clone())
+                .load(path)
+        }).collect();
+
+        let texture_format = image.texture_descriptor.format;
+        let image = load_context.add_labeled_asset(TILE_ATLAS_IMAGE_LABEL.to_owned(), image);
+        Ok(TileAtlas::from_builder(image, &builder)
+            .with_texture_format(texture_format)
+            .with_overflow(overflow))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[TILE_ATLAS_FILE_EXTENSION]
+    }
+}
+
+/**
+ * `TextureUsages` only implements serde when wgpu's serde feature is enabled, so
+ * we store the raw bits instead.
+ */
+mod serde_texture_usages {
+    use bevy::render::render_resource::TextureUsages;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)] // Signature required by serde
+    pub fn serialize<S: Serializer>(value: &TextureUsages, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(value.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TextureUsages, D::Error> {
+        u32::deserialize(deserializer).map(TextureUsages::from_bits_truncate)
+    }
+}
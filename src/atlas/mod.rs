@@ -2,13 +2,25 @@
 
 use std::collections::HashMap;
 
-use bevy::{platform::hash::FixedHasher, prelude::*};
+use bevy::{platform::hash::FixedHasher, prelude::*, render::render_resource::TextureFormat};
 
 pub mod builder;
 
+mod loader;
+pub use loader::*;
+
+mod patch;
+pub use patch::*;
+
 mod plugin;
 pub use plugin::*;
 
+mod processor;
+pub use processor::*;
+
+mod remap;
+pub use remap::*;
+
 mod slot;
 pub use slot::*;
 
@@ -24,8 +36,16 @@ pub struct TileAtlasEntry {
 
 #[derive(Debug, Clone, Asset, TypePath)]
 pub struct TileAtlas {
-    image:  Handle<Image>,
-    lookup: HashMap<String, TileAtlasGroup, FixedHasher>,
+    image:    Handle<Image>,
+    lookup:   HashMap<String, TileAtlasGroup, FixedHasher>,
+    uv_scale: f32,
+    uv_offset: f32,
+    page_tiles: u32,
+    texture_format: TextureFormat,
+    channels: HashMap<String, Handle<Image>, FixedHasher>,
+    timings:  HashMap<String, HashMap<String, builder::TileAtlasFrameTiming, FixedHasher>, FixedHasher>,
+    #[dependency]
+    overflow:   Vec<Handle<TileAtlas>>,
 }
 
 impl TileAtlas {
@@ -33,7 +53,139 @@ impl TileAtlas {
     #[must_use]
     pub fn new(image: Handle<Image>, lookup: HashMap<String, TileAtlasGroup, FixedHasher>) -> Self {
         assert!(image.is_strong());
-        Self{image, lookup}
+        Self{image, lookup, uv_scale: 1.0, uv_offset: 0.0, page_tiles: builder::TILE_ATLAS_PAGE_TILES, texture_format: TextureFormat::Rgba8UnormSrgb, channels: HashMap::default(), timings: HashMap::default(), overflow: Vec::new()}
+    }
+
+    /**
+     * Creates an atlas for the `image` built by the `builder`, with its lookup, slot size, border, page size and timings.
+     * - The texture format is the builder's uncompressed format, set it if the image was compressed, see `Self::with_texture_format`.
+     */
+    #[must_use]
+    pub fn from_builder(image: Handle<Image>, builder: &builder::TileAtlasBuilder) -> Self {
+        Self::new(image, builder.build_lookup())
+            .with_uv_scale(builder.uv_scale())
+            .with_uv_offset(builder.uv_offset())
+            .with_timings(builder.timings().clone())
+            .with_page_tiles(builder.page_tiles())
+            .with_texture_format(builder.format().texture_format())
+    }
+
+    /**
+     * Sets the fraction of each slot covered by a tile, for atlases with padded tiles, see `TileAtlasBuilder::uv_scale`.
+     */
+    #[must_use]
+    pub fn with_uv_scale(self, uv_scale: f32) -> Self {
+        Self{ uv_scale, ..self }
+    }
+
+    #[must_use]
+    pub const fn uv_scale(&self) -> f32 {
+        self.uv_scale
+    }
+
+    /**
+     * Sets the offset of each tile from the corner of its slot, for atlases with tile borders, see `TileAtlasBuilder::uv_offset`.
+     * - Atlases with an offset are sampled once with hardware filtering, rather than clamped to the tile.
+     */
+    #[must_use]
+    pub fn with_uv_offset(self, uv_offset: f32) -> Self {
+        Self{ uv_offset, ..self }
+    }
+
+    #[must_use]
+    pub const fn uv_offset(&self) -> f32 {
+        self.uv_offset
+    }
+
+    /**
+     * Sets the number of tiles along each side of a page, see `TileAtlasBuilder::page_tiles`.
+     */
+    #[must_use]
+    pub fn with_page_tiles(self, page_tiles: u32) -> Self {
+        Self{ page_tiles, ..self }
+    }
+
+    #[must_use]
+    pub const fn page_tiles(&self) -> u32 {
+        self.page_tiles
+    }
+
+    /**
+     * Sets the texture format of the atlas image, which patches are converted and encoded to,
+     * see `TileAtlasBuilder::format` and `TileAtlasBuilder::compress`.
+     */
+    #[must_use]
+    pub fn with_texture_format(self, texture_format: TextureFormat) -> Self {
+        Self{ texture_format, ..self }
+    }
+
+    #[must_use]
+    pub const fn texture_format(&self) -> TextureFormat {
+        self.texture_format
+    }
+
+    /**
+     * The pixel format of the atlas image, ignoring any compression.
+     */
+    #[must_use]
+    pub fn format(&self) -> builder::TileAtlasPixelFormat {
+        builder::TileAtlasPixelFormat::from_texture_format(self.texture_format).unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn compression(&self) -> builder::TileAtlasCompression {
+        builder::TileAtlasCompression::from_texture_format(self.texture_format).unwrap_or_default()
+    }
+
+    /**
+     * Adds a companion texture, such as a normal or emissive map, sharing the slots of `Self::image`,
+     * see `TileAtlasMaterialBuilder`.
+     */
+    #[must_use]
+    pub fn with_channel(mut self, name: impl Into<String>, image: Handle<Image>) -> Self {
+        self.channels.insert(name.into(), image);
+        self
+    }
+
+    #[must_use]
+    pub fn channel(&self, name: &str) -> Option<&Handle<Image>> {
+        self.channels.get(name)
+    }
+
+    /**
+     * Iterates over the companion textures as (`name`, `image`), these don't include `Self::image`.
+     */
+    pub fn channels(&self) -> impl Iterator<Item = (&str, &Handle<Image>)> {
+        self.channels.iter().map(|(name, image)| (name.as_str(), image))
+    }
+
+    /**
+     * Sets the frame timing of animated tiles, see `TileAtlasBuilder::set_timing`.
+     */
+    #[must_use]
+    pub fn with_timings(self, timings: HashMap<String, HashMap<String, builder::TileAtlasFrameTiming, FixedHasher>, FixedHasher>) -> Self {
+        Self{ timings, ..self }
+    }
+
+    /**
+     * The frame timing of an animated tile, used to set up its slots with `TileAtlasFrameTiming::apply`.
+     */
+    #[must_use]
+    pub fn timing(&self, group: &str, id: &str) -> Option<&builder::TileAtlasFrameTiming> {
+        self.timings.get(group).and_then(|g| g.get(id))
+    }
+
+    /**
+     * Sets the atlases holding the tiles that didn't fit in this one, see `TileAtlasBuilder::split`.
+     */
+    #[must_use]
+    pub fn with_overflow(self, overflow: Vec<Handle<TileAtlas>>) -> Self {
+        Self{ overflow, ..self }
+    }
+
+    #[must_use]
+    pub fn overflow(&self) -> &[Handle<TileAtlas>] {
+        &self.overflow
     }
 
     #[must_use]
@@ -41,6 +193,11 @@ impl TileAtlas {
         &self.image
     }
 
+    #[must_use]
+    pub const fn lookup(&self) -> &HashMap<String, TileAtlasGroup, FixedHasher> {
+        &self.lookup
+    }
+
     #[must_use]
     pub fn get_group(&self, group: &str) -> Option<&TileAtlasGroup> {
         self.lookup.get(group)
@@ -51,5 +208,15 @@ impl TileAtlas {
         self.lookup.get(group).and_then(|g| g.get(id))
     }
 
-}
+    /**
+     * Finds a tile in this atlas or any of its overflow atlases, as (`atlas`, `entry`).
+     * - `atlas` is `None` when the tile is in this atlas, otherwise it's the overflow atlas a grid showing the tile should use.
+     * - A grid draws from a single atlas, so every tile on a grid must come from the same atlas of the set.
+     */
+    #[must_use]
+    pub fn find_entry<'a>(&'a self, atlases: &'a Assets<TileAtlas>, group: &str, id: &str) -> Option<(Option<&'a Handle<TileAtlas>>, &'a TileAtlasEntry)> {
+        if let Some(entry) = self.get_entry(group, id) { return Some((None, entry)); }
+        self.overflow.iter().find_map(|handle| atlases.get(handle)?.get_entry(group, id).map(|entry| (Some(handle), entry)))
+    }
 
+}
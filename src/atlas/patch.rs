@@ -0,0 +1,218 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use bevy::{prelude::*, render::{render_asset::RenderAssets, render_resource::{Extent3d, Origin3d, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect, TextureFormat}, renderer::RenderQueue, texture::GpuImage, MainWorld}};
+
+use crate::atlas::{builder::{image_extrude, DownsampleAlgorithm, TileAtlasBuilderSet, TileAtlasCompression}, TileAtlas, TileAtlasEntry};
+
+/**
+ * Replacement pixel data for one mip level of a single atlas slot.
+ */
+#[derive(Debug, Clone)]
+pub struct TileAtlasPatch {
+    image: AssetId<Image>,
+    index: u16,
+    level: u32,
+    size:  u32,
+    page_tiles: u32,
+    texture_format: TextureFormat,
+    bytes_per_row:  u32,
+    data:  Box<[u8]>,
+}
+
+/**
+ * Queues writes of individual tiles into atlas textures, only the affected
+ * regions of the texture are uploaded.
+ * - Patches are applied to the GPU texture only, the `Image` asset isn't modified and
+ *   rebuilding or reloading the atlas discards them.
+ * - Patches for textures that haven't been uploaded yet are held until they are.
+ * - Patches for mip levels the texture doesn't have are ignored.
+ * - Data is expected to be in the atlas' pixel format and in row-major order, see `TileAtlas::format`.
+ * - Patches for compressed atlases are encoded when written, levels with slots smaller than a block are ignored,
+ *   see `TileAtlasBuilder::compressed_level_count`.
+ * - Only `TileAtlas::image` is written, companion channels keep their contents.
+ */
+#[derive(Debug, Default, Resource)]
+pub struct TileAtlasPatches {
+    patches: Vec<TileAtlasPatch>,
+}
+
+impl TileAtlasPatches {
+
+    /**
+     * Replaces a single mip `level` of the slot at `index`, `data` must be the size of a slot in that level, including any padding.
+     * - Data that isn't a square image is discarded with a warning.
+     */
+    pub fn write_level(&mut self, atlas: &TileAtlas, index: u16, level: u32, data: impl Into<Box<[u8]>>) {
+        let mut data    = data.into();
+        let pixel_len   = atlas.format().pixel_len();
+        let Some(size)  = tile_size_from_len(data.len(), pixel_len, index) else { return; };
+        let compression = atlas.compression();
+        let mut bytes_per_row = size*pixel_len;
+        if compression != TileAtlasCompression::None {
+            // Compressed textures stop at the last level with whole blocks
+            if !size.is_multiple_of(compression.block_size()) { return; }
+            let mut encoded = vec![0_u8; compression.data_len(size) as usize].into_boxed_slice();
+            compression.encode(&data, size, &mut encoded);
+            data = encoded;
+            bytes_per_row = (size/compression.block_size())*compression.block_len();
+        }
+
+        self.patches.push(TileAtlasPatch {
+            image: atlas.image().id(),
+            index,
+            level,
+            size,
+            page_tiles: atlas.page_tiles(),
+            texture_format: atlas.texture_format(),
+            bytes_per_row,
+            data,
+        });
+    }
+
+    /**
+     * Replaces the slot at `index` with the tile in `data`, generating every mip level with the `downsampler`.
+     * - `data` must be the whole slot at mip level 0, including any border and padding, rather than just the tile.
+     * - Atlases with tile borders have them extruded again in every generated level, see `TileAtlas::uv_offset`.
+     * - Data that isn't a square image is discarded with a warning.
+     */
+    pub fn write_tile(&mut self, atlas: &TileAtlas, index: u16, data: &[u8], downsampler: &impl DownsampleAlgorithm) {
+        let format    = atlas.format();
+        let Some(mut size) = tile_size_from_len(data.len(), format.pixel_len(), index) else { return; };
+        let mut level = 0;
+        let mut src   = Box::<[u8]>::from(data);
+
+        // The border and content in pixels at level 0, recovered from the fractions of the slot they cover
+        let border  = (atlas.uv_offset()*size as f32).round() as u32;
+        let content = (atlas.uv_scale()*size as f32).round() as u32;
+
+        loop {
+            let next = (size.trailing_zeros() > 0).then(|| {
+                let mut dst = vec![0_u8; src.len()/4].into_boxed_slice();
+                downsampler.downsample_format(format, data, &src, size as usize, &mut dst);
+                if border > 0 {
+                    image_extrude(&mut dst, format.pixel_len(), size/2, border >> (level + 1), content.div_ceil(1 << (level + 1)));
+                }
+                dst
+            });
+
+            self.write_level(atlas, index, level, src);
+            let Some(next) = next else { break; };
+            src    = next;
+            size  /= 2;
+            level += 1;
+        }
+    }
+
+    /**
+     * Replaces the sequence at `entry` with the frames in `frames`, generating every mip level with the `downsampler`.
+     * - Each frame must be the whole slot at mip level 0, see `Self::write_tile`.
+     * - Frames beyond the length of the sequence are ignored.
+     */
+    pub fn write_sequence<'a>(
+        &mut self,
+        atlas: &TileAtlas,
+        entry: TileAtlasEntry,
+        frames: impl IntoIterator<Item = &'a [u8]>,
+        downsampler: &impl DownsampleAlgorithm,
+    ) {
+        for (index, data) in (entry.index..entry.index+entry.count).zip(frames) {
+            self.write_tile(atlas, index, data, downsampler);
+        }
+    }
+
+    /**
+     * Replaces the sequence at `entry` with every level of a tile from a `TileAtlasBuilder`.
+     */
+    pub fn write_builder_tile(&mut self, atlas: &TileAtlas, entry: TileAtlasEntry, set: &TileAtlasBuilderSet) {
+        for (level, sequence) in set.levels.iter().enumerate() {
+            for (index, data) in (entry.index..entry.index+entry.count).zip(&sequence.data) {
+                self.write_level(atlas, index, level as u32, data.clone());
+            }
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+}
+
+/**
+ * The patches extracted to the render world, waiting for their texture.
+ */
+#[derive(Debug, Default, Resource)]
+pub struct TileAtlasPatchesPending {
+    patches: Vec<TileAtlasPatch>,
+}
+
+pub fn tile_atlas_extract_patches(
+    mut main_world: ResMut<MainWorld>,
+    mut r_pending:  ResMut<TileAtlasPatchesPending>,
+) {
+    let Some(mut patches) = main_world.get_resource_mut::<TileAtlasPatches>() else { return; };
+    if patches.is_empty() { return; }
+    r_pending.patches.append(&mut patches.patches);
+}
+
+pub fn tile_atlas_prepare_patches(
+    mut r_pending: ResMut<TileAtlasPatchesPending>,
+    r_images: Res<RenderAssets<GpuImage>>,
+    r_queue:  Res<RenderQueue>,
+) {
+    r_pending.patches.retain(|patch| {
+        let Some(gpu_image) = r_images.get(patch.image) else { return true; };
+
+        let tile_size  = gpu_image.size.width / patch.page_tiles;
+        let level_size = tile_size.unbounded_shr(patch.level);
+        let page  = (patch.index as u32) / (patch.page_tiles*patch.page_tiles);
+        let slot  = (patch.index as u32) % (patch.page_tiles*patch.page_tiles);
+
+        // Generated levels beyond the atlas' mip levels are expected
+        if patch.level >= gpu_image.mip_level_count { return false; }
+
+        if patch.size != level_size || page >= gpu_image.size.depth_or_array_layers || gpu_image.texture_format != patch.texture_format {
+            warn!("Discarding tile atlas patch for slot {} level {}, it doesn't fit the atlas texture", patch.index, patch.level);
+            return false;
+        }
+
+        r_queue.write_texture(
+            TexelCopyTextureInfo {
+                texture:   &gpu_image.texture,
+                mip_level: patch.level,
+                origin:    Origin3d {
+                    x: (slot % patch.page_tiles)*level_size,
+                    y: (slot / patch.page_tiles)*level_size,
+                    z: page,
+                },
+                aspect: TextureAspect::All,
+            },
+            &patch.data,
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row:  Some(patch.bytes_per_row),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width:  level_size,
+                height: level_size,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        false
+    });
+}
+
+/**
+ * Calculates the size of a square tile from its data length, warning that the patch for slot `index` is discarded if it isn't one.
+ */
+fn tile_size_from_len(len: usize, pixel_len: u32, index: u16) -> Option<u32> {
+    let pixel_len = pixel_len as usize;
+    let size = (len/pixel_len).isqrt();
+    if size == 0 || size*size*pixel_len != len {
+        warn!("Discarding tile atlas patch for slot {index}, its {len} bytes aren't a square image in the atlas' pixel format");
+        return None;
+    }
+    Some(size as u32)
+}
@@ -1,8 +1,8 @@
 // Copyright 2025 Natalie Baker // Apache License v2 //
 
-use bevy::prelude::*;
+use bevy::{asset::{AssetApp, AssetEventSystems}, image::{CompressedImageFormatSupport, CompressedImageFormats}, prelude::*, render::{Render, RenderApp, RenderSystems}};
 
-use crate::atlas::{builder::{proccess_tile_atlas_build_queue, process_tile_atlas_build_queues_with_target}, TileAtlas};
+use crate::atlas::{builder::{hot_reload_tile_atlas_sources, poll_tile_atlas_build_tasks, proccess_tile_atlas_build_queue, process_tile_atlas_build_queues_with_target, TileAtlasBuildBudget, TileAtlasBuilder, TileAtlasHotReload, TILE_ATLAS_FILE_EXTENSION, TILE_ATLAS_MANIFEST_EXTENSION}, remap_tile_atlas_grids, tile_atlas_extract_patches, tile_atlas_prepare_patches, TileAtlas, TileAtlasBakeProcess, TileAtlasBuilderSaver, TileAtlasLoader, TileAtlasManifestLoader, TileAtlasPatches, TileAtlasPatchesPending, TileAtlasRemapCache, TileAtlasRemapSettings};
 
 pub struct PluginTileAtlas;
 
@@ -10,13 +10,39 @@ impl Plugin for PluginTileAtlas {
 
     fn build(&self, app: &mut bevy::app::App) {
         app.init_asset::<TileAtlas>()
+            .init_asset::<TileAtlasBuilder>()
+            .preregister_asset_loader::<TileAtlasLoader>(&[TILE_ATLAS_FILE_EXTENSION])
+            .init_asset_loader::<TileAtlasManifestLoader>()
+            .register_asset_processor(TileAtlasBakeProcess::from(TileAtlasBuilderSaver))
+            .set_default_asset_processor::<TileAtlasBakeProcess>(TILE_ATLAS_MANIFEST_EXTENSION)
+            .init_resource::<TileAtlasRemapSettings>()
+            .init_resource::<TileAtlasRemapCache>()
+            .init_resource::<TileAtlasPatches>()
+            .init_resource::<TileAtlasHotReload>()
+            .init_resource::<TileAtlasBuildBudget>()
+            .add_systems(PostUpdate, remap_tile_atlas_grids.after(AssetEventSystems))
+            .add_systems(PostUpdate, hot_reload_tile_atlas_sources.after(AssetEventSystems))
             .add_systems(
                 Last, 
                 (
                     proccess_tile_atlas_build_queue,
                     process_tile_atlas_build_queues_with_target,
+                    poll_tile_atlas_build_tasks,
                 ).chain()
             );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .init_resource::<TileAtlasPatchesPending>()
+                .add_systems(ExtractSchedule, tile_atlas_extract_patches)
+                .add_systems(Render, tile_atlas_prepare_patches.in_set(RenderSystems::PrepareResources));
+        }
+    }
+
+    fn finish(&self, app: &mut bevy::app::App) {
+        // The supported compressed formats are only known once the render device is created
+        let supported = app.world().get_resource::<CompressedImageFormatSupport>().map_or(CompressedImageFormats::NONE, |support| support.0);
+        app.register_asset_loader(TileAtlasLoader::new(supported));
     }
 
 }
\ No newline at end of file
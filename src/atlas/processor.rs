@@ -0,0 +1,121 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::HashMap;
+
+use bevy::{asset::{io::{Reader, Writer}, processor::LoadTransformAndSave, saver::{AssetSaver, SavedAsset}, transformer::IdentityAssetTransformer, AssetLoader, AsyncWriteExt, LoadContext, LoadDirectError, ParseAssetPathError, ReadAssetBytesError}, image::Image};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::atlas::{builder::{TileAtlasBuilder, TileAtlasFileCodec, TileAtlasIndexManifest, TileAtlasManifest, TileAtlasManifestError, TILE_ATLAS_MANIFEST_EXTENSION}, TileAtlasLoader, TileAtlasLoaderSettings};
+
+/**
+ * Bakes a tile atlas manifest and its source images into a `.sfa` file when
+ * asset processing is enabled, the result is loaded by `TileAtlasLoader`.
+ */
+pub type TileAtlasBakeProcess = LoadTransformAndSave<TileAtlasManifestLoader, IdentityAssetTransformer<TileAtlasBuilder>, TileAtlasBuilderSaver>;
+
+#[derive(Debug, Error)]
+pub enum TileAtlasManifestLoaderError {
+    #[error("failed to load tile atlas manifest: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to parse tile atlas manifest: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("invalid tile atlas source path: {0}")]
+    Path(#[from] ParseAssetPathError),
+
+    #[error("failed to load tile atlas source image: {0}")]
+    Image(#[from] Box<LoadDirectError>),
+
+    #[error("failed to load tile atlas index manifest: {0}")]
+    IndexRead(#[from] ReadAssetBytesError),
+
+    #[error("failed to parse tile atlas index manifest: {0}")]
+    IndexParse(ron::error::SpannedError),
+
+    #[error("tile atlas source image \"{0}\" can't be converted to the atlas' pixel format")]
+    ImageFormat(String),
+
+    #[error(transparent)]
+    Manifest(#[from] TileAtlasManifestError),
+}
+
+/**
+ * Loads a tile atlas manifest and all the images it references into a `TileAtlasBuilder`.
+ */
+#[derive(Debug, Default)]
+pub struct TileAtlasManifestLoader;
+
+impl AssetLoader for TileAtlasManifestLoader {
+    type Asset    = TileAtlasBuilder;
+    type Settings = ();
+    type Error    = TileAtlasManifestLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let manifest = TileAtlasManifest::from_ron(&bytes)?;
+
+        let mut images = HashMap::<&str, (Vec<u8>, [u32; 2])>::new();
+        for source in manifest.sources() {
+            let path  = load_context.asset_path().resolve_embed(source)?;
+            let image = load_context.loader().immediate().load::<Image>(path).await.map_err(Box::new)?.take();
+            let data  = manifest.format.image_data(&image).ok_or_else(|| TileAtlasManifestLoaderError::ImageFormat(source.to_owned()))?;
+            images.insert(source, (data.into_owned(), [image.width(), image.height()]));
+        }
+
+        let indices = match &manifest.indices {
+            Some(indices) => {
+                let path  = load_context.asset_path().resolve_embed(indices)?;
+                let bytes = load_context.read_asset_bytes(path).await?;
+                TileAtlasIndexManifest::from_ron(&bytes).map_err(TileAtlasManifestLoaderError::IndexParse)?
+            },
+            None => TileAtlasIndexManifest::default(),
+        };
+
+        Ok(manifest.build_with_indices(&indices, |path| {
+            let (data, size) = images.get(path)?;
+            Some((data.as_slice(), *size))
+        })?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[TILE_ATLAS_MANIFEST_EXTENSION]
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TileAtlasBuilderSaverSettings {
+    pub codec: TileAtlasFileCodec,
+}
+
+/**
+ * Saves a `TileAtlasBuilder` as a `.sfa` file.
+ */
+#[derive(Debug, Default)]
+pub struct TileAtlasBuilderSaver;
+
+impl AssetSaver for TileAtlasBuilderSaver {
+    type Asset        = TileAtlasBuilder;
+    type Settings     = TileAtlasBuilderSaverSettings;
+    type OutputLoader = TileAtlasLoader;
+    type Error        = std::io::Error;
+
+    async fn save(
+        &self,
+        writer: &mut Writer,
+        asset: SavedAsset<'_, Self::Asset>,
+        settings: &Self::Settings,
+    ) -> Result<TileAtlasLoaderSettings, Self::Error> {
+        let mut bytes = Vec::new();
+        asset.get().write_to(&mut bytes, settings.codec)?;
+        writer.write_all(&bytes).await?;
+        Ok(TileAtlasLoaderSettings::default())
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+use std::collections::HashMap;
+
+use bevy::{platform::hash::FixedHasher, prelude::*};
+
+use crate::{atlas::{TileAtlas, TileAtlasGroup, TileAtlasSlot}, dense::{TileGridDenseData, TileGridDenseInfo}, sparse::TileGridSparse};
+
+/**
+ * What tiles that no longer exist in a rebuilt atlas are replaced with.
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum TileAtlasRemapFallback {
+    #[default]
+    Empty,
+    /// Replaces the tile with the given slot, including its animation settings.
+    Slot(TileAtlasSlot),
+    /// Replaces the tile with the first frame of a tile in the rebuilt atlas, keeping the animation settings up to its frame count.
+    Tile {
+        group_id: String,
+        tile_id:  String,
+    },
+}
+
+/**
+ * Controls how grids are updated when their atlas is rebuilt.
+ */
+#[derive(Debug, Clone, Resource)]
+pub struct TileAtlasRemapSettings {
+    /// Disables remapping, grids keep their raw slot indices when their atlas is rebuilt.
+    pub enabled:  bool,
+    pub fallback: TileAtlasRemapFallback,
+}
+
+impl Default for TileAtlasRemapSettings {
+    fn default() -> Self {
+        Self {
+            enabled:  true,
+            fallback: TileAtlasRemapFallback::default(),
+        }
+    }
+}
+
+/**
+ * Maps the slot indices of an atlas to the indices of the same tiles in a rebuilt atlas.
+ */
+#[derive(Debug, Clone)]
+pub struct TileAtlasRemap {
+    /// New index of each old index and the frames of its tile from there on, `None` if the tile no longer exists.
+    table:    Vec<Option<(u16, u16)>>,
+    fallback: TileAtlasSlotFallback,
+    /// Set if any tile no longer exists.
+    missing:  bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TileAtlasSlotFallback {
+    Replace(TileAtlasSlot),
+    Index(u16, u16),
+}
+
+impl TileAtlasRemap {
+
+    /**
+     * Creates a remap table between two atlas lookups, matching tiles by group and tile id.
+     * - Frames beyond the end of a shortened sequence map to its last frame, see `Self::apply`.
+     * - A `TileAtlasRemapFallback::Tile` missing from `new` falls back to an empty tile.
+     */
+    #[must_use]
+    pub fn new(
+        old: &HashMap<String, TileAtlasGroup, FixedHasher>,
+        new: &HashMap<String, TileAtlasGroup, FixedHasher>,
+        fallback: &TileAtlasRemapFallback,
+    ) -> Self {
+        let len = old.values().flat_map(|g| g.values()).map(|e| e.index as usize + e.count as usize).max().unwrap_or(0);
+        let mut table   = vec![None; len];
+        let mut missing = false;
+
+        for (group_id, group) in old {
+            for (tile_id, old_entry) in group.iter() {
+                let new_entry = new.get(group_id).and_then(|g| g.get(tile_id)).filter(|e| e.count > 0);
+                let Some(new_entry) = new_entry else { missing = true; continue; };
+                for frame in 0..old_entry.count {
+                    let new_frame = frame.min(new_entry.count - 1);
+                    table[(old_entry.index + frame) as usize] = Some((new_entry.index + new_frame, new_entry.count - new_frame));
+                }
+            }
+        }
+
+        let fallback = match fallback {
+            TileAtlasRemapFallback::Empty      => TileAtlasSlotFallback::Replace(TileAtlasSlot::EMPTY),
+            TileAtlasRemapFallback::Slot(slot) => TileAtlasSlotFallback::Replace(*slot),
+            TileAtlasRemapFallback::Tile{ group_id, tile_id } => new.get(group_id).and_then(|g| g.get(tile_id))
+                .filter(|e| e.count > 0)
+                .map_or(TileAtlasSlotFallback::Replace(TileAtlasSlot::EMPTY), |e| TileAtlasSlotFallback::Index(e.index, e.count)),
+        };
+
+        Self{ table, fallback, missing }
+    }
+
+    /**
+     * Returns true if every tile keeps its index, in which case grids don't need updating.
+     */
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        !self.missing && self.table.iter().enumerate().all(|(i, v)| v.is_none_or(|(v, _)| v == i as u16))
+    }
+
+    /**
+     * Maps an old slot to the new atlas, keeping its animation settings.
+     * - Empty slots are unchanged.
+     * - The frame count is limited to the frames left in the new tile, so a shortened sequence doesn't play into the slots after it.
+     */
+    #[must_use]
+    pub fn apply(&self, slot: TileAtlasSlot) -> TileAtlasSlot {
+        let Some(index) = slot.slot() else { return slot; };
+        let target = self.table.get(index as usize).copied().flatten();
+        let (index, frames) = match (target, self.fallback) {
+            (Some(target), _) => target,
+            (None, TileAtlasSlotFallback::Index(index, frames)) => (index, frames),
+            (None, TileAtlasSlotFallback::Replace(fallback)) => return fallback,
+        };
+        slot.with_animation(slot.frame_count().min(frames), slot.frame_duration(), slot.frame_delay())
+            .with_slot(index)
+            .unwrap_or(TileAtlasSlot::EMPTY)
+    }
+
+}
+
+/**
+ * The lookup and overflow atlases of every loaded atlas as of the last update, used to remap grids when an atlas is rebuilt.
+ */
+#[derive(Debug, Default, Resource)]
+pub struct TileAtlasRemapCache {
+    atlases: HashMap<AssetId<TileAtlas>, TileAtlasRemapCacheEntry, FixedHasher>,
+}
+
+#[derive(Debug)]
+struct TileAtlasRemapCacheEntry {
+    lookup:   HashMap<String, TileAtlasGroup, FixedHasher>,
+    overflow: Vec<AssetId<TileAtlas>>,
+}
+
+impl TileAtlasRemapCacheEntry {
+
+    fn new(atlas: &TileAtlas) -> Self {
+        Self{ lookup: atlas.lookup().clone(), overflow: atlas.overflow().iter().map(Handle::id).collect() }
+    }
+
+}
+
+/**
+ * Remaps the grids using a modified atlas, or any of its overflow atlases, see `TileAtlas::overflow`.
+ * - Tiles can move between the atlas and its overflow atlases, so each grid moves to the atlas
+ *   holding the most tiles of the atlas it used, tiles that aren't in that atlas are replaced by the fallback.
+ */
+pub fn remap_tile_atlas_grids(
+    mut e_atlas: MessageReader<AssetEvent<TileAtlas>>,
+    mut r_atlas: ResMut<Assets<TileAtlas>>,
+    r_settings:  Res<TileAtlasRemapSettings>,
+    mut r_cache: ResMut<TileAtlasRemapCache>,
+    mut q_dense:  Query<(&mut TileGridDenseInfo, &mut TileGridDenseData)>,
+    mut q_sparse: Query<&mut TileGridSparse>,
+) {
+    for event in e_atlas.read() {
+        match *event {
+            AssetEvent::Added{ id } => {
+                if let Some(atlas) = r_atlas.get(id) {
+                    r_cache.atlases.insert(id, TileAtlasRemapCacheEntry::new(atlas));
+                }
+            },
+            AssetEvent::Modified{ id } => {
+                let Some(atlas) = r_atlas.get(id) else { continue; };
+                let old = r_cache.atlases.insert(id, TileAtlasRemapCacheEntry::new(atlas));
+                let Some(old) = old.filter(|_| r_settings.enabled) else { continue; };
+
+                let old_set = core::iter::once((id, old.lookup))
+                    .chain(old.overflow.iter().filter_map(|id| Some((*id, r_cache.atlases.get(id)?.lookup.clone()))))
+                    .collect::<Vec<_>>();
+
+                let Some(handle) = r_atlas.get_strong_handle(id) else { continue; };
+                let new_set = core::iter::once(handle)
+                    .chain(r_atlas.get(id).unwrap().overflow().iter().cloned())
+                    .filter_map(|handle| r_atlas.get(&handle).map(|atlas| (handle, atlas)))
+                    .collect::<Vec<_>>();
+
+                // Overflow atlases are remapped along with this one, so their own events must find them current
+                for (handle, atlas) in &new_set[1..] {
+                    r_cache.atlases.insert(handle.id(), TileAtlasRemapCacheEntry::new(atlas));
+                }
+
+                // Each grid is remapped once, by the atlas it used before this rebuild
+                let remaps = old_set.iter().filter_map(|(old_id, old_lookup)| {
+                    // Ties prefer the atlas the grid already uses, then the first atlas
+                    let (new_handle, new_atlas) = new_set.iter()
+                        .max_by_key(|(handle, atlas)| (count_shared_tiles(old_lookup, atlas.lookup()), handle.id() == *old_id, handle.id() == id))
+                        .unwrap();
+                    let remap = TileAtlasRemap::new(old_lookup, new_atlas.lookup(), &r_settings.fallback);
+                    let moved = (new_handle.id() != *old_id).then(|| new_handle.clone());
+                    (moved.is_some() || !remap.is_identity()).then_some((*old_id, (moved, remap)))
+                }).collect::<HashMap<_, _, FixedHasher>>();
+                if remaps.is_empty() { continue; }
+
+                for (mut info, mut data) in &mut q_dense {
+                    let Some((moved, remap)) = info.atlas().as_ref().and_then(|h| remaps.get(&h.id())) else { continue; };
+                    if let Some(handle) = moved { info.set_atlas(Some(handle.clone())); }
+                    data.map_slots(|slot| remap.apply(slot));
+                }
+
+                for mut grid in &mut q_sparse {
+                    let Some((moved, remap)) = grid.atlas().as_ref().and_then(|h| remaps.get(&h.id())) else { continue; };
+                    if let Some(handle) = moved { grid.set_atlas(Some(handle.clone())); }
+                    grid.map_slots(|slot| remap.apply(slot));
+                }
+            },
+            AssetEvent::Removed{ id } => {
+                r_cache.atlases.remove(&id);
+            },
+            AssetEvent::Unused{ .. } | AssetEvent::LoadedWithDependencies{ .. } => {},
+        }
+    }
+}
+
+/**
+ * Counts the tiles of `old` that are also in `new`.
+ */
+fn count_shared_tiles(old: &HashMap<String, TileAtlasGroup, FixedHasher>, new: &HashMap<String, TileAtlasGroup, FixedHasher>) -> usize {
+    old.iter()
+        .filter_map(|(group_id, group)| Some((group, new.get(group_id)?)))
+        .map(|(old, new)| old.keys().filter(|tile_id| new.contains_key(*tile_id)).count())
+        .sum()
+}
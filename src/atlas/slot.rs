@@ -36,6 +36,20 @@ impl TileAtlasSlot {
 
 impl TileAtlasSlot {
 
+    /**
+     * Replaces the slot index, keeping the animation settings.
+     */
+    #[must_use]
+    pub fn with_slot(self, slot: u16) -> Option<Self> {
+        Some(Self {
+            slot: slot.checked_add(1)?,
+            anim: self.anim,
+        })
+    }
+
+    /**
+     * Sets the animation, packed as in `tile_grid.wgsl`: 4 bits of `frame_count`, 4 bits of `frame_duration` and 8 bits of `frame_delay`.
+     */
     #[must_use]
     pub const fn with_animation(
         self,
@@ -47,7 +61,7 @@ impl TileAtlasSlot {
             slot: self.slot,
             anim:  (frame_count    & 0x000F)
                 | ((frame_duration & 0x000F) << 4)
-                | ((frame_delay    & 0x000F) << 8)
+                | ((frame_delay    & 0x00FF) << 8)
         }
     }
 
@@ -91,7 +105,7 @@ impl TileAtlasSlot {
 
     #[must_use]
     pub const fn frame_duration(&self) -> u16 {
-        (self.anim >> 4) & 0x0003
+        (self.anim >> 4) & 0x000F
     }
 
     #[must_use]
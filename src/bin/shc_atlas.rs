@@ -0,0 +1,536 @@
+// Copyright 2025 Natalie Baker // Apache License v2 //
+
+//! Command-line tool for working with tile atlas files outside the game.
+
+use std::{borrow::Cow, collections::{BTreeMap, HashMap}, fs::File, io::{BufReader, BufWriter}, path::{Path, PathBuf}, process::ExitCode};
+
+use bevy::render::render_resource::TextureFormat;
+use image::{ExtendedColorType, ImageFormat};
+use thiserror::Error;
+
+use shc_tiles::atlas::builder::{TileAtlasBuilder, TileAtlasBuilderReadError, TileAtlasBuilderSet, TileAtlasCompression, TileAtlasCompressionError, TileAtlasFileCodec, TileAtlasFileHeader, TileAtlasIndexManifest, TileAtlasLimits, TileAtlasLimitsError, TileAtlasManifest, TileAtlasManifestError, TileAtlasManifestTile, TileAtlasPixelFormat, TILE_ATLAS_MANIFEST_EXTENSION, TILE_ATLAS_SLOT_COUNT};
+
+const USAGE: &str = "\
+Usage:
+  shc-atlas pack <MANIFEST|DIR> -o <OUT.sfa> [--index FILE] [--uncompressed] [--tile-size N] [--page-tiles N] [--no-mips] [--limit-levels N] [--limits L] [--fit] [--compression C] [--format F] [--border N] [--pad] [--dedup]
+  shc-atlas inspect <ATLAS.sfa>
+  shc-atlas unpack <ATLAS.sfa> -o <DIR>
+  shc-atlas diff <OLD.sfa> <NEW.sfa>
+
+pack     Builds an atlas from a `.atlas.ron` manifest or a directory of images,
+         each sub-directory is a group and each image a tile named by its file stem.
+         Images in the root of the directory are added to the `base` group.
+         The index manifest given by `--index`, or the manifest's `indices`, is pinned
+         when it exists and then updated with the index of every tile.
+         `--limits` checks the atlas against `default` or `downlevel` wgpu limits, or
+         `<SIZE>x<LAYERS>`, and `--fit` changes the atlas to fit them instead of failing.
+         Tiles that don't fit in a single atlas are split into overflow atlases written
+         next to the output as `<OUT>.1.sfa`, `<OUT>.2.sfa`, ... each with its own index.
+         `--compression bc7` encodes the texture ahead of time, `none` stores it uncompressed.
+         `--format` stores tiles as `rgba8-srgb` colour, `rgba8` linear data, `r8` masks
+         or `rgba16f` HDR colour, images are converted to it when packed.
+         `--border` surrounds every tile with N extruded edge pixels, a power of two.
+         `--pad` pads tiles up to the next power of two, so they keep a full mip chain.
+         `--dedup` stores identical frames once, tiles repeating them share their slots.
+inspect  Prints the groups, tiles, sequence lengths, mip completeness and page usage.
+unpack   Writes every level of every frame of every tile to `<DIR>/<group>/<tile>/l<level>_f<frame>.png`,
+         `rgba16f` tiles are clipped to sRGB.
+diff     Reports the tiles added, removed or changed, exits with 1 if the atlases differ.";
+
+const DIRECTORY_BASE_GROUP: &str = "base";
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("{0}\n\n{USAGE}")]
+    Usage(String),
+
+    #[error("{path}: {source}")]
+    Io {
+        path:   PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("{path}: {source}")]
+    Image {
+        path:   PathBuf,
+        source: image::ImageError,
+    },
+
+    #[error("{path}: {source}")]
+    Read {
+        path:   PathBuf,
+        source: TileAtlasBuilderReadError,
+    },
+
+    #[error("{path}: {source}")]
+    Parse {
+        path:   PathBuf,
+        source: ron::error::SpannedError,
+    },
+
+    #[error("{path}: can't be converted to {format:?}")]
+    ImageFormat {
+        path:   PathBuf,
+        format: TileAtlasPixelFormat,
+    },
+
+    #[error(transparent)]
+    Manifest(#[from] TileAtlasManifestError),
+
+    #[error(transparent)]
+    Limits(#[from] TileAtlasLimitsError),
+
+    #[error(transparent)]
+    Compression(#[from] TileAtlasCompressionError),
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.first().map(String::as_str) {
+        Some("pack")    => pack(&args[1..]),
+        Some("inspect") => inspect(&args[1..]),
+        Some("unpack")  => unpack(&args[1..]),
+        Some("diff")    => diff(&args[1..]),
+        Some("help" | "-h" | "--help") => { println!("{USAGE}"); Ok(ExitCode::SUCCESS) },
+        Some(command)   => Err(CliError::Usage(format!("unknown command `{command}`"))),
+        None            => Err(CliError::Usage("missing command".to_owned())),
+    };
+
+    result.unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        ExitCode::from(2)
+    })
+}
+
+// // Commands // //
+
+fn pack(args: &[String]) -> Result<ExitCode, CliError> {
+    let mut args = Args::new(args);
+    let mut input  = None;
+    let mut output = None;
+    let mut index  = None;
+    let mut codec  = TileAtlasFileCodec::Brotli;
+    let mut tile_size    = None;
+    let mut page_tiles   = None;
+    let mut limit_levels = None;
+    let mut limits       = None;
+    let mut fit_limits   = false;
+    let mut compression  = None;
+    let mut format       = None;
+    let mut border       = None;
+    let mut padded       = false;
+    let mut dedup        = false;
+    let mut generate_mips = true;
+
+    while let Some(arg) = args.next() {
+        match arg {
+            "-o" | "--output"  => output = Some(PathBuf::from(args.value(arg)?)),
+            "--index"          => index = Some(PathBuf::from(args.value(arg)?)),
+            "--uncompressed"   => codec = TileAtlasFileCodec::None,
+            "--tile-size"      => tile_size = Some(args.parse(arg)?),
+            "--page-tiles"     => page_tiles = Some(args.parse(arg)?),
+            "--limit-levels"   => limit_levels = Some(args.parse(arg)?),
+            "--no-mips"        => generate_mips = false,
+            "--limits"         => limits = Some(parse_limits(args.value(arg)?)?),
+            "--fit"            => fit_limits = true,
+            "--compression"    => compression = Some(parse_compression(args.value(arg)?)?),
+            "--format"         => format = Some(parse_format(args.value(arg)?)?),
+            "--border"         => border = Some(args.parse(arg)?),
+            "--pad"            => padded = true,
+            "--dedup"          => dedup = true,
+            _ => input = Some(PathBuf::from(positional(arg, input.is_none())?)),
+        }
+    }
+
+    let input  = input.ok_or_else(|| CliError::Usage("missing manifest or directory".to_owned()))?;
+    let output = output.ok_or_else(|| CliError::Usage("missing output path".to_owned()))?;
+
+    let (mut manifest, root) = if input.is_dir() {
+        (manifest_from_directory(&input)?, input)
+    } else {
+        let bytes = std::fs::read(&input).map_err(|source| CliError::Io{ path: input.clone(), source })?;
+        let manifest = TileAtlasManifest::from_ron(&bytes).map_err(|source| CliError::Parse{ path: input.clone(), source })?;
+        (manifest, input.parent().map(Path::to_path_buf).unwrap_or_default())
+    };
+
+    if tile_size.is_some()    { manifest.tile_size = tile_size; }
+    if page_tiles.is_some()   { manifest.page_tiles = page_tiles; }
+    if limit_levels.is_some() { manifest.limit_levels = limit_levels; }
+    if !generate_mips         { manifest.mips.generate = false; }
+    if let Some(compression) = compression { manifest.compression = compression; }
+    if let Some(format) = format { manifest.format = format; }
+    if let Some(border) = border { manifest.border = border; }
+    if padded                 { manifest.padded = true; }
+    if dedup                  { manifest.dedup = true; }
+
+    let mut images = HashMap::new();
+    for source in manifest.sources() {
+        let path = root.join(source);
+        images.insert(source, load_image(&path, manifest.format)?);
+    }
+
+    let index = index.or_else(|| manifest.indices.as_ref().map(|p| root.join(p)));
+    let mut indices = TileAtlasIndexManifest::default();
+    if let Some(index) = &index {
+        // Overflow atlases have their own index manifests, as their indices overlap
+        let paths = (1..).map(|i| TileAtlasBuilder::overflow_path(index, i));
+        for path in core::iter::once(index.clone()).chain(paths).take_while(|p| p.exists()) {
+            let bytes = std::fs::read(&path).map_err(|source| CliError::Io{ path: path.clone(), source })?;
+            let part  = TileAtlasIndexManifest::from_ron(&bytes).map_err(|source| CliError::Parse{ path: path.clone(), source })?;
+            for (group_id, tile_id, i) in part.iter() {
+                indices.insert(group_id, tile_id, i);
+            }
+        }
+    }
+
+    let builder = manifest.build_with_indices(&indices, |path| images.get(path).map(|(data, size)| (data.as_slice(), *size)))?;
+    let parts = match (limits, fit_limits) {
+        (Some(limits), fit) => builder.split_limits(&limits, fit)?,
+        (None, true)  => return Err(CliError::Usage("`--fit` expects `--limits`".to_owned())),
+        (None, false) => builder.split(TILE_ATLAS_SLOT_COUNT)?,
+    };
+
+    for (i, mut part) in parts.into_iter().enumerate() {
+        // Splitting or fitting changes the tiles, so the image is encoded again
+        if !part.is_compressed_current() {
+            part.compress(manifest.compression)?;
+        }
+
+        let output = if i == 0 { output.clone() } else { TileAtlasBuilder::overflow_path(&output, i as u32) };
+        let layout = part.build_layout();
+        for (group_id, tile_id, pin) in layout.conflicts() {
+            eprintln!("warning: {group_id}/{tile_id} can't be placed at its pinned index {pin}, moved to {}", layout.get(group_id, tile_id).unwrap().index);
+        }
+
+        let file = File::create(&output).map_err(|source| CliError::Io{ path: output.clone(), source })?;
+        part.write_to(BufWriter::new(file), codec).map_err(|source| CliError::Io{ path: output.clone(), source })?;
+
+        if let Some(index) = &index {
+            let path = if i == 0 { index.clone() } else { TileAtlasBuilder::overflow_path(index, i as u32) };
+            let ron = layout.to_index_manifest().to_ron().map_err(|source| CliError::Io{ path: path.clone(), source: std::io::Error::other(source) })?;
+            std::fs::write(&path, ron).map_err(|source| CliError::Io{ path: path.clone(), source })?;
+        }
+
+        let shared = match layout.shared_slot_count() {
+            0     => String::new(),
+            count => format!(" ({count} shared)"),
+        };
+        println!("packed {} images{shared} in {} pages into {}", part.image_count(), layout.page_count(), output.display());
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn inspect(args: &[String]) -> Result<ExitCode, CliError> {
+    let path = Args::new(args).single_positional("atlas")?;
+    let bytes = std::fs::read(&path).map_err(|source| CliError::Io{ path: path.clone(), source })?;
+    let header = TileAtlasFileHeader::from_bytes(&bytes).map_err(|source| CliError::Read{ path: path.clone(), source })?;
+    let builder = TileAtlasBuilder::read_from_bytes(&bytes).map_err(|source| CliError::Read{ path: path.clone(), source })?;
+
+    let tiles  = sorted_tiles(&builder);
+    let layout = builder.try_build_layout()?;
+    let level_count = builder.mip_levels_max();
+    let image_count = builder.image_count();
+    let page_count  = layout.page_count();
+    let page_tiles  = builder.page_tiles();
+    let page_slots  = layout.page_slots();
+    let last_page   = match layout.slot_count() % page_slots { 0 if page_count > 0 => page_slots, v => v };
+
+    println!("file:       {}", path.display());
+    match header {
+        Some(header) => println!("version:    {} ({:?}, {} bytes)", header.version, header.codec, header.payload_len),
+        None         => println!("version:    0 (legacy)"),
+    }
+    if builder.tile_size() == builder.slot_size() {
+        println!("tile size:  {}", builder.tile_size());
+    } else {
+        println!("tile size:  {} ({}px slots)", builder.tile_size(), builder.slot_size());
+    }
+    println!("levels:     {level_count} (common max {}, complete: {})", builder.find_mip_level_common_max(), yes_no(builder.mip_levels_complete()));
+    println!("tiles:      {} in {} groups, {image_count} images", tiles.len(), builder.groups().count());
+    println!("format:     {:?}", builder.format());
+    if builder.border() > 0 {
+        println!("border:     {}px", builder.border());
+    }
+    println!("pages:      {page_count} of {page_tiles}x{page_tiles} tiles, {}px ({} slots spanned, last page {last_page}/{page_slots} used)", builder.page_size(), layout.slot_count());
+    if let Some(compressed) = builder.compressed() {
+        let outdated = if builder.is_compressed_current() { "" } else { " (outdated)" };
+        println!("texture:    {:?}, {} levels, {} bytes{outdated}", compressed.compression, compressed.level_count, compressed.data.len());
+    }
+    if builder.overflow_count() > 0 {
+        println!("overflow:   {} ({} to {})", builder.overflow_count(), TileAtlasBuilder::overflow_path(&path, 1).display(), TileAtlasBuilder::overflow_path(&path, builder.overflow_count()).display());
+    }
+    if builder.dedup() {
+        println!("dedup:      {} tiles share slots, saving {} slots", layout.shared().len(), layout.shared_slot_count());
+    }
+    for (group_id, tile_id, pin) in layout.conflicts() {
+        println!("conflict:   {group_id}/{tile_id} is pinned to {pin} but overlaps another tile");
+    }
+
+    let mut group = None;
+    for (group_id, tile_id, set) in tiles {
+        if group != Some(group_id) {
+            println!("\ngroup \"{group_id}\"");
+            group = Some(group_id);
+        }
+
+        let frames = set.levels[0].data.len();
+        let levels = set.levels.iter().map(|l| match l.data.len() {
+            0                  => '-',
+            v if v == frames   => '#',
+            _                  => '!',
+        }).collect::<String>();
+        let index = layout.get(group_id, tile_id).map_or(String::new(), |e| e.index.to_string());
+        let pin    = if builder.pins().get(group_id, tile_id).is_some() { "pinned" } else { "" };
+        let shared = if layout.shared().iter().any(|e| e.group_id == group_id && e.tile_id == tile_id) { "shared" } else { "" };
+        let notes  = [pin, shared].into_iter().filter(|note| !note.is_empty()).collect::<Vec<_>>().join(" ");
+        println!("  {tile_id:<24} index {index:>5}  frames {frames:>4}  levels [{levels}]  {notes}");
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn unpack(args: &[String]) -> Result<ExitCode, CliError> {
+    let mut args = Args::new(args);
+    let mut input  = None;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg {
+            "-o" | "--output" => output = Some(PathBuf::from(args.value(arg)?)),
+            _ => input = Some(PathBuf::from(positional(arg, input.is_none())?)),
+        }
+    }
+
+    let input  = input.ok_or_else(|| CliError::Usage("missing atlas".to_owned()))?;
+    let output = output.ok_or_else(|| CliError::Usage("missing output directory".to_owned()))?;
+    let builder = read_atlas(&input)?;
+    let format  = builder.format();
+    let pixel_len = format.pixel_len() as usize;
+    let color   = match format {
+        TileAtlasPixelFormat::R8Unorm => ExtendedColorType::L8,
+        _ => ExtendedColorType::Rgba8,
+    };
+
+    let mut count = 0;
+    for (group_id, tile_id, set) in sorted_tiles(&builder) {
+        let dir = output.join(group_id).join(tile_id);
+        std::fs::create_dir_all(&dir).map_err(|source| CliError::Io{ path: dir.clone(), source })?;
+
+        for (level, sequence) in set.levels.iter().enumerate() {
+            let size    = builder.mip_level_size(level as u32) as usize;
+            let content = builder.tile_level_size(level as u32);
+            let border  = builder.tile_level_border(level as u32) as usize;
+            for (frame, data) in sequence.data.iter().enumerate() {
+                // Padding and borders are dropped, so unpacked tiles can be packed again
+                let data = data.chunks_exact(size*pixel_len).skip(border).take(content as usize).flat_map(|row| &row[border*pixel_len..(border + content as usize)*pixel_len]).copied().collect::<Vec<_>>();
+                let data = if format.is_float() {
+                    TileAtlasPixelFormat::Rgba8Srgb.convert(&data, format.texture_format()).unwrap().into_owned()
+                } else {
+                    data
+                };
+                let path = dir.join(format!("l{level}_f{frame}.png"));
+                image::save_buffer_with_format(&path, &data, content, content, color, ImageFormat::Png)
+                    .map_err(|source| CliError::Image{ path: path.clone(), source })?;
+                count += 1;
+            }
+        }
+    }
+
+    println!("unpacked {count} images into {}", output.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn diff(args: &[String]) -> Result<ExitCode, CliError> {
+    let mut args = Args::new(args);
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        paths.push(PathBuf::from(positional(arg, paths.len() < 2)?));
+    }
+
+    let [old, new] = paths.as_slice() else {
+        return Err(CliError::Usage("expected two atlases".to_owned()));
+    };
+
+    let diff = read_atlas(old)?.diff(&read_atlas(new)?);
+    if let Some((old_size, new_size)) = diff.size_changed {
+        println!("tile size changed from {old_size} to {new_size}");
+    }
+    for (group_id, tile_id) in &diff.added   { println!("+ {group_id}/{tile_id}"); }
+    for (group_id, tile_id) in &diff.removed { println!("- {group_id}/{tile_id}"); }
+    for (group_id, tile_id) in &diff.changed { println!("~ {group_id}/{tile_id}"); }
+
+    if diff.is_empty() {
+        println!("atlases are identical");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("{} added, {} removed, {} changed", diff.added.len(), diff.removed.len(), diff.changed.len());
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+// // Helpers // //
+
+fn read_atlas(path: &Path) -> Result<TileAtlasBuilder, CliError> {
+    let file = File::open(path).map_err(|source| CliError::Io{ path: path.to_path_buf(), source })?;
+    TileAtlasBuilder::read_from(BufReader::new(file)).map_err(|source| CliError::Read{ path: path.to_path_buf(), source })
+}
+
+/**
+ * Loads an image converted to the pixel `format`, float formats keep the precision of 16-bit sources.
+ */
+fn load_image(path: &Path, format: TileAtlasPixelFormat) -> Result<(Vec<u8>, [u32; 2]), CliError> {
+    let image = image::open(path).map_err(|source| CliError::Image{ path: path.to_path_buf(), source })?;
+    let size  = [image.width(), image.height()];
+    let data  = if format.is_float() {
+        let bytes = image.to_rgba32f().into_raw().into_iter().flat_map(f32::to_le_bytes).collect::<Vec<_>>();
+        format.convert(&bytes, TextureFormat::Rgba32Float).map(Cow::into_owned)
+    } else {
+        format.convert(image.to_rgba8().as_raw(), TextureFormat::Rgba8UnormSrgb).map(Cow::into_owned)
+    };
+    let data = data.ok_or_else(|| CliError::ImageFormat{ path: path.to_path_buf(), format })?;
+    Ok((data, size))
+}
+
+fn sorted_tiles(builder: &TileAtlasBuilder) -> Vec<(&str, &str, &TileAtlasBuilderSet)> {
+    let mut tiles = builder.tiles().collect::<Vec<_>>();
+    tiles.sort_unstable_by_key(|&(group_id, tile_id, _)| (group_id, tile_id));
+    tiles
+}
+
+/**
+ * Creates a manifest from a directory, sub-directories become groups and images become tiles.
+ */
+fn manifest_from_directory(root: &Path) -> Result<TileAtlasManifest, CliError> {
+    let mut groups = BTreeMap::<String, BTreeMap<String, TileAtlasManifestTile>>::new();
+
+    let mut add_images = |dir: &Path, group_id: &str, prefix: &str| -> Result<(), CliError> {
+        for entry in read_dir_sorted(dir)? {
+            if !entry.is_file() || !is_image(&entry) { continue; }
+            let Some(tile_id) = entry.file_stem().and_then(|s| s.to_str()) else { continue; };
+            let Some(file_name) = entry.file_name().and_then(|s| s.to_str()) else { continue; };
+            groups.entry(group_id.to_owned()).or_default().insert(tile_id.to_owned(), TileAtlasManifestTile {
+                path: Some(format!("{prefix}{file_name}")),
+                ..TileAtlasManifestTile::default()
+            });
+        }
+        Ok(())
+    };
+
+    add_images(root, DIRECTORY_BASE_GROUP, "")?;
+    for entry in read_dir_sorted(root)? {
+        if !entry.is_dir() { continue; }
+        let Some(group_id) = entry.file_name().and_then(|s| s.to_str()) else { continue; };
+        add_images(&entry, group_id, &format!("{group_id}/"))?;
+    }
+
+    Ok(TileAtlasManifest {
+        groups,
+        ..TileAtlasManifest::default()
+    })
+}
+
+fn read_dir_sorted(dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let entries = std::fs::read_dir(dir).and_then(|d| d.map(|e| e.map(|e| e.path())).collect::<Result<Vec<_>, _>>());
+    let mut entries = entries.map_err(|source| CliError::Io{ path: dir.to_path_buf(), source })?;
+    entries.sort_unstable();
+    Ok(entries)
+}
+
+fn is_image(path: &Path) -> bool {
+    let is_manifest = path.to_str().is_some_and(|p| p.ends_with(TILE_ATLAS_MANIFEST_EXTENSION));
+    !is_manifest && ImageFormat::from_path(path).is_ok_and(|format| format.reading_enabled())
+}
+
+const fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+/**
+ * Parses `none` or `bc7` as a texture compression.
+ */
+fn parse_compression(value: &str) -> Result<TileAtlasCompression, CliError> {
+    match value {
+        "none" => Ok(TileAtlasCompression::None),
+        "bc7"  => Ok(TileAtlasCompression::Bc7),
+        _ => Err(CliError::Usage(format!("invalid value `{value}` for `--compression`"))),
+    }
+}
+
+/**
+ * Parses `rgba8-srgb`, `rgba8`, `r8` or `rgba16f` as a pixel format.
+ */
+fn parse_format(value: &str) -> Result<TileAtlasPixelFormat, CliError> {
+    match value {
+        "rgba8-srgb" => Ok(TileAtlasPixelFormat::Rgba8Srgb),
+        "rgba8"      => Ok(TileAtlasPixelFormat::Rgba8Unorm),
+        "r8"         => Ok(TileAtlasPixelFormat::R8Unorm),
+        "rgba16f"    => Ok(TileAtlasPixelFormat::Rgba16Float),
+        _ => Err(CliError::Usage(format!("invalid value `{value}` for `--format`"))),
+    }
+}
+
+/**
+ * Parses `default`, `downlevel` or `<SIZE>x<LAYERS>` as texture limits.
+ */
+fn parse_limits(value: &str) -> Result<TileAtlasLimits, CliError> {
+    match value {
+        "default"   => Ok(TileAtlasLimits::default()),
+        "downlevel" => Ok(TileAtlasLimits::downlevel_defaults()),
+        _ => value.split_once('x')
+            .and_then(|(size, layers)| Some(TileAtlasLimits {
+                max_texture_dimension_2d: size.parse().ok()?,
+                max_texture_array_layers: layers.parse().ok()?,
+            }))
+            .ok_or_else(|| CliError::Usage(format!("invalid value `{value}` for `--limits`"))),
+    }
+}
+
+/**
+ * Accepts `arg` as a positional argument if it isn't an option and `accept` is set.
+ */
+fn positional(arg: &str, accept: bool) -> Result<&str, CliError> {
+    if arg.starts_with('-') {
+        Err(CliError::Usage(format!("unknown option `{arg}`")))
+    } else if accept {
+        Ok(arg)
+    } else {
+        Err(CliError::Usage(format!("unexpected argument `{arg}`")))
+    }
+}
+
+/**
+ * Minimal argument cursor, options take their value from the following argument.
+ */
+struct Args<'a> {
+    iter: core::slice::Iter<'a, String>,
+}
+
+impl<'a> Args<'a> {
+
+    fn new(args: &'a [String]) -> Self {
+        Self{ iter: args.iter() }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.iter.next().map(String::as_str)
+    }
+
+    fn value(&mut self, option: &str) -> Result<&'a str, CliError> {
+        self.next().ok_or_else(|| CliError::Usage(format!("`{option}` expects a value")))
+    }
+
+    fn parse<T: core::str::FromStr>(&mut self, option: &str) -> Result<T, CliError> {
+        let value = self.value(option)?;
+        value.parse().map_err(|_| CliError::Usage(format!("invalid value `{value}` for `{option}`")))
+    }
+
+    fn single_positional(mut self, name: &str) -> Result<PathBuf, CliError> {
+        let mut result = None;
+        while let Some(arg) = self.next() {
+            result = Some(PathBuf::from(positional(arg, result.is_none())?));
+        }
+        result.ok_or_else(|| CliError::Usage(format!("missing {name}")))
+    }
+
+}
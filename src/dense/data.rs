@@ -50,6 +50,14 @@ impl TileGridDenseData {
         self.data[idx] = value.into();
     }
 
+    /**
+     * Replaces every slot in the grid with the result of `f`.
+     */
+    pub fn map_slots(&mut self, mut f: impl FnMut(TileAtlasSlot) -> TileAtlasSlot) {
+        self.update_data_change_tick();
+        self.data.iter_mut().for_each(|v| *v = f(*v));
+    }
+
     #[must_use]
     pub fn get(&self, pos: UVec2) -> TileAtlasSlot {
         self.get_at(Self::calc_idx(self.size, pos))
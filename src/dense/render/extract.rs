@@ -5,7 +5,7 @@ use core::num::NonZeroU64;
 use bevy::{prelude::*, render::{render_asset::RenderAssets, settings::WgpuLimits, sync_world::RenderEntity, texture::GpuImage, Extract}};
 use bytemuck::{Pod, Zeroable};
 
-use crate::{atlas::TileAtlas, dense::{render::{PreparedTileGridDense, TileGridDenseBindGroups}, TileGridDenseData, TileGridDenseInfo}, shared::{RenderPass2d, TileGridAnimator}};
+use crate::{atlas::{builder::TILE_ATLAS_PAGE_TILES, TileAtlas}, dense::{render::{PreparedTileGridDense, TileGridDenseBindGroups}, TileGridDenseData, TileGridDenseInfo}, shared::{RenderPass2d, TileGridAnimator}};
 
 #[derive(Debug, Clone, Copy, Zeroable, PartialEq, Pod)]
 #[repr(C)]
@@ -17,6 +17,10 @@ pub struct TileGridDenseUniforms {
     pub depth:  f32,
     pub y_depth_scale: f32,
     pub frame_time: u32,
+    pub tile_uv_scale: f32,
+    pub atlas_page_tiles: u32,
+    pub tile_uv_offset: f32,
+    padding: u32,
 }
 
 impl TileGridDenseUniforms {
@@ -52,6 +56,8 @@ pub fn tile_grid_dense_extract_updates(
     q_extracted.iter().for_each(|(entity, grid_data, grid_info, animator, transform, visibility)| {
         if !visibility.get() { return; }
 
+        let atlas = grid_info.atlas().as_ref().and_then(|h| r_tex_atlases.get(h));
+
         let uniforms = TileGridDenseUniforms {
             origin:         transform.translation().xy() + grid_info.offset() * grid_info.scale(),
             depth:          transform.translation().z,
@@ -59,11 +65,14 @@ pub fn tile_grid_dense_extract_updates(
             scale:          grid_info.scale(),
             y_depth_scale:  grid_info.y_depth_scale(),
             frame_time:     animator.map_or(0, TileGridAnimator::frame_count),
+            tile_uv_scale:  atlas.map_or(1.0, TileAtlas::uv_scale),
+            atlas_page_tiles: atlas.map_or(TILE_ATLAS_PAGE_TILES, TileAtlas::page_tiles),
+            tile_uv_offset: atlas.map_or(0.0, TileAtlas::uv_offset),
+            padding: 0,
         };
 
         let render_pass = grid_info.render_pass();
-        let texture = grid_info.atlas().as_ref()
-            .and_then(|h| r_tex_atlases.get(h))
+        let texture = atlas
             .map(TileAtlas::image)
             .and_then(|texture| image_assets.get(texture).is_some().then_some(texture)) // TODO HACK how do we update when GPUImage updates
             .cloned();
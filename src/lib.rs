@@ -17,6 +17,7 @@ pub mod prelude {
     pub use super::atlas::TileAtlasSlot;
     pub use super::atlas::TileAtlasGroup;
     pub use super::atlas::TileAtlasEntry;
+    pub use super::atlas::TileAtlasLoaderSettings;
 
     pub use super::atlas::builder::TileAtlasBuilder;
     pub use super::atlas::builder::TileAtlasBuilderGroup;
@@ -27,8 +28,11 @@ pub mod prelude {
     pub use super::atlas::builder::TileAtlasBuilderImageSequence;
     pub use super::atlas::builder::TileAtlasBuildQueue;
     pub use super::atlas::builder::TileAtlasBuildQueueTarget;
+    pub use super::atlas::builder::TileAtlasBuildSettings;
 
     pub use super::atlas::builder::DownsampleBilinearSRGB;
+    pub use super::atlas::builder::DownsampleLanczos3;
+    pub use super::atlas::builder::DownsampleNearest;
 
     pub use super::shared::TileGridAnimator;
 
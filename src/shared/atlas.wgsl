@@ -2,27 +2,27 @@
 
 #define_import_path sf_tile_render::atlas
 
-const ATLAS_TILE_COUNT_XY_U: u32 = 16;
-const ATLAS_TILE_COUNT_XY_F: f32 = 16;
+// `page_tiles` is the number of tiles along each side of a page
 
-fn atlas_get_tile_size(t: texture_2d_array<f32>) -> f32 {
-    return f32(textureDimensions(t).x)/ATLAS_TILE_COUNT_XY_F;
+fn atlas_get_tile_size(t: texture_2d_array<f32>, page_tiles: u32) -> f32 {
+    return f32(textureDimensions(t).x)/f32(page_tiles);
 }
 
 fn atlas_get_uv_pixel_size(t: texture_2d_array<f32>, level: u32) -> f32 {
     return 1.0/f32(textureDimensions(t, level).x);
 }
 
-fn atlas_calculate_page_uv(slot: vec2<u32>, uv: vec2<f32>, uv_pixel: f32) -> vec2<f32> {
-    let scale  = 1.0/ATLAS_TILE_COUNT_XY_F;
+// `uv` is relative to the tile's content, `uv_scale` is the fraction of the slot it covers, `uv_offset` its offset from the slot's corner
+fn atlas_calculate_page_uv(slot: vec2<u32>, uv: vec2<f32>, uv_scale: f32, uv_offset: f32, uv_pixel: f32, page_tiles: u32) -> vec2<f32> {
+    let scale  = 1.0/f32(page_tiles);
     var uv_min = 0.5*uv_pixel;
-    var uv_max = 1.0/ATLAS_TILE_COUNT_XY_F - 0.5*uv_pixel;
-    var uv_scaled_clamped = clamp(uv * scale, vec2<f32>(uv_min, uv_min), vec2<f32>(uv_max, uv_max));
-    return uv_scaled_clamped + vec2<f32>(slot)*scale;
+    var uv_max = max(uv_scale*scale - 0.5*uv_pixel, uv_min);
+    var uv_scaled_clamped = clamp(uv * uv_scale * scale, vec2<f32>(uv_min, uv_min), vec2<f32>(uv_max, uv_max));
+    return uv_scaled_clamped + (vec2<f32>(slot) + uv_offset)*scale;
 }
 
-fn atlas_get_mipmap_level(t: texture_2d_array<f32>, uv: vec2<f32>) -> f32 {
-    var tile_size = atlas_get_tile_size(t);
+fn atlas_get_mipmap_level(t: texture_2d_array<f32>, uv: vec2<f32>, page_tiles: u32) -> f32 {
+    var tile_size = atlas_get_tile_size(t, page_tiles);
     var max_level = f32(textureNumLevels(t) - 1);
 
     var dx_vtc = dpdxFine(uv*tile_size);
@@ -40,13 +40,29 @@ fn atlas_sample_texture(
     s: sampler, 
     slot: u32, 
     uv: vec2<f32>,
+    uv_scale: f32,
+    page_tiles: u32,
 ) -> vec4<f32> {
+    // Limit the mipmap level to the texture's max level
+    let level = atlas_get_mipmap_level(t, uv * uv_scale, page_tiles);
+    return atlas_sample_texture_level(t, s, slot, uv, uv_scale, 0.0, level, page_tiles);
+}
 
-    let slot_xy   = vec2<u32>(slot & 0x0F, (slot >> 4) & 0x0F);
-    let slot_page = (slot >> 8) & 0xFF;
+fn atlas_sample_texture_level(
+    t: texture_2d_array<f32>, 
+    s: sampler, 
+    slot: u32, 
+    uv: vec2<f32>,
+    uv_scale: f32,
+    uv_offset: f32,
+    level: f32,
+    page_tiles: u32,
+) -> vec4<f32> {
 
-    // Limit the mipmap level to the texture's max level
-    var level = atlas_get_mipmap_level(t, uv);
+    let page_slots = page_tiles*page_tiles;
+    let slot_local = slot % page_slots;
+    let slot_xy    = vec2<u32>(slot_local % page_tiles, slot_local / page_tiles);
+    let slot_page  = slot / page_slots;
 
     // We sample the lower and upper mipmap levels seperately and interpolate ourselves
     //   this is required to prevent texture bleed as the half-texel offset is per-mip-level
@@ -54,11 +70,46 @@ fn atlas_sample_texture(
     var level_high = ceil(level);
     var level_mix  = level - f32(level_low);
 
-    var page_uv_low  = atlas_calculate_page_uv(slot_xy, uv, atlas_get_uv_pixel_size(t, u32(level_low )));
-    var page_uv_high = atlas_calculate_page_uv(slot_xy, uv, atlas_get_uv_pixel_size(t, u32(level_high)));
+    var page_uv_low  = atlas_calculate_page_uv(slot_xy, uv, uv_scale, uv_offset, atlas_get_uv_pixel_size(t, u32(level_low )), page_tiles);
+    var page_uv_high = atlas_calculate_page_uv(slot_xy, uv, uv_scale, uv_offset, atlas_get_uv_pixel_size(t, u32(level_high)), page_tiles);
 
     var sample_low  = textureSampleLevel(t, s, page_uv_low,  slot_page, level_low );
     var sample_high = textureSampleLevel(t, s, page_uv_high, slot_page, level_high);
 
     return mix(sample_low, sample_high, level_mix);
+}
+
+// Samples atlases whose tiles are surrounded by extruded borders, `uv_offset` is the border's fraction of the slot
+//   the border absorbs the filter footprint, so a single trilinear or anisotropic sample doesn't bleed
+//   the border halves with each level, levels where it's under a pixel are sampled clamped instead
+fn atlas_sample_texture_bordered(
+    t: texture_2d_array<f32>, 
+    s: sampler, 
+    slot: u32, 
+    uv: vec2<f32>,
+    uv_scale: f32,
+    uv_offset: f32,
+    page_tiles: u32,
+) -> vec4<f32> {
+
+    let page_slots = page_tiles*page_tiles;
+    let slot_local = slot % page_slots;
+    let slot_xy    = vec2<u32>(slot_local % page_tiles, slot_local / page_tiles);
+    let slot_page  = slot / page_slots;
+    let scale      = 1.0/f32(page_tiles);
+
+    // Gradients are taken before clamping and without the slot, so they stay continuous across tiles
+    let uv_local = uv * uv_scale * scale;
+    let ddx      = dpdx(uv_local);
+    let ddy      = dpdy(uv_local);
+
+    let border        = round(uv_offset * atlas_get_tile_size(t, page_tiles));
+    let border_levels = floor(log2(max(border, 1.0))) + 1.0;
+    let level         = atlas_get_mipmap_level(t, uv * uv_scale, page_tiles);
+    if ceil(level) >= border_levels {
+        return atlas_sample_texture_level(t, s, slot, uv, uv_scale, uv_offset, level, page_tiles);
+    }
+
+    let page_uv = clamp(uv, vec2<f32>(0.0), vec2<f32>(1.0)) * uv_scale * scale + (vec2<f32>(slot_xy) + uv_offset)*scale;
+    return textureSampleGrad(t, s, page_uv, slot_page, ddx, ddy);
 }
\ No newline at end of file
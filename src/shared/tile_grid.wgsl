@@ -3,7 +3,7 @@
 #define_import_path sf_tile_render::tile_grid
 
 #import bevy_render::view::View
-#import sf_tile_render::atlas::atlas_sample_texture
+#import sf_tile_render::atlas::{atlas_sample_texture, atlas_sample_texture_bordered}
 
 // // Passes // //
 
@@ -56,10 +56,19 @@ fn tile_grid_fragment(
     t: texture_2d_array<f32>,
     s: sampler,
     slot: u32,
-    uv: vec2<f32>
+    uv: vec2<f32>,
+    uv_scale: f32,
+    uv_offset: f32,
+    page_tiles: u32,
 ) -> vec4<f32> {
+    var sample: vec4<f32>;
+    if uv_offset > 0.0 {
+        sample = atlas_sample_texture_bordered(t, s, slot - 1, uv, uv_scale, uv_offset, page_tiles);
+    } else {
+        sample = atlas_sample_texture(t, s, slot - 1, uv, uv_scale, page_tiles);
+    }
     return select(
-        atlas_sample_texture(t, s, slot - 1, uv), 
+        sample, 
         vec4(0.0, 0.0, 0.0, 0.0), 
         slot == 0
     );
@@ -135,6 +135,14 @@ impl TileGridSparse {
         self.data.retain(|v| f(v.position(), v.texture()));
     }
 
+    /**
+     * Replaces the slot of every tile in the grid with the result of `f`.
+     */
+    pub fn map_slots(&mut self, mut f: impl FnMut(TileAtlasSlot) -> TileAtlasSlot) {
+        self.update_data_change_tick();
+        self.data.iter_mut().for_each(|v| *v = TileGridSparseValue::new(v.position(), f(v.texture())));
+    }
+
     pub fn clear(&mut self) {
         self.update_data_change_tick();
         self.data.clear();
@@ -3,7 +3,7 @@
 use bevy::{prelude::*, render::{render_asset::RenderAssets, settings::WgpuLimits, sync_world::RenderEntity, texture::GpuImage, Extract}};
 use bytemuck::{Pod, Zeroable};
 
-use crate::{atlas::TileAtlas, shared::{RenderPass2d, TileGridAnimator}, sparse::{render::{PreparedTileGridSparse, TileGridSparseBindGroups}, TileGridSparse}};
+use crate::{atlas::{builder::TILE_ATLAS_PAGE_TILES, TileAtlas}, shared::{RenderPass2d, TileGridAnimator}, sparse::{render::{PreparedTileGridSparse, TileGridSparseBindGroups}, TileGridSparse}};
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
@@ -14,6 +14,10 @@ pub struct TileGridSparseUniforms {
     pub depth:  f32,
     pub y_depth_scale: f32,
     pub frame_time: u32,
+    pub tile_uv_scale: f32,
+    pub atlas_page_tiles: u32,
+    pub tile_uv_offset: f32,
+    padding: u32,
 }
 
 impl PartialEq for TileGridSparseUniforms {
@@ -23,7 +27,10 @@ impl PartialEq for TileGridSparseUniforms {
         self.scale          == other.scale && 
         self.depth          == other.depth &&
         self.y_depth_scale  == other.y_depth_scale &&
-        self.frame_time == other.frame_time
+        self.frame_time == other.frame_time &&
+        self.tile_uv_scale == other.tile_uv_scale &&
+        self.atlas_page_tiles == other.atlas_page_tiles &&
+        self.tile_uv_offset == other.tile_uv_offset
     }
 }
 
@@ -60,17 +67,22 @@ pub fn tile_grid_sparse_extract_updates(
     q_extracted.iter().for_each(|(entity, tile_grid_sparse, animator, transform, visibility)| {
         if !visibility.get() { return; }
 
+        let atlas = tile_grid_sparse.atlas().as_ref().and_then(|h| r_tex_atlases.get(h));
+
         let uniforms = TileGridSparseUniforms {
             origin: transform.translation().xy() + tile_grid_sparse.offset() * tile_grid_sparse.scale(),
             depth:  transform.translation().z,
             scale:  tile_grid_sparse.scale(),
             y_depth_scale: tile_grid_sparse.y_depth_scale(),
             frame_time: animator.map_or(0, TileGridAnimator::frame_count),
+            tile_uv_scale: atlas.map_or(1.0, TileAtlas::uv_scale),
+            atlas_page_tiles: atlas.map_or(TILE_ATLAS_PAGE_TILES, TileAtlas::page_tiles),
+            tile_uv_offset: atlas.map_or(0.0, TileAtlas::uv_offset),
+            padding: 0,
         };
 
         let render_pass = tile_grid_sparse.render_pass();
-        let texture = tile_grid_sparse.atlas().as_ref()
-            .and_then(|h| r_tex_atlases.get(h))
+        let texture = atlas
             .map(TileAtlas::image)
             .and_then(|texture| image_assets.get(texture).is_some().then_some(texture)) // TODO HACK how do we update when GPUImage updates
             .cloned();
@@ -23,6 +23,9 @@ struct TileGridSparseUniforms {
     depth:  f32,
     y_depth_scale: f32,
     frame_time: u32,
+    tile_uv_scale: f32,
+    atlas_page_tiles: u32,
+    tile_uv_offset: f32,
 }
 
 struct VertexOutput {
@@ -74,5 +77,5 @@ fn vertex(in: VertexInput) -> VertexOutput {
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return tile_grid_fragment(atlas_textures, atlas_sampler, in.slot, in.uv);
+    return tile_grid_fragment(atlas_textures, atlas_sampler, in.slot, in.uv, tile_grid_uniforms.tile_uv_scale, tile_grid_uniforms.tile_uv_offset, tile_grid_uniforms.atlas_page_tiles);
 }